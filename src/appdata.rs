@@ -0,0 +1,30 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{de::DeserializeOwned, Serialize};
+
+// Everything Thymeline keeps between runs lives in %APPDATA%\Thymeline
+pub fn data_path(name: &str) -> Option<PathBuf> {
+    let appdata = std::env::var_os("APPDATA")?;
+    Some(PathBuf::from(appdata).join("Thymeline").join(name))
+}
+
+// None when the file is missing, or unreadable, which is also logged
+pub fn load_json<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = data_path(name)?;
+    let text = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&text)
+        .map_err(|e| println!("Warning: Ignoring unreadable {}: {}", path.display(), e))
+        .ok()
+}
+
+// Pretty-printed, so the files can be read and edited by hand. Failures are only logged.
+pub fn store_json<T: Serialize + ?Sized>(name: &str, value: &T) {
+    let Some(path) = data_path(name) else {
+        return;
+    };
+    let written = path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, serde_json::to_string_pretty(value).unwrap_or_default()));
+    if let Err(e) = written {
+        println!("Warning: Failed to save {}: {}", path.display(), e);
+    }
+}
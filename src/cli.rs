@@ -0,0 +1,526 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::compat::Quirk;
+use crate::hotcorner::Corner;
+use crate::priority::InputPriority;
+use crate::timetrack::TimeTrackingOptions;
+use crate::TilerCommand;
+
+// One-shot actions that run instead of the tiler
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subcommand {
+    PwshModule(Option<PathBuf>),   // Generate the PowerShell companion module
+    Quit,                          // Ask the running instance to shut down
+    State,                         // Print the running instance's layout
+    Command {                      // Run one command in the running instance
+        name: String,
+        hwnd: Option<isize>,
+    },
+    Start(Vec<String>),            // Launch a resident instance with these arguments, detached
+    Restart(Vec<String>),          // Replace the running instance, reusing its arguments if none are given
+    SaveMacro(Option<String>),     // Name the running instance's last recorded macro; None is an error
+    PlayMacro(Option<String>),     // Replay a named macro, or the last recording
+    LinkMacro(String),             // Replay a named macro from a link, skipping all but its navigation steps
+    Stats,                         // Print the running instance's usage statistics
+    ImportLayout(PathBuf),         // Apply a layout file, like one copy-layout wrote
+    Template {                     // Apply or print a built-in layout; list them without a name
+        name: Option<String>,
+        row: i32,                  // Row the template's first row goes in
+        print: bool,
+    },
+    Uri(String),                   // Run what a thymeline: link asks for
+    RegisterUri(bool),             // Make this binary open thymeline: links, or stop it with false
+}
+
+// Which tile takes focus when a scroll lands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollFocus {
+    Keep,       // Leave focus alone unless the focused tile scrolled out of view
+    Center,     // Tile nearest the screen centre
+    Leftmost,   // Leftmost tile on screen
+    None,       // Never move focus
+}
+
+impl ScrollFocus {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "keep" => Some(Self::Keep),
+            "center" => Some(Self::Center),
+            "leftmost" => Some(Self::Leftmost),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+// How the animation thread spaces its ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePacing {
+    Fixed,        // Sleep one frame after each tick; slow frames push later ones back
+    Compensated,  // Tick on a fixed schedule, shortening the wait after a slow frame and skipping missed ones
+}
+
+impl FramePacing {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fixed" => Some(Self::Fixed),
+            "compensated" => Some(Self::Compensated),
+            _ => None,
+        }
+    }
+}
+
+// What adopting a window does when the current row is at layout.row_limit / max_row_width
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LimitPolicy {
+    Spill,          // Use the next row down with room
+    Refuse,         // Leave the window untiled
+    ReplaceOldest,  // Release the row's longest-tiled window to make room
+}
+
+impl LimitPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "spill" => Some(Self::Spill),
+            "refuse" => Some(Self::Refuse),
+            "replace-oldest" => Some(Self::ReplaceOldest),
+            _ => None,
+        }
+    }
+}
+
+// Options parsed from the command line
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub subcommand: Option<Subcommand>,
+    pub time_tracking: Option<TimeTrackingOptions>,
+    pub idle_minutes: u64,         // 0 disables idle suspension
+    pub ipc: bool,                 // Serve the JSON pipe protocol
+    pub throw_focus_follows: bool, // Focus moves with a window sent to another monitor
+    pub viewport_splits: Vec<f32>, // Ultrawide viewport boundaries as fractions of screen width
+    pub scroll_focus: ScrollFocus,
+    pub row_focus_memory: bool,    // Switching rows returns to that row's last focused tile
+    pub startup_layout: Option<PathBuf>, // Apps to launch and place when the tiler starts
+    pub hot_corners: Vec<(Corner, TilerCommand)>,
+    pub hot_corner_dwell_ms: u64,  // How long the cursor rests in a corner before it fires
+    pub force_manage: Vec<String>, // Window classes / exe names tiled past the style heuristics
+    pub quirks: Vec<(String, Option<Quirk>)>, // Per-app handling overriding the built-in database
+    pub adopt_settle_ms: u64,      // Wait for every window to stop moving before tiling; 0 disables
+    pub transparency_step: u8,     // Alpha change per transparency keypress
+    pub transparency_floor: u8,    // Lowest alpha the tiles can be set to
+    pub native_release: bool,      // Released windows use the system's restore animation, not the tiler's
+    pub instant: bool,             // Start in rapid-fire mode: no throttling, animation or transparency
+    pub dry_run: bool,             // Log adoptions and geometry instead of touching any window
+    pub frame_pacing: FramePacing,
+    pub input_priority: InputPriority,
+    pub handoff: bool,             // Take the layout over from the running instance
+    pub config_overrides: Vec<(String, String)>, // --set section.key=value, applied over config.toml
+    pub console: bool,             // Print to a console window instead of the log file
+    pub restore_layout: bool,      // Put windows still open back where the last run had them
+    pub auto_tile: bool,           // Tile new windows without Win+Shift+T
+}
+
+impl Options {
+    // Options from THYMELINE_OPTIONS come first so the command line overrides them.
+    // Subcommands are left alone; a started instance reads the variable itself.
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let is_subcommand = args.first().is_some_and(|arg| !arg.starts_with('-'));
+        let from_env = match std::env::var(OPTIONS_VAR) {
+            Ok(value) if !is_subcommand => split_options(&value),
+            _ => Vec::new(),
+        };
+        Self::parse(from_env.into_iter().chain(args))
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Self {
+        let mut args = args.peekable();
+        let mut subcommand = None;
+
+        match args.peek().map(String::as_str) {
+            Some("pwsh-module") => {
+                args.next();
+                subcommand = Some(Subcommand::PwshModule(args.next().map(PathBuf::from)));
+            }
+            Some("quit") => {
+                args.next();
+                subcommand = Some(Subcommand::Quit);
+            }
+            Some("state") => {
+                args.next();
+                subcommand = Some(Subcommand::State);
+            }
+            Some("stats") => {
+                args.next();
+                subcommand = Some(Subcommand::Stats);
+            }
+            Some("command") => {
+                args.next();
+                let name = args.next().unwrap_or_default();
+                let hwnd = match args.next().as_deref() {
+                    Some("--hwnd") => args.next().and_then(|v| v.parse().ok()),
+                    _ => None,
+                };
+                subcommand = Some(Subcommand::Command { name, hwnd });
+            }
+            // One verb for scripts and AutoHotkey: a query, or else the name of any command
+            Some("ctl") => {
+                args.next();
+                subcommand = match args.next().as_deref() {
+                    Some("state") => Some(Subcommand::State),
+                    Some("stats") => Some(Subcommand::Stats),
+                    Some("quit") => Some(Subcommand::Quit),
+                    name => {
+                        let name = name.unwrap_or_default().to_string();
+                        let hwnd = match args.next().as_deref() {
+                            Some("--hwnd") => args.next().and_then(|v| v.parse().ok()),
+                            _ => None,
+                        };
+                        Some(Subcommand::Command { name, hwnd })
+                    }
+                };
+            }
+            Some("demo") => {
+                args.next();
+                subcommand = Some(Subcommand::Command { name: "demo".to_string(), hwnd: None });
+            }
+            Some("macro") => {
+                args.next();
+                subcommand = match args.next().as_deref() {
+                    Some("save") => Some(Subcommand::SaveMacro(args.next())),
+                    Some("play") => Some(Subcommand::PlayMacro(args.next())),
+                    // `macro review` is short for `macro play review`
+                    name => Some(Subcommand::PlayMacro(name.map(str::to_string))),
+                };
+            }
+            Some("start") => {
+                args.next();
+                subcommand = Some(Subcommand::Start(args.by_ref().collect()));
+            }
+            Some("import-layout") => {
+                args.next();
+                subcommand = Some(Subcommand::ImportLayout(args.next().map(PathBuf::from).unwrap_or_default()));
+            }
+            Some("template") => {
+                args.next();
+                let mut name = None;
+                let (mut row, mut print) = (0, false);
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--row" => row = args.next().and_then(|row| row.parse().ok()).unwrap_or(0),
+                        "--print" => print = true,
+                        _ => name = Some(arg),
+                    }
+                }
+                subcommand = Some(Subcommand::Template { name, row, print });
+            }
+            Some("uri") => {
+                args.next();
+                subcommand = Some(Subcommand::Uri(args.next().unwrap_or_default()));
+            }
+            Some("register-uri") => {
+                args.next();
+                subcommand = Some(Subcommand::RegisterUri(true));
+            }
+            Some("unregister-uri") => {
+                args.next();
+                subcommand = Some(Subcommand::RegisterUri(false));
+            }
+            Some("restart") => {
+                args.next();
+                subcommand = Some(Subcommand::Restart(args.by_ref().collect()));
+            }
+            _ => {}
+        }
+
+        let mut track_path: Option<PathBuf> = None;
+        let mut record_titles = true;
+        let mut managed_only = false;
+        let mut excluded_exes = Vec::new();
+        let mut idle_minutes = 10;
+        let mut ipc = true;
+        let mut throw_focus_follows = true;
+        let mut viewport_splits = Vec::new();
+        let mut scroll_focus = ScrollFocus::Center;
+        let mut row_focus_memory = false;
+        let mut startup_layout = None;
+        let mut hot_corners = Vec::new();
+        let mut hot_corner_dwell_ms = crate::hotcorner::DEFAULT_DWELL_MS;
+        let mut force_manage = Vec::new();
+        let mut quirks = Vec::new();
+        let mut adopt_settle_ms = 0;
+        let mut transparency_step = 10;
+        let mut transparency_floor = 50;
+        let mut native_release = false;
+        let mut instant = false;
+        let mut dry_run = false;
+        let mut console = false;
+        let mut restore_layout = true;
+        let mut auto_tile = false;
+        let mut frame_pacing = FramePacing::Fixed;
+        let mut input_priority = InputPriority::AboveNormal;
+        let mut handoff = false;
+        let mut config_overrides = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--track-time" => match args.next() {
+                    Some(path) => track_path = Some(PathBuf::from(path)),
+                    None => println!("Warning: --track-time needs a file path"),
+                },
+                "--track-time-no-titles" => record_titles = false,
+                "--track-time-managed-only" => managed_only = true,
+                "--track-time-exclude" => match args.next() {
+                    Some(exe) => excluded_exes.push(exe),
+                    None => println!("Warning: --track-time-exclude needs an exe name"),
+                },
+                "--idle-minutes" => match args.next().and_then(|v| v.parse().ok()) {
+                    Some(minutes) => idle_minutes = minutes,
+                    None => println!("Warning: --idle-minutes needs a number of minutes"),
+                },
+                // Switches come in pairs, so the command line can undo one set in THYMELINE_OPTIONS
+                "--ipc" | "--no-ipc" => ipc = arg == "--ipc",
+                "--throw-keep-focus" => throw_focus_follows = false,
+                "--viewports" => match args.next().as_deref().and_then(parse_viewports) {
+                    Some(splits) => viewport_splits = splits,
+                    None => println!("Warning: --viewports needs 2, 3 or boundaries like 0.4,0.7"),
+                },
+                // Row layout flags stand for --set layout.<key>, so they win over the config file
+                "--visible-rows" => match args.next().filter(|v| v.parse::<i32>().is_ok_and(|rows| rows > 0)) {
+                    Some(rows) => config_overrides.push(("layout.visible_rows".to_string(), rows)),
+                    None => println!("Warning: --visible-rows needs a number of rows"),
+                },
+                "--scroll-focus" => match args.next().as_deref().and_then(ScrollFocus::parse) {
+                    Some(policy) => scroll_focus = policy,
+                    None => println!("Warning: --scroll-focus needs keep, center, leftmost or none"),
+                },
+                "--row-focus-memory" | "--no-row-focus-memory" => row_focus_memory = arg == "--row-focus-memory",
+                "--startup-layout" => match args.next() {
+                    Some(path) => startup_layout = Some(PathBuf::from(path)),
+                    None => println!("Warning: --startup-layout needs a file path"),
+                },
+                "--hot-corner" => match args.next().as_deref().and_then(parse_hot_corner) {
+                    Some(binding) => {
+                        hot_corners.retain(|(corner, _)| *corner != binding.0);
+                        hot_corners.push(binding);
+                    }
+                    None => println!("Warning: --hot-corner needs <corner>=<command>, e.g. top-left=scroll-to-window"),
+                },
+                "--hot-corner-dwell" => match args.next().and_then(|v| v.parse().ok()) {
+                    Some(ms) => hot_corner_dwell_ms = ms,
+                    None => println!("Warning: --hot-corner-dwell needs a number of milliseconds"),
+                },
+                "--force-manage" => match args.next() {
+                    Some(name) => force_manage.push(name),
+                    None => println!("Warning: --force-manage needs a window class or exe name"),
+                },
+                "--quirk" => match args.next().as_deref().and_then(parse_quirk) {
+                    Some(quirk) => quirks.push(quirk),
+                    None => println!("Warning: --quirk needs <exe-or-class>=<float|delay-adopt|restore-style|no-resize|ignore|opaque|none>"),
+                },
+                "--adopt-settle" => match args.next().and_then(|v| v.parse().ok()) {
+                    Some(ms) => adopt_settle_ms = ms,
+                    None => println!("Warning: --adopt-settle needs a number of milliseconds"),
+                },
+                "--transparency-step" => match args.next().and_then(|v| v.parse().ok()).filter(|&step| step > 0) {
+                    Some(step) => transparency_step = step,
+                    None => println!("Warning: --transparency-step needs a number from 1 to 255"),
+                },
+                "--transparency-floor" => match args.next().and_then(|v| v.parse().ok()) {
+                    Some(floor) => transparency_floor = floor,
+                    None => println!("Warning: --transparency-floor needs a number from 0 to 255"),
+                },
+                "--native-release" | "--no-native-release" => native_release = arg == "--native-release",
+                "--instant" | "--no-instant" => instant = arg == "--instant",
+                "--dry-run" | "--no-dry-run" => dry_run = arg == "--dry-run",
+                "--console" | "--no-console" => console = arg == "--console",
+                "--restore" | "--no-restore" => restore_layout = arg == "--restore",
+                "--auto-tile" | "--no-auto-tile" => auto_tile = arg == "--auto-tile",
+                "--frame-pacing" => match args.next().as_deref().and_then(FramePacing::parse) {
+                    Some(pacing) => frame_pacing = pacing,
+                    None => println!("Warning: --frame-pacing needs fixed or compensated"),
+                },
+                "--handoff" => handoff = true,
+                "--input-priority" => match args.next().as_deref().and_then(InputPriority::parse) {
+                    Some(priority) => input_priority = priority,
+                    None => println!("Warning: --input-priority needs normal, above-normal or highest"),
+                },
+                "--row-limit" => match args.next().filter(|v| v.parse::<usize>().is_ok_and(|limit| limit > 0)) {
+                    Some(limit) => config_overrides.push(("layout.row_limit".to_string(), limit)),
+                    None => println!("Warning: --row-limit needs a number of tiles"),
+                },
+                "--max-row-width" => match args.next().filter(|v| v.parse::<f32>().is_ok_and(|width| width > 0.0)) {
+                    Some(width) => config_overrides.push(("layout.max_row_width".to_string(), width)),
+                    None => println!("Warning: --max-row-width needs a number of screen widths"),
+                },
+                "--limit-policy" => match args.next().filter(|v| LimitPolicy::parse(v).is_some()) {
+                    Some(policy) => config_overrides.push(("layout.limit_policy".to_string(), policy)),
+                    None => println!("Warning: --limit-policy needs spill, refuse or replace-oldest"),
+                },
+                "--set" => match args.next().as_deref().and_then(|spec| spec.split_once('=')) {
+                    Some((key, value)) => config_overrides.push((key.trim().to_string(), value.trim().to_string())),
+                    None => println!("Warning: --set needs <section>.<key>=<value>, e.g. animations.duration_ms=120"),
+                },
+                other => println!("Warning: Ignoring unknown argument '{}'", other),
+            }
+        }
+
+        let time_tracking = track_path.map(|path| {
+            let mut tracking = TimeTrackingOptions::new(path);
+            tracking.record_titles = record_titles;
+            tracking.managed_only = managed_only;
+            tracking.excluded_exes = excluded_exes;
+            tracking
+        });
+
+        Self {
+            subcommand,
+            time_tracking,
+            idle_minutes,
+            ipc,
+            throw_focus_follows,
+            viewport_splits,
+            scroll_focus,
+            row_focus_memory,
+            startup_layout,
+            hot_corners,
+            hot_corner_dwell_ms,
+            force_manage,
+            quirks,
+            adopt_settle_ms,
+            transparency_step,
+            transparency_floor,
+            native_release,
+            instant,
+            dry_run,
+            frame_pacing,
+            input_priority,
+            handoff,
+            config_overrides,
+            console,
+            restore_layout,
+            auto_tile,
+        }
+    }
+}
+
+// Default options, e.g. set per shortcut or per test run
+const OPTIONS_VAR: &str = "THYMELINE_OPTIONS";
+
+// Whitespace-separated, with double quotes around values containing spaces
+fn split_options(value: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    options.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        options.push(current);
+    }
+    options
+}
+
+// "2" / "3" split evenly; otherwise one or two ascending boundaries between 0 and 1
+fn parse_viewports(spec: &str) -> Option<Vec<f32>> {
+    match spec {
+        "1" => return Some(Vec::new()),
+        "2" => return Some(vec![0.5]),
+        "3" => return Some(vec![1.0 / 3.0, 2.0 / 3.0]),
+        _ => {}
+    }
+
+    let splits: Vec<f32> = spec.split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    let in_range = splits.iter().all(|&f| f > 0.0 && f < 1.0);
+    let ascending = splits.windows(2).all(|pair| pair[0] < pair[1]);
+    (!splits.is_empty() && splits.len() <= 2 && in_range && ascending).then_some(splits)
+}
+
+// "top-left=scroll-to-window"
+fn parse_hot_corner(spec: &str) -> Option<(Corner, TilerCommand)> {
+    let (corner, command) = spec.split_once('=')?;
+    Some((Corner::parse(corner.trim())?, TilerCommand::from_name(command.trim())?))
+}
+
+// "Taskmgr.exe=none"
+fn parse_quirk(spec: &str) -> Option<(String, Option<Quirk>)> {
+    let (name, quirk) = spec.split_once('=')?;
+    Some((name.trim().to_string(), Quirk::parse(quirk.trim())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn environment_options_are_split_like_a_command_line() {
+        let cases: [(&str, &[&str]); 10] = [
+            ("", &[]),
+            ("   ", &[]),
+            ("--instant", &["--instant"]),
+            ("  --instant \t --row-limit   4  ", &["--instant", "--row-limit", "4"]),
+            ("--startup-layout \"C:\\My Layouts\\work.json\"", &["--startup-layout", "C:\\My Layouts\\work.json"]),
+            ("--set \"layout.limit_policy=refuse\" --dry-run", &["--set", "layout.limit_policy=refuse", "--dry-run"]),
+            ("--track-time-exclude \"\"", &["--track-time-exclude", ""]),
+            ("\"\" \"\"", &["", ""]),
+            ("a\"b c\"d e", &["ab cd", "e"]),
+            ("\"unterminated value", &["unterminated value"]),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(split_options(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn later_switches_win() {
+        let base = ["--instant", "--dry-run", "--auto-tile", "--native-release", "--row-focus-memory", "--no-ipc", "--no-restore"];
+        let set = options(&base);
+        assert!(set.instant && set.dry_run && set.auto_tile && set.native_release && set.row_focus_memory);
+        assert!(!set.ipc && !set.restore_layout);
+
+        let undone: Vec<&str> = base.iter().copied()
+            .chain(["--no-instant", "--no-dry-run", "--no-auto-tile", "--no-native-release", "--no-row-focus-memory", "--ipc", "--restore"])
+            .collect();
+        let undone = options(&undone);
+        assert!(!undone.instant && !undone.dry_run && !undone.auto_tile && !undone.native_release && !undone.row_focus_memory);
+        assert!(undone.ipc && undone.restore_layout);
+    }
+
+    #[test]
+    fn later_values_win() {
+        let parsed = options(&["--idle-minutes", "5", "--row-limit", "4", "--idle-minutes", "20", "--row-limit", "6"]);
+        assert_eq!(parsed.idle_minutes, 20);
+        assert_eq!(parsed.config_overrides.last(), Some(&("layout.row_limit".to_string(), "6".to_string())));
+
+        // Lists add up; for quirks the last entry for an app is the one used
+        let parsed = options(&["--force-manage", "a.exe", "--quirk", "a.exe=float", "--force-manage", "b.exe", "--quirk", "a.exe=none"]);
+        assert_eq!(parsed.force_manage, vec!["a.exe".to_string(), "b.exe".to_string()]);
+        assert_eq!(parsed.quirks.last(), Some(&("a.exe".to_string(), None)));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use windows::{
+    core::*,
+    Win32::{Foundation::CloseHandle, System::Threading::*},
+};
+
+use crate::cli::Subcommand;
+use crate::handoff::HandoffState;
+use crate::ipc;
+use crate::launch;
+use crate::templates;
+use crate::uri;
+
+// How long a handoff waits for the old instance to exit
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Thin invocations of the same binary that talk to the resident instance over the
+// pipe instead of running a tiler. Returns the process exit code.
+pub fn run(subcommand: &Subcommand) -> i32 {
+    let result = match subcommand {
+        Subcommand::Quit => request(&json!({ "type": "quit" }))
+            .map(|_| println!("Thymeline is restoring windows and shutting down")),
+        Subcommand::State => request(&json!({ "type": "state" }))
+            .map(|state| println!("{}", serde_json::to_string_pretty(&state).unwrap_or_default())),
+        Subcommand::Stats => request(&json!({ "type": "stats" }))
+            .map(|stats| println!("{}", serde_json::to_string_pretty(&stats).unwrap_or_default())),
+        Subcommand::Command { name, hwnd } => {
+            let mut command = json!({ "type": "command", "command": name });
+            if let Some(hwnd) = hwnd {
+                command["hwnd"] = json!(hwnd);
+            }
+            request(&command).map(|_| ())
+        }
+        Subcommand::SaveMacro(Some(name)) => request(&json!({ "type": "save_macro", "name": name }))
+            .map(|_| println!("Saved the last recorded macro as '{}'", name)),
+        Subcommand::SaveMacro(None) => Err(io::Error::other("macro save needs a name, e.g. windowpots macro save review")),
+        Subcommand::PlayMacro(name) => request(&json!({ "type": "play_macro", "name": name })).map(|_| ()),
+        Subcommand::LinkMacro(name) => request(&json!({ "type": "play_macro", "name": name, "navigation_only": true })).map(|_| ()),
+        Subcommand::ImportLayout(path) => import_layout(path),
+        Subcommand::Template { name, row, print } => template(name.as_deref(), *row, *print),
+        Subcommand::Uri(uri) => match uri::parse(uri) {
+            Some(subcommand) => return run(&subcommand),
+            None => Err(io::Error::other(format!("'{}' is not a link like thymeline://pan-right; links only run navigation commands and macros", uri))),
+        },
+        Subcommand::Start(args) => start(args),
+        Subcommand::Restart(args) => restart(args.clone()),
+        Subcommand::PwshModule(_) | Subcommand::RegisterUri(_) => Ok(()),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("Thymeline is not running (nothing on {})", ipc::pipe_name());
+            1
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            1
+        }
+    }
+}
+
+fn connect() -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(ipc::pipe_name())
+}
+
+// One request, one reply; error replies come back as Err
+pub fn request(request: &Value) -> io::Result<Value> {
+    let pipe = connect()?;
+    let mut writer = &pipe;
+    writer.write_all(format!("{}\n", request).as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(&pipe).read_line(&mut reply)?;
+    let reply: Value = serde_json::from_str(&reply).map_err(io::Error::other)?;
+    if reply["type"] == "error" {
+        let message = reply["message"].as_str().unwrap_or("request failed");
+        return Err(io::Error::other(message.to_string()));
+    }
+    Ok(reply)
+}
+
+// Checked here, so a mistake in the file is reported against it rather than the request
+fn import_layout(path: &Path) -> io::Result<()> {
+    let apps = launch::load(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Can't read {}: {}", path.display(), e)))?;
+    request(&json!({ "type": "apply_layout", "apps": apps }))
+        .map(|_| println!("Applying a layout of {} apps", apps.len()))
+}
+
+fn template(name: Option<&str>, row: i32, print: bool) -> io::Result<()> {
+    let Some(name) = name else {
+        for (name, description) in templates::TEMPLATES {
+            println!("{:<10} {}", name, description);
+        }
+        return Ok(());
+    };
+    let Some(apps) = templates::get(name, row) else {
+        return Err(io::Error::other(format!("No template named '{}'; run `windowpots template` for the list", name)));
+    };
+    if print {
+        println!("{}", launch::to_text(&apps));
+        return Ok(());
+    }
+    request(&json!({ "type": "apply_template", "name": name, "row": row }))
+        .map(|_| println!("Applying the {} template", name))
+}
+
+fn start(args: &[String]) -> io::Result<()> {
+    if connect().is_ok() {
+        return Err(io::Error::other("Thymeline is already running in this session"));
+    }
+    spawn(args)
+}
+
+fn spawn(args: &[String]) -> io::Result<()> {
+    let exe = std::env::current_exe()?.to_string_lossy().into_owned();
+    let command_line = std::iter::once(&exe).chain(args).map(|arg| quote(arg)).collect::<Vec<_>>().join(" ");
+    let mut command_line: Vec<u16> = command_line.encode_utf16().chain(Some(0)).collect();
+
+    // No console: the resident instance outlives the one it was started from
+    let startup = STARTUPINFOW {
+        cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+        ..Default::default()
+    };
+    let mut process = PROCESS_INFORMATION::default();
+    unsafe {
+        CreateProcessW(
+            None,
+            PWSTR(command_line.as_mut_ptr()),
+            None,
+            None,
+            false,
+            CREATE_NO_WINDOW,
+            None,
+            None,
+            &startup,
+            &mut process,
+        ).map_err(io::Error::other)?;
+        CloseHandle(process.hThread).ok();
+        CloseHandle(process.hProcess).ok();
+    }
+    println!("Started Thymeline (pid {})", process.dwProcessId);
+    Ok(())
+}
+
+// Quote one argument the way CommandLineToArgvW splits it back up
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+// Swap the resident instance for a fresh one, e.g. after replacing the binary. The
+// new instance takes the layout over, so no window moves. Without arguments it gets
+// the same ones as the old.
+fn restart(mut args: Vec<String>) -> io::Result<()> {
+    let hello = request(&json!({ "type": "hello", "protocol_version": ipc::PROTOCOL_VERSION, "client": "restart" }))?;
+    if args.is_empty() {
+        args = hello["args"].as_array()
+            .map(|args| args.iter().filter_map(|arg| arg.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+    }
+    if !args.iter().any(|arg| arg == "--handoff") {
+        args.push("--handoff".to_string());
+    }
+    spawn(&args)
+}
+
+// Run by a starting --handoff instance: fetch the old instance's layout, commit to it so
+// the old one lets go without restoring, then wait for it to unhook and exit so the two
+// never manage windows at the same time. Once the layout is in hand it is always returned.
+pub fn take_over() -> io::Result<HandoffState> {
+    let reply = request(&json!({ "type": "handoff" }))?;
+    let state = match serde_json::from_value(reply.clone()) {
+        Ok(state) => state,
+        Err(e) => {
+            // Nothing was committed, so the old instance still has the windows and restores them
+            request(&json!({ "type": "quit" })).ok();
+            return Err(io::Error::other(format!("Can't read the old instance's layout, asked it to restore its windows instead: {}", e)));
+        }
+    };
+
+    // An instance from before the commit step let go as soon as it answered
+    if let Some(token) = reply["token"].as_u64() {
+        if let Err(e) = request(&json!({ "type": "handoff_commit", "token": token })) {
+            println!("Warning: Failed to commit the handoff: {}", e);
+        }
+    }
+
+    let deadline = Instant::now() + EXIT_TIMEOUT;
+    loop {
+        match connect() {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(state),
+            _ if Instant::now() >= deadline => {
+                println!("Warning: Timed out waiting for the old instance to exit");
+                return Ok(state);
+            }
+            _ => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
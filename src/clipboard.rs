@@ -0,0 +1,74 @@
+use windows::Win32::{
+    Foundation::*,
+    System::DataExchange::*,
+    System::Memory::*,
+};
+
+// Standard clipboard format for UTF-16 text; the windows crate keeps it under System::Ole
+const CF_UNICODETEXT: u32 = 13;
+
+// Replace the clipboard's contents with `text`, owned by `owner`
+pub fn set_text(owner: HWND, text: &str) -> bool {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        if OpenClipboard(owner).is_err() {
+            println!("Warning: The clipboard is in use by another app");
+            return false;
+        }
+        let copied = copy(&wide);
+        CloseClipboard().ok();
+        copied
+    }
+}
+
+// The clipboard's text, if it holds any
+pub fn get_text(owner: HWND) -> Option<String> {
+    unsafe {
+        if OpenClipboard(owner).is_err() {
+            println!("Warning: The clipboard is in use by another app");
+            return None;
+        }
+        let text = paste();
+        CloseClipboard().ok();
+        text
+    }
+}
+
+// With the clipboard open. The handle stays the clipboard's; only lock it to read.
+unsafe fn paste() -> Option<String> {
+    let data = GetClipboardData(CF_UNICODETEXT).ok()?;
+    let memory = HGLOBAL(data.0 as *mut std::ffi::c_void);
+    let source = GlobalLock(memory) as *const u16;
+    if source.is_null() {
+        return None;
+    }
+    let len = GlobalSize(memory) / 2;
+    let wide = std::slice::from_raw_parts(source, len);
+    let end = wide.iter().position(|&c| c == 0).unwrap_or(len);
+    let text = String::from_utf16_lossy(&wide[..end]);
+    GlobalUnlock(memory).ok();
+    Some(text)
+}
+
+// With the clipboard open. Once SetClipboardData succeeds the memory is the system's.
+unsafe fn copy(wide: &[u16]) -> bool {
+    if EmptyClipboard().is_err() {
+        return false;
+    }
+    let Ok(memory) = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(wide)) else {
+        return false;
+    };
+    let target = GlobalLock(memory) as *mut u16;
+    if target.is_null() {
+        GlobalFree(memory).ok();
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(wide.as_ptr(), target, wide.len());
+    GlobalUnlock(memory).ok();
+
+    if SetClipboardData(CF_UNICODETEXT, HANDLE(memory.0 as isize)).is_err() {
+        GlobalFree(memory).ok();
+        return false;
+    }
+    true
+}
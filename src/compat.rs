@@ -0,0 +1,80 @@
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::GetClassNameW,
+};
+
+use crate::window_info;
+
+// How a known-awkward app is handled instead of being tiled normally
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quirk {
+    Float,          // Track as floating, never tile
+    DelayAdopt,     // Wait before tiling; the app moves itself right after opening
+    RestoreStyle,   // Tile without stripping the caption buttons from its style
+    Ignore,         // Leave the window alone entirely
+    Opaque,         // Tile, but never make it transparent (goes black when layered)
+    NoResize,       // Tile at its own size, centred in the slot
+}
+
+impl Quirk {
+    // "none" parses to Some(None): an override that disables a built-in entry
+    pub fn parse(value: &str) -> Option<Option<Self>> {
+        match value {
+            "float" => Some(Some(Self::Float)),
+            "delay-adopt" => Some(Some(Self::DelayAdopt)),
+            "restore-style" => Some(Some(Self::RestoreStyle)),
+            "ignore" => Some(Some(Self::Ignore)),
+            "opaque" => Some(Some(Self::Opaque)),
+            "no-resize" => Some(Some(Self::NoResize)),
+            "none" => Some(None),
+            _ => None,
+        }
+    }
+}
+
+struct KnownApp {
+    exe: &'static str,
+    class: Option<&'static str>,   // None matches every window of the exe
+    quirk: Quirk,
+}
+
+// Built-in handling for apps that misbehave when tiled; user overrides win
+const KNOWN_APPS: &[KnownApp] = &[
+    // Explorer restores its saved size a moment after the window appears
+    KnownApp { exe: "explorer.exe", class: Some("CabinetWClass"), quirk: Quirk::DelayAdopt },
+    // Office backstage (File menu) is a full-window overlay owned by the document
+    KnownApp { exe: "WINWORD.EXE", class: Some("FullpageUIHost"), quirk: Quirk::Float },
+    KnownApp { exe: "EXCEL.EXE", class: Some("FullpageUIHost"), quirk: Quirk::Float },
+    KnownApp { exe: "POWERPNT.EXE", class: Some("FullpageUIHost"), quirk: Quirk::Float },
+    KnownApp { exe: "OUTLOOK.EXE", class: Some("FullpageUIHost"), quirk: Quirk::Float },
+    // Task Manager runs elevated, so moves from an unelevated tiler are refused
+    KnownApp { exe: "Taskmgr.exe", class: None, quirk: Quirk::Float },
+    // Flip-model swap chains show black through layered alpha
+    KnownApp { exe: "mpv.exe", class: None, quirk: Quirk::Opaque },
+    // OneNote redraws its ribbon wrongly once the maximize box is gone
+    KnownApp { exe: "ONENOTE.EXE", class: None, quirk: Quirk::RestoreStyle },
+    // Character Map is a fixed-size dialog and draws clipped when stretched
+    KnownApp { exe: "charmap.exe", class: None, quirk: Quirk::NoResize },
+];
+
+// Overrides are (exe or class name, quirk); a None quirk turns a built-in entry off.
+// Apps found to go black when layered come next, then the built-in list.
+pub fn lookup(hwnd: HWND, overrides: &[(String, Option<Quirk>)], opaque_apps: &[String]) -> Option<Quirk> {
+    let mut class_name = [0u16; 256];
+    let class_len = unsafe { GetClassNameW(hwnd, &mut class_name) };
+    let class = String::from_utf16_lossy(&class_name[..class_len.max(0) as usize]);
+    let exe = window_info::process_name(hwnd).unwrap_or_default();
+
+    let user = overrides.iter().rev()
+        .find(|(name, _)| *name == class || name.eq_ignore_ascii_case(&exe));
+    if let Some((_, quirk)) = user {
+        return *quirk;
+    }
+    if opaque_apps.iter().any(|app| app.eq_ignore_ascii_case(&exe)) {
+        return Some(Quirk::Opaque);
+    }
+
+    KNOWN_APPS.iter()
+        .find(|app| app.exe.eq_ignore_ascii_case(&exe) && app.class.is_none_or(|c| c == class))
+        .map(|app| app.quirk)
+}
@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Direct2D::Common::*,
+        Graphics::Direct2D::*,
+        Graphics::Direct3D::*,
+        Graphics::Direct3D11::*,
+        Graphics::DirectComposition::*,
+        Graphics::DirectWrite::*,
+        Graphics::Dxgi::Common::*,
+        Graphics::Dxgi::*,
+    },
+};
+
+// Direct2D drawing onto DirectComposition surfaces, so overlays animate in the
+// compositor instead of re-uploading a layered bitmap every frame. Device objects
+// are shared by every overlay; all of it lives on the tiler's main thread.
+pub struct Compositor {
+    d3d: ID3D11Device,
+    dxgi_factory: IDXGIFactory2,
+    d2d: ID2D1Device,
+    dcomp: IDCompositionDevice,
+    dwrite: IDWriteFactory,
+}
+
+thread_local! {
+    static SHARED: RefCell<Option<Rc<Compositor>>> = const { RefCell::new(None) };
+}
+
+const FORMAT: DXGI_FORMAT = DXGI_FORMAT_B8G8R8A8_UNORM;
+
+impl Compositor {
+    fn create() -> Result<Self> {
+        unsafe {
+            let mut d3d = None;
+            // Fall back to WARP on machines without a usable GPU driver (RDP, some VMs)
+            let created = [D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_WARP].iter().any(|&driver| {
+                D3D11CreateDevice(
+                    None,
+                    driver,
+                    HMODULE::default(),
+                    D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                    None,
+                    D3D11_SDK_VERSION,
+                    Some(&mut d3d),
+                    None,
+                    None,
+                ).is_ok()
+            });
+            let d3d: ID3D11Device = match d3d {
+                Some(d3d) if created => d3d,
+                _ => return Err(Error::from(E_FAIL)),
+            };
+
+            let dxgi_device: IDXGIDevice = d3d.cast()?;
+            Ok(Self {
+                dxgi_factory: CreateDXGIFactory2(0)?,
+                d2d: D2D1CreateDevice(&dxgi_device, None)?,
+                dcomp: DCompositionCreateDevice(&dxgi_device)?,
+                dwrite: DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?,
+                d3d,
+            })
+        }
+    }
+
+    // Created on first use; None when the machine can't compose (e.g. no Direct3D)
+    pub fn shared() -> Option<Rc<Self>> {
+        SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            if shared.is_none() {
+                match Self::create() {
+                    Ok(compositor) => *shared = Some(Rc::new(compositor)),
+                    Err(e) => println!("Warning: Overlay rendering unavailable: {}", e),
+                }
+            }
+            shared.clone()
+        })
+    }
+}
+
+// A window's composition surface: swap chain, visual and the D2D context drawing into it
+pub struct Surface {
+    compositor: Rc<Compositor>,
+    swap_chain: IDXGISwapChain1,
+    context: ID2D1DeviceContext,
+    _target: IDCompositionTarget,
+    _visual: IDCompositionVisual,
+    size: (u32, u32),
+}
+
+impl Surface {
+    // The window needs WS_EX_NOREDIRECTIONBITMAP so nothing but the visual is shown
+    pub fn new(hwnd: HWND) -> Option<Self> {
+        let compositor = Compositor::shared()?;
+        Self::create(compositor, hwnd).ok()
+    }
+
+    fn create(compositor: Rc<Compositor>, hwnd: HWND) -> Result<Self> {
+        unsafe {
+            let desc = DXGI_SWAP_CHAIN_DESC1 {
+                Width: 1,
+                Height: 1,
+                Format: FORMAT,
+                SampleDesc: DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                BufferCount: 2,
+                SwapEffect: DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+                AlphaMode: DXGI_ALPHA_MODE_PREMULTIPLIED,
+                ..Default::default()
+            };
+            let swap_chain = compositor.dxgi_factory.CreateSwapChainForComposition(&compositor.d3d, &desc, None)?;
+            let context = compositor.d2d.CreateDeviceContext(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?;
+
+            let target = compositor.dcomp.CreateTargetForHwnd(hwnd, true)?;
+            let visual = compositor.dcomp.CreateVisual()?;
+            visual.SetContent(&swap_chain)?;
+            target.SetRoot(&visual)?;
+            compositor.dcomp.Commit()?;
+
+            Ok(Self {
+                compositor,
+                swap_chain,
+                context,
+                _target: target,
+                _visual: visual,
+                size: (1, 1),
+            })
+        }
+    }
+
+    // Redraw the whole surface at a pixel size and DPI, then hand it to the compositor
+    pub fn draw(&mut self, width: u32, height: u32, dpi: f32, paint: impl FnOnce(&Canvas)) -> Result<()> {
+        unsafe {
+            let size = (width.max(1), height.max(1));
+            if size != self.size {
+                self.context.SetTarget(None);
+                self.swap_chain.ResizeBuffers(2, size.0, size.1, FORMAT, 0)?;
+                self.size = size;
+            }
+
+            let surface: IDXGISurface = self.swap_chain.GetBuffer(0)?;
+            let properties = D2D1_BITMAP_PROPERTIES1 {
+                pixelFormat: D2D1_PIXEL_FORMAT {
+                    format: FORMAT,
+                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                },
+                dpiX: dpi,
+                dpiY: dpi,
+                bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                ..Default::default()
+            };
+            let bitmap = self.context.CreateBitmapFromDxgiSurface(&surface, Some(&properties))?;
+
+            self.context.SetTarget(&bitmap);
+            self.context.SetDpi(dpi, dpi);
+            self.context.BeginDraw();
+            self.context.Clear(Some(&rgba(0, 0, 0, 0.0)));
+            paint(&Canvas {
+                context: &self.context,
+                dwrite: &self.compositor.dwrite,
+                width: size.0 as f32 * 96.0 / dpi,
+                height: size.1 as f32 * 96.0 / dpi,
+            });
+            let drawn = self.context.EndDraw(None, None);
+            self.context.SetTarget(None);
+            drawn?;
+
+            self.swap_chain.Present(1, 0).ok()?;
+            self.compositor.dcomp.Commit()
+        }
+    }
+}
+
+// Straight (not premultiplied) colour; D2D premultiplies when it blends
+pub fn rgba(r: u8, g: u8, b: u8, a: f32) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: r as f32 / 255.0,
+        g: g as f32 / 255.0,
+        b: b as f32 / 255.0,
+        a,
+    }
+}
+
+// Drawing calls for an overlay painter, in device-independent pixels
+pub struct Canvas<'a> {
+    context: &'a ID2D1DeviceContext,
+    dwrite: &'a IDWriteFactory,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Canvas<'_> {
+    pub fn fill_rect(&self, left: f32, top: f32, right: f32, bottom: f32, color: D2D1_COLOR_F) {
+        unsafe {
+            if let Ok(brush) = self.context.CreateSolidColorBrush(&color, None) {
+                let rect = D2D_RECT_F { left, top, right, bottom };
+                self.context.FillRectangle(&rect, &brush);
+            }
+        }
+    }
+
+    pub fn fill_rounded_rect(&self, left: f32, top: f32, right: f32, bottom: f32, radius: f32, color: D2D1_COLOR_F) {
+        unsafe {
+            if let Ok(brush) = self.context.CreateSolidColorBrush(&color, None) {
+                let rounded = D2D1_ROUNDED_RECT {
+                    rect: D2D_RECT_F { left, top, right, bottom },
+                    radiusX: radius,
+                    radiusY: radius,
+                };
+                self.context.FillRoundedRectangle(&rounded, &brush);
+            }
+        }
+    }
+
+    // Square of premultiplied BGRA pixels, top row first, scaled into the rect
+    pub fn bitmap(&self, pixels: &[u8], size: u32, left: f32, top: f32, right: f32, bottom: f32) {
+        let properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: FORMAT,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+            ..Default::default()
+        };
+        unsafe {
+            let size = D2D_SIZE_U { width: size, height: size };
+            if let Ok(bitmap) = self.context.CreateBitmap2(size, Some(pixels.as_ptr() as *const _), size.width * 4, &properties) {
+                let rect = D2D_RECT_F { left, top, right, bottom };
+                self.context.DrawBitmap2(&bitmap, Some(&rect), 1.0, D2D1_INTERPOLATION_MODE_HIGH_QUALITY_CUBIC, None, None);
+            }
+        }
+    }
+
+    // Single line centred in the rect
+    #[allow(clippy::too_many_arguments)]
+    pub fn text(&self, text: &str, left: f32, top: f32, right: f32, bottom: f32, size: f32, color: D2D1_COLOR_F) {
+        self.draw_text(text, D2D_RECT_F { left, top, right, bottom }, size, color, false);
+    }
+
+    // Single line from the left edge of the rect, cut off at its right edge
+    #[allow(clippy::too_many_arguments)]
+    pub fn text_leading(&self, text: &str, left: f32, top: f32, right: f32, bottom: f32, size: f32, color: D2D1_COLOR_F) {
+        self.draw_text(text, D2D_RECT_F { left, top, right, bottom }, size, color, true);
+    }
+
+    fn draw_text(&self, text: &str, rect: D2D_RECT_F, size: f32, color: D2D1_COLOR_F, leading: bool) {
+        unsafe {
+            let Ok(format) = self.dwrite.CreateTextFormat(
+                w!("Segoe UI"),
+                None,
+                DWRITE_FONT_WEIGHT_SEMI_BOLD,
+                DWRITE_FONT_STYLE_NORMAL,
+                DWRITE_FONT_STRETCH_NORMAL,
+                size,
+                w!(""),
+            ) else {
+                return;
+            };
+            let (alignment, options) = if leading {
+                format.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP).ok();
+                (DWRITE_TEXT_ALIGNMENT_LEADING, D2D1_DRAW_TEXT_OPTIONS_CLIP)
+            } else {
+                (DWRITE_TEXT_ALIGNMENT_CENTER, D2D1_DRAW_TEXT_OPTIONS_NONE)
+            };
+            format.SetTextAlignment(alignment).ok();
+            format.SetParagraphAlignment(DWRITE_PARAGRAPH_ALIGNMENT_CENTER).ok();
+
+            let Ok(brush) = self.context.CreateSolidColorBrush(&color, None) else {
+                return;
+            };
+            let wide: Vec<u16> = text.encode_utf16().collect();
+            self.context.DrawText(
+                &wide,
+                &format,
+                &rect,
+                &brush,
+                options,
+                DWRITE_MEASURING_MODE_NATURAL,
+            );
+        }
+    }
+}
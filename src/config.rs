@@ -0,0 +1,933 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+use serde::Deserialize;
+use toml::{Table, Value};
+use windows::Win32::{Foundation::HWND, UI::Input::KeyboardAndMouse::*};
+
+use crate::cli::LimitPolicy;
+use crate::easing::{Curve, Curves, Easing};
+use crate::{appdata, osd, rules::{self, RuleConfig}, schedule::{self, ScheduleConfig}, sharing, tray, window_info, TilerCommand};
+
+// Settings read from %APPDATA%\Thymeline\config.toml, at startup and again whenever the
+// file changes (see CONFIG_TIMER_ID). Every key is optional;
+// anything left out keeps the built-in default, and `--set section.key=value` on the
+// command line overrides the file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub layout: LayoutConfig,
+    pub appearance: AppearanceConfig,
+    pub animations: AnimationConfig,
+    pub input: InputConfig,
+    pub sharing: SharingConfig,
+    pub rotation: RotationConfig,
+    pub remote: RemoteConfig,
+    pub tablet: TabletConfig,
+    pub keybindings: BTreeMap<String, String>,   // "Win+Ctrl+Left" = "resize-left", or "none" to free a combo
+    pub hold_keybindings: BTreeMap<String, String>,   // The same, run when the combo is held down instead
+    pub repeat: BTreeMap<String, RepeatConfig>,       // Key repeat acceleration by command name
+    pub rules: Vec<RuleConfig>,                       // Per-app handling, first match wins
+    pub schedule: Vec<ScheduleConfig>,                // Row appearance by time of day
+    pub row_displays: Vec<RowDisplayConfig>,          // Primary display rows that belong on another display
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub margin_horizontal: i32,
+    pub margin_vertical: i32,
+    pub resize_step_percent: u32,   // Of the screen width, for resize-grow and resize-shrink
+    pub scratchpad_height_percent: u32,   // Of the screen height, for the scratchpad when dropped down
+    pub visible_rows: Option<i32>,   // Rows stacked on screen; None decides by orientation
+    pub row_limit: Option<usize>,    // Tiles a row may hold; None is unbounded
+    pub max_row_width: Option<f32>,  // Widest a row may grow, in screen widths; None is unbounded
+    pub limit_policy: LimitPolicy,   // What a new window does at either cap
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            margin_horizontal: 40,
+            margin_vertical: 80,
+            resize_step_percent: 5,
+            scratchpad_height_percent: 50,
+            visible_rows: None,
+            row_limit: None,
+            max_row_width: None,
+            limit_policy: LimitPolicy::Spill,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppearanceConfig {
+    pub transparency: u8,   // Alpha tiles start at
+    pub hud: bool,          // Show the row and tile the view is on while panning
+    pub dim_unfocused: bool,   // Keep the focused window opaque; only the others get `transparency`
+    pub row_dim: f32,       // How dark the row strip shades rows other than the current and picked one
+}
+
+impl Default for AppearanceConfig {
+    fn default() -> Self {
+        Self { transparency: 255, hud: true, dim_unfocused: false, row_dim: 0.5 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimationConfig {
+    pub fps: Option<u64>,    // None follows the display's refresh rate
+    pub duration_ms: u64,    // Slides, scrolls, and tiles entering or leaving
+    pub enabled: Option<bool>,   // None follows Windows' animation effects setting
+    pub easing: Easing,      // Curve of every animation whose own section doesn't set one
+    pub scroll: CurveConfig,
+    #[serde(rename = "move")]
+    pub moves: CurveConfig,
+    pub entry: CurveConfig,
+    pub exit: CurveConfig,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            fps: None,
+            duration_ms: 200,
+            enabled: None,
+            easing: Easing::default(),
+            scroll: CurveConfig::default(),
+            moves: CurveConfig::default(),
+            entry: CurveConfig::default(),
+            exit: CurveConfig::default(),
+        }
+    }
+}
+
+// One kind of animation's [animations.<kind>] section; unset keys follow [animations]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurveConfig {
+    pub duration_ms: Option<u64>,
+    pub easing: Option<Easing>,
+}
+
+// A tile moving within the ribbon is quicker than a scroll unless set otherwise
+const MOVE_DURATION_MS: u64 = 87;
+
+impl AnimationConfig {
+    pub fn curves(&self) -> Curves {
+        let curve = |section: &CurveConfig, duration_ms: u64| Curve {
+            duration: Duration::from_millis(section.duration_ms.unwrap_or(duration_ms)),
+            easing: section.easing.unwrap_or(self.easing),
+        };
+        Curves {
+            scroll: curve(&self.scroll, self.duration_ms),
+            moves: curve(&self.moves, MOVE_DURATION_MS),
+            entry: curve(&self.entry, self.duration_ms),
+            exit: curve(&self.exit, self.duration_ms),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub command_throttle_ms: u64,   // Repeats of one command closer together than this are dropped
+    pub passthrough: Vec<String>,   // Exe or class names whose windows get every shortcut unhandled
+    pub keymap: String,             // Preset laid over the default shortcuts, one of KEYMAPS
+    pub hold_ms: u32,               // How long a combo with a hold binding must stay down to count as held
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { command_throttle_ms: 50, passthrough: Vec::new(), keymap: "default".to_string(), hold_ms: 300 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SharingConfig {
+    pub guard: bool,         // Hold back pop-ups and automatic scrolls while one of `apps` runs
+    pub apps: Vec<String>,   // Exe names of screen sharing and recording apps
+}
+
+impl Default for SharingConfig {
+    fn default() -> Self {
+        Self { guard: false, apps: sharing::default_apps() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationConfig {
+    pub rows: Vec<i32>,           // Rows shown in turn, for dashboards; rotation is off when empty
+    pub interval_secs: u64,       // How long each row stays on screen
+    pub resume_after_secs: u64,   // Input pauses rotation until it has been idle this long
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self { rows: Vec::new(), interval_secs: 30, resume_after_secs: 60 }
+    }
+}
+
+// Profile a convertible switches to while folded or detached into a tablet
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TabletConfig {
+    pub auto: bool,               // Switch profile with the device's posture
+    pub margin_horizontal: i32,   // Wider gaps, easier to tap between tiles
+    pub margin_vertical: i32,
+    pub follow: bool,             // Scroll to whichever tile is tapped or picked from the taskbar
+    pub grab_strip: bool,         // A bar along the bottom to drag the ribbon with a finger or pen
+}
+
+impl Default for TabletConfig {
+    fn default() -> Self {
+        Self { auto: true, margin_horizontal: 80, margin_vertical: 120, follow: true, grab_strip: true }
+    }
+}
+
+// Read at startup only; the listener isn't moved by an edit to the file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub enabled: bool,     // Accept the pipe protocol over TCP, from other machines
+    pub address: String,   // Address and port to listen on
+    pub token: String,     // Secret every remote client must send first
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self { enabled: false, address: "127.0.0.1:47800".to_string(), token: String::new() }
+    }
+}
+
+// How a held key repeats its command: nothing until `delay_ms` after the press, then
+// every `interval_ms`, each gap `speedup` times the last down to `min_interval_ms`.
+// Repeats never come faster than the keyboard's own repeat rate.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepeatConfig {
+    pub delay_ms: u32,
+    pub interval_ms: u32,
+    pub min_interval_ms: u32,
+    pub speedup: f32,
+}
+
+impl Default for RepeatConfig {
+    fn default() -> Self {
+        Self { delay_ms: 400, interval_ms: 200, min_interval_ms: 50, speedup: 0.75 }
+    }
+}
+
+// One [[row_displays]] entry. Whenever `display` is attached, the tiles of this row of
+// the primary display's ribbon go to the same row of its ribbon instead.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowDisplayConfig {
+    pub row: i32,
+    pub display: String,   // "primary", or a device name such as DISPLAY2
+}
+
+// Commands that step through the ribbon accelerate when held, so crossing a long row
+// is quick while a tap still moves exactly one step
+fn default_repeats() -> Vec<(TilerCommand, RepeatConfig)> {
+    use TilerCommand::*;
+    [PanLeft, PanRight, FocusLeft, FocusRight, ResizeLeft, ResizeRight, ResizeGrow, ResizeShrink, MoveLeft, MoveRight, MoveUp, MoveDown]
+        .into_iter()
+        .map(|command| (command, RepeatConfig::default()))
+        .collect()
+}
+
+// The file is polled on the main window's timer, so edits apply without a restart
+pub const CONFIG_TIMER_ID: usize = 7;
+pub const CONFIG_POLL_MS: u32 = 1000;
+
+pub fn path() -> Option<PathBuf> {
+    appdata::data_path("config.toml")
+}
+
+// None while there is no config file
+pub fn modified() -> Option<SystemTime> {
+    fs::metadata(path()?).and_then(|metadata| metadata.modified()).ok()
+}
+
+// The console is usually hidden, so the first mistake is also raised from the tray icon,
+// and the heading shown on screen. The log has every one.
+fn report(heading: &str, errors: Vec<String>) {
+    println!("Warning: {}:", heading);
+    for error in &errors {
+        println!("  {}", error.trim_end());
+    }
+
+    // A TOML parse error spans several lines: where it is, a snippet, then what is wrong
+    let lines: Vec<&str> = errors.first().map_or("", String::as_str)
+        .lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('|'))
+        .collect();
+    let mut summary = match lines.as_slice() {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [first, .., last] => format!("{}: {}", first, last),
+    };
+    if errors.len() > 1 {
+        summary.push_str(&format!(" (and {} more, see the log)", errors.len() - 1));
+    }
+    tray::notify(heading, &summary);
+    osd::show(heading, 0.0);
+}
+
+// Modifier bits of a key combination
+pub const CTRL: u8 = 1;
+pub const ALT: u8 = 2;
+pub const SHIFT: u8 = 4;
+pub const WIN: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyCombo {
+    pub modifiers: u8,
+    pub vk: u16,
+}
+
+// Key names usable in a combo, besides single letters and digits
+const KEY_NAMES: &[(&str, VIRTUAL_KEY)] = &[
+    ("left", VK_LEFT), ("right", VK_RIGHT), ("up", VK_UP), ("down", VK_DOWN),
+    ("plus", VK_OEM_PLUS), ("minus", VK_OEM_MINUS), ("numpadplus", VK_ADD), ("numpadminus", VK_SUBTRACT),
+    ("space", VK_SPACE), ("tab", VK_TAB), ("enter", VK_RETURN), ("escape", VK_ESCAPE),
+    ("backspace", VK_BACK), ("insert", VK_INSERT), ("delete", VK_DELETE),
+    ("home", VK_HOME), ("end", VK_END), ("pageup", VK_PRIOR), ("pagedown", VK_NEXT),
+    ("pgup", VK_PRIOR), ("pgdn", VK_NEXT), ("numpadmultiply", VK_MULTIPLY), ("numpaddivide", VK_DIVIDE),
+    ("comma", VK_OEM_COMMA), ("period", VK_OEM_PERIOD), ("semicolon", VK_OEM_1), ("slash", VK_OEM_2),
+    ("backtick", VK_OEM_3), ("leftbracket", VK_OEM_4), ("backslash", VK_OEM_5),
+    ("rightbracket", VK_OEM_6), ("quote", VK_OEM_7),
+];
+
+fn parse_key(name: &str) -> Option<u16> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(&(_, vk)) = KEY_NAMES.iter().find(|(key, _)| *key == lower) {
+        return Some(vk.0);
+    }
+    if let [c] = lower.as_bytes() {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase() as u16);
+        }
+    }
+    if let Some(digit) = lower.strip_prefix("numpad").and_then(|d| d.parse::<u16>().ok()).filter(|&d| d <= 9) {
+        return Some(VK_NUMPAD0.0 + digit);
+    }
+    lower.strip_prefix('f')
+        .and_then(|n| n.parse::<u16>().ok())
+        .filter(|n| (1..=24).contains(n))
+        .map(|n| VK_F1.0 + n - 1)
+}
+
+// "Win+Ctrl+Left". At least one modifier is needed, so a binding never swallows typing.
+pub fn parse_combo(spec: &str) -> Result<KeyCombo, String> {
+    let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+    let key = parts.pop().filter(|key| !key.is_empty()).ok_or_else(|| "missing key".to_string())?;
+
+    let mut modifiers = 0;
+    for part in parts {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => CTRL,
+            "alt" => ALT,
+            "shift" => SHIFT,
+            "win" => WIN,
+            _ => return Err(format!("unknown modifier '{}'", part)),
+        };
+    }
+    if modifiers == 0 {
+        return Err("needs at least one of Win, Ctrl, Alt or Shift".to_string());
+    }
+
+    let vk = parse_key(key).ok_or_else(|| format!("unknown key '{}'", key))?;
+    Ok(KeyCombo { modifiers, vk })
+}
+
+// The shortcuts Thymeline ships with
+fn default_bindings() -> Vec<(KeyCombo, TilerCommand)> {
+    use TilerCommand::*;
+    let combo = |modifiers, vk: VIRTUAL_KEY| KeyCombo { modifiers, vk: vk.0 };
+    let letter = |modifiers, c: u8| KeyCombo { modifiers, vk: c as u16 };
+    vec![
+        (combo(WIN, VK_UP), PanUp),
+        (combo(WIN, VK_DOWN), PanDown),
+        (combo(WIN, VK_LEFT), PanLeft),
+        (combo(WIN, VK_RIGHT), PanRight),
+        (letter(WIN, b'C'), ForceRecalc),
+        (combo(WIN, VK_OEM_PLUS), IncreaseTransparency),
+        (combo(WIN, VK_ADD), IncreaseTransparency),
+        (combo(WIN, VK_OEM_MINUS), DecreaseTransparency),
+        (combo(WIN, VK_SUBTRACT), DecreaseTransparency),
+        (letter(WIN, b'S'), ScrollToWindow),
+        (letter(WIN, b'M'), IncreaseMargins),
+        (letter(WIN, b'N'), DecreaseMargins),
+        (letter(WIN, b'F'), CycleFPS),
+        // For keyboards without an arrow cluster to spare, e.g. Fn+arrows on laptops
+        (combo(WIN, VK_PRIOR), PanUp),
+        (combo(WIN, VK_NEXT), PanDown),
+        (combo(WIN, VK_HOME), PanStart),
+        (combo(WIN, VK_END), PanEnd),
+        (combo(WIN | CTRL, VK_LEFT), ResizeLeft),
+        (combo(WIN | CTRL, VK_RIGHT), ResizeRight),
+        (combo(WIN | CTRL | ALT, VK_LEFT), ResizeShrink),
+        (combo(WIN | CTRL | ALT, VK_RIGHT), ResizeGrow),
+        (combo(WIN | SHIFT, VK_LEFT), SendToMonitorLeft),
+        (combo(WIN | SHIFT, VK_RIGHT), SendToMonitorRight),
+        (combo(WIN | SHIFT, VK_OEM_PLUS), IncreaseTransparency),
+        (combo(WIN | SHIFT, VK_ADD), IncreaseTransparency),
+        (combo(WIN | SHIFT, VK_OEM_MINUS), DecreaseTransparency),
+        (combo(WIN | SHIFT, VK_SUBTRACT), DecreaseTransparency),
+        (letter(WIN | SHIFT, b'T'), AddWindow),
+        (letter(WIN | SHIFT, b'R'), RemoveWindow),
+        (letter(WIN | SHIFT, b'P'), ToggleMirror),
+        (letter(WIN | SHIFT, b'V'), CycleViewport),
+        (letter(WIN | SHIFT, b'I'), ToggleInstant),
+        (letter(WIN | SHIFT, b'Q'), RecordMacro),
+        (letter(WIN | SHIFT, b'E'), ReplayMacro),
+        (letter(WIN | SHIFT, b'F'), ToggleFloat),
+        (letter(WIN | SHIFT, b'D'), TogglePresentation),
+        (letter(WIN | SHIFT, b'C'), CopyLayout),
+        (combo(WIN | SHIFT, VK_OEM_3), ToggleScratchpad),
+        (combo(WIN | CTRL | SHIFT, VK_OEM_3), SetScratchpad),
+        (combo(WIN | SHIFT, VK_OEM_4), StackWindow),
+        (combo(WIN | SHIFT, VK_OEM_6), UnstackWindow),
+        (combo(WIN, VK_OEM_3), CycleStack),
+        (combo(WIN, VK_TAB), SwitchWindow),
+        (combo(WIN | ALT, VK_UP), FocusUp),
+        (combo(WIN | ALT, VK_DOWN), FocusDown),
+        (combo(WIN | ALT, VK_LEFT), FocusLeft),
+        (combo(WIN | ALT, VK_RIGHT), FocusRight),
+        (combo(WIN | CTRL | SHIFT, VK_UP), MoveUp),
+        (combo(WIN | CTRL | SHIFT, VK_DOWN), MoveDown),
+        (combo(WIN | CTRL | SHIFT, VK_LEFT), MoveLeft),
+        (combo(WIN | CTRL | SHIFT, VK_RIGHT), MoveRight),
+    ]
+}
+
+// Shortcuts that do something else when held. The combo's own binding then runs on
+// release instead of on the press, if the key came up before input.hold_ms.
+fn default_hold_bindings() -> Vec<(KeyCombo, TilerCommand)> {
+    vec![
+        (KeyCombo { modifiers: WIN, vk: b'S' as u16 }, TilerCommand::ToggleFollow),
+    ]
+}
+
+const KEYMAPS: &[&str] = &["default", "vim-hjkl", "paperwm", "i3"];
+
+// Shortcuts a keymap preset adds on top of the defaults, replacing any on the same combo.
+// Windows keeps Win+L for locking, so the letter presets use Win+Alt as their modifier.
+fn preset_bindings(keymap: &str) -> Vec<(KeyCombo, TilerCommand)> {
+    use TilerCommand::*;
+    let combo = |modifiers, vk: VIRTUAL_KEY| KeyCombo { modifiers, vk: vk.0 };
+    let letter = |modifiers, c: u8| KeyCombo { modifiers, vk: c as u16 };
+    match keymap {
+        "vim-hjkl" => vec![
+            (letter(WIN | ALT, b'H'), PanLeft),
+            (letter(WIN | ALT, b'J'), PanDown),
+            (letter(WIN | ALT, b'K'), PanUp),
+            (letter(WIN | ALT, b'L'), PanRight),
+            (letter(WIN | ALT | CTRL, b'H'), ResizeLeft),
+            (letter(WIN | ALT | CTRL, b'L'), ResizeRight),
+            (letter(WIN | ALT | SHIFT, b'H'), MoveLeft),
+            (letter(WIN | ALT | SHIFT, b'J'), MoveDown),
+            (letter(WIN | ALT | SHIFT, b'K'), MoveUp),
+            (letter(WIN | ALT | SHIFT, b'L'), MoveRight),
+        ],
+        // Neighbours on comma and period, rows on PageUp/PageDown, Win+R for the width
+        "paperwm" => vec![
+            (combo(WIN, VK_OEM_COMMA), PanLeft),
+            (combo(WIN, VK_OEM_PERIOD), PanRight),
+            (combo(WIN | SHIFT, VK_OEM_COMMA), MoveLeft),
+            (combo(WIN | SHIFT, VK_OEM_PERIOD), MoveRight),
+            (combo(WIN | SHIFT, VK_PRIOR), MoveUp),
+            (combo(WIN | SHIFT, VK_NEXT), MoveDown),
+            (letter(WIN, b'R'), ResizeRight),
+        ],
+        // i3's home-row layout, shifted one key right of vim's
+        "i3" => vec![
+            (letter(WIN | ALT, b'J'), PanLeft),
+            (letter(WIN | ALT, b'K'), PanDown),
+            (letter(WIN | ALT, b'L'), PanUp),
+            (combo(WIN | ALT, VK_OEM_1), PanRight),
+            (letter(WIN | ALT | SHIFT, b'J'), MoveLeft),
+            (letter(WIN | ALT | SHIFT, b'K'), MoveDown),
+            (letter(WIN | ALT | SHIFT, b'L'), MoveUp),
+            (combo(WIN | ALT | SHIFT, VK_OEM_1), MoveRight),
+            (letter(WIN | ALT, b'F'), ResizeRight),
+            (letter(WIN | ALT | SHIFT, b'Q'), RemoveWindow),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+// Active shortcuts, read by the keyboard hook
+static BINDINGS: Mutex<Vec<(KeyCombo, TilerCommand)>> = Mutex::new(Vec::new());
+static HOLD_BINDINGS: Mutex<Vec<(KeyCombo, TilerCommand)>> = Mutex::new(Vec::new());
+static HOLD_MS: AtomicU32 = AtomicU32::new(300);
+static REPEATS: Mutex<Vec<(TilerCommand, RepeatConfig)>> = Mutex::new(Vec::new());
+
+// The key each numpad digit stands for with NumLock off. With NumLock on the same keys
+// report VK_NUMPAD*, so a combo on one of them falls back to its navigation key.
+const NUMPAD_NAVIGATION: &[(VIRTUAL_KEY, VIRTUAL_KEY)] = &[
+    (VK_NUMPAD8, VK_UP), (VK_NUMPAD2, VK_DOWN), (VK_NUMPAD4, VK_LEFT), (VK_NUMPAD6, VK_RIGHT),
+    (VK_NUMPAD9, VK_PRIOR), (VK_NUMPAD3, VK_NEXT), (VK_NUMPAD7, VK_HOME), (VK_NUMPAD1, VK_END),
+];
+
+fn lookup(bindings: &[(KeyCombo, TilerCommand)], combo: KeyCombo) -> Option<TilerCommand> {
+    let find = |combo: KeyCombo| bindings.iter().find(|(bound, _)| *bound == combo).map(|&(_, command)| command);
+    find(combo).or_else(|| {
+        let &(_, navigation) = NUMPAD_NAVIGATION.iter().find(|(numpad, _)| numpad.0 == combo.vk)?;
+        find(KeyCombo { vk: navigation.0, ..combo })
+    })
+}
+
+pub fn binding(combo: KeyCombo) -> Option<TilerCommand> {
+    lookup(&BINDINGS.lock().unwrap(), combo)
+}
+
+pub fn hold_binding(combo: KeyCombo) -> Option<TilerCommand> {
+    lookup(&HOLD_BINDINGS.lock().unwrap(), combo)
+}
+
+pub fn repeat(command: TilerCommand) -> Option<RepeatConfig> {
+    REPEATS.lock().unwrap().iter()
+        .find(|(repeating, _)| *repeating as u32 == command as u32)
+        .map(|&(_, repeat)| repeat)
+}
+
+pub fn hold_ms() -> u32 {
+    HOLD_MS.load(Ordering::Relaxed)
+}
+
+// The file's entries over built-in bindings, replacing or removing the ones on the same combo
+fn overlay(mut bindings: Vec<(KeyCombo, TilerCommand)>, entries: &BTreeMap<String, String>) -> Vec<(KeyCombo, TilerCommand)> {
+    for (combo, command) in entries {
+        let Ok(combo) = parse_combo(combo) else {
+            continue;
+        };
+        bindings.retain(|(bound, _)| *bound != combo);
+        if let Some(command) = TilerCommand::from_name(command) {
+            bindings.push((combo, command));
+        }
+    }
+    bindings
+}
+
+// A value given to --set, as TOML where it parses and as a plain string otherwise
+fn override_value(value: &str) -> Value {
+    format!("value = {}", value).parse::<Table>().ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn apply_override(table: &mut Table, key: &str, value: &str) -> Result<(), String> {
+    let mut path: Vec<&str> = key.split('.').map(|name| name.trim_matches('"')).collect();
+    let last = path.pop().filter(|last| !last.is_empty()).ok_or_else(|| format!("--set {}: missing key", key))?;
+    let mut section = table;
+    for name in path {
+        section = match section.entry(name).or_insert_with(|| Value::Table(Table::new())) {
+            Value::Table(inner) => inner,
+            _ => return Err(format!("--set {}: '{}' is not a section", key, name)),
+        };
+    }
+    section.insert(last.to_string(), override_value(value));
+    Ok(())
+}
+
+impl Config {
+    // Every problem in the file, each naming the key it is about
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.layout.margin_horizontal < 0 {
+            errors.push("layout.margin_horizontal: must not be negative".to_string());
+        }
+        if self.layout.margin_vertical < 0 {
+            errors.push("layout.margin_vertical: must not be negative".to_string());
+        }
+        if !(1..=50).contains(&self.layout.resize_step_percent) {
+            errors.push(format!("layout.resize_step_percent: {} is outside 1 to 50", self.layout.resize_step_percent));
+        }
+        if !(20..=100).contains(&self.layout.scratchpad_height_percent) {
+            errors.push(format!("layout.scratchpad_height_percent: {} is outside 20 to 100", self.layout.scratchpad_height_percent));
+        }
+        if self.layout.visible_rows.is_some_and(|rows| rows < 1) {
+            errors.push("layout.visible_rows: needs at least 1".to_string());
+        }
+        if self.layout.row_limit == Some(0) {
+            errors.push("layout.row_limit: needs at least 1".to_string());
+        }
+        if self.layout.max_row_width.is_some_and(|width| width <= 0.0) {
+            errors.push("layout.max_row_width: must be more than 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.appearance.row_dim) {
+            errors.push(format!("appearance.row_dim: {} is outside 0 to 1", self.appearance.row_dim));
+        }
+        if let Some(fps) = self.animations.fps.filter(|fps| !(30..=240).contains(fps)) {
+            errors.push(format!("animations.fps: {} is outside 30 to 240", fps));
+        }
+        if self.animations.duration_ms > 2000 {
+            errors.push(format!("animations.duration_ms: {} is longer than 2000", self.animations.duration_ms));
+        }
+        let sections = [("scroll", &self.animations.scroll), ("move", &self.animations.moves),
+            ("entry", &self.animations.entry), ("exit", &self.animations.exit)];
+        for (name, section) in sections {
+            if let Some(duration_ms) = section.duration_ms.filter(|&ms| ms > 2000) {
+                errors.push(format!("animations.{}.duration_ms: {} is longer than 2000", name, duration_ms));
+            }
+        }
+        if !KEYMAPS.contains(&self.input.keymap.as_str()) {
+            errors.push(format!("input.keymap: '{}' is not one of {}", self.input.keymap, KEYMAPS.join(", ")));
+        }
+        if self.input.passthrough.iter().any(|name| name.trim().is_empty()) {
+            errors.push("input.passthrough: names must not be empty".to_string());
+        }
+        if !(100..=2000).contains(&self.input.hold_ms) {
+            errors.push(format!("input.hold_ms: {} is outside 100 to 2000", self.input.hold_ms));
+        }
+        if self.sharing.apps.iter().any(|name| name.trim().is_empty()) {
+            errors.push("sharing.apps: names must not be empty".to_string());
+        }
+        if self.rotation.rows.iter().any(|&row| row < 0) {
+            errors.push("rotation.rows: rows must not be negative".to_string());
+        }
+        for (index, preference) in self.row_displays.iter().enumerate() {
+            if preference.row < 0 {
+                errors.push(format!("row_displays[{}].row: must not be negative", index));
+            }
+            if preference.display.trim().is_empty() {
+                errors.push(format!("row_displays[{}].display: must not be empty", index));
+            }
+        }
+        if self.remote.address.parse::<SocketAddr>().is_err() {
+            errors.push(format!("remote.address: '{}' is not an address like 0.0.0.0:47800", self.remote.address));
+        }
+        if self.remote.enabled && self.remote.token.chars().count() < 16 {
+            errors.push("remote.token: needs at least 16 characters when remote.enabled is on".to_string());
+        }
+        if self.rotation.interval_secs < 5 {
+            errors.push(format!("rotation.interval_secs: {} is shorter than 5", self.rotation.interval_secs));
+        }
+        errors.extend(rules::validate(&self.rules));
+        errors.extend(schedule::validate(&self.schedule));
+        for (command, repeat) in &self.repeat {
+            if TilerCommand::from_name(command).is_none() {
+                errors.push(format!("repeat.\"{}\": unknown command", command));
+            }
+            if !(repeat.speedup > 0.0 && repeat.speedup <= 1.0) {
+                errors.push(format!("repeat.\"{}\".speedup: {} is outside 0 to 1", command, repeat.speedup));
+            }
+            if repeat.min_interval_ms > repeat.interval_ms {
+                errors.push(format!("repeat.\"{}\".min_interval_ms: longer than interval_ms", command));
+            }
+        }
+        for (table, bindings) in [("keybindings", &self.keybindings), ("hold_keybindings", &self.hold_keybindings)] {
+            for (combo, command) in bindings {
+                if let Err(e) = parse_combo(combo) {
+                    errors.push(format!("{}.\"{}\": {}", table, combo, e));
+                }
+                if command != "none" && TilerCommand::from_name(command).is_none() {
+                    errors.push(format!("{}.\"{}\": unknown command '{}'", table, combo, command));
+                }
+            }
+        }
+        errors
+    }
+
+    // Parse the file with overrides applied on top
+    fn parse(text: &str, overrides: &[(String, String)]) -> Result<Self, Vec<String>> {
+        let mut table: Table = text.parse().map_err(|e: toml::de::Error| vec![e.to_string()])?;
+        for (key, value) in overrides {
+            apply_override(&mut table, key, value).map_err(|e| vec![e])?;
+        }
+        let config = Self::deserialize(Value::Table(table)).map_err(|e| vec![e.to_string()])?;
+        match config.validate() {
+            errors if errors.is_empty() => Ok(config),
+            errors => Err(errors),
+        }
+    }
+
+    // A missing file reads as the defaults
+    fn read(overrides: &[(String, String)]) -> Result<Self, Vec<String>> {
+        let text = path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+        Self::parse(&text, overrides)
+    }
+
+    // A file with mistakes is reported key by key and ignored as a whole, so a half-read
+    // config never mixes with the defaults
+    pub fn load(overrides: &[(String, String)]) -> Self {
+        Self::read(overrides).unwrap_or_else(|errors| {
+            report("Ignoring config file, using the defaults", errors);
+            Self::default()
+        })
+    }
+
+    // After an edit. None leaves the last valid config in force, so a typo mid-edit
+    // never throws away a working setup.
+    pub fn reload(overrides: &[(String, String)]) -> Option<Self> {
+        Self::read(overrides).map_err(|errors| {
+            report("Config file has mistakes, keeping the previous settings", errors);
+        }).ok()
+    }
+
+    // Games, VM consoles and remote desktops want the Win combos for themselves. Exe
+    // names match case-insensitively, class names exactly.
+    pub fn passes_through(&self, hwnd: HWND) -> bool {
+        if self.input.passthrough.is_empty() || hwnd.0 == 0 {
+            return false;
+        }
+        let class = window_info::class_name(hwnd);
+        let exe = window_info::process_name(hwnd).unwrap_or_default();
+        self.input.passthrough.iter().any(|name| *name == class || name.eq_ignore_ascii_case(&exe))
+    }
+
+    // Make this config's shortcuts the active ones: the defaults and the keymap preset,
+    // with the file's entries replacing or removing the ones on the same combo
+    pub fn apply_keybindings(&self) {
+        let mut bindings = default_bindings();
+        for (combo, command) in preset_bindings(&self.input.keymap) {
+            bindings.retain(|(bound, _)| *bound != combo);
+            bindings.push((combo, command));
+        }
+        *BINDINGS.lock().unwrap() = overlay(bindings, &self.keybindings);
+        *HOLD_BINDINGS.lock().unwrap() = overlay(default_hold_bindings(), &self.hold_keybindings);
+        HOLD_MS.store(self.input.hold_ms, Ordering::Relaxed);
+        
+        let mut repeats = default_repeats();
+        for (command, repeat) in &self.repeat {
+            if let Some(command) = TilerCommand::from_name(command) {
+                repeats.retain(|(repeating, _)| *repeating as u32 != command as u32);
+                repeats.push((command, *repeat));
+            }
+        }
+        *REPEATS.lock().unwrap() = repeats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, Vec<String>> {
+        Config::parse(text, &[])
+    }
+
+    fn with_overrides(text: &str, overrides: &[(&str, &str)]) -> Result<Config, Vec<String>> {
+        let overrides: Vec<(String, String)> = overrides.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect();
+        Config::parse(text, &overrides)
+    }
+
+    #[test]
+    fn empty_file_is_the_defaults() {
+        assert_eq!(parse(""), Ok(Config::default()));
+        assert_eq!(parse("# nothing set\n\n[layout]\n"), Ok(Config::default()));
+    }
+
+    #[test]
+    fn keys_left_out_keep_their_defaults() {
+        let config = parse("[layout]\nmargin_horizontal = 10\nlimit_policy = \"replace-oldest\"\n\n[animations.scroll]\neasing = \"linear\"\n").unwrap();
+        assert_eq!(config.layout.margin_horizontal, 10);
+        assert_eq!(config.layout.margin_vertical, LayoutConfig::default().margin_vertical);
+        assert_eq!(config.layout.limit_policy, LimitPolicy::ReplaceOldest);
+        assert_eq!(config.animations.scroll.easing, Some(Easing::Linear));
+        assert_eq!(config.animations.scroll.duration_ms, None);
+        assert_eq!(config.appearance, AppearanceConfig::default());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for text in [
+            "margins = 10",
+            "[layout]\nmargin = 10",
+            "[animations.scroll]\nspeed = 2",
+            "[repeat.\"pan-left\"]\ndelay = 100",
+            "[[row_displays]]\nrow = 1\ndisplay = \"DISPLAY2\"\nprimary = true",
+            "[nonsense]",
+        ] {
+            let errors = parse(text).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}", text);
+            assert!(errors[0].contains("unknown"), "{}: {}", text, errors[0]);
+        }
+    }
+
+    #[test]
+    fn wrong_types_and_bad_toml_are_rejected() {
+        for text in [
+            "[layout]\nmargin_horizontal = \"wide\"",
+            "[layout]\nlimit_policy = \"drop\"",
+            "[animations]\neasing = \"bouncy\"",
+            "[appearance]\ntransparency = 300",
+            "[layout\nmargin_horizontal = 10",
+            "[layout]\nmargin_horizontal = ",
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn range_checks() {
+        let cases = [
+            ("[layout]\nmargin_horizontal = -1", "layout.margin_horizontal"),
+            ("[layout]\nmargin_vertical = -1", "layout.margin_vertical"),
+            ("[layout]\nresize_step_percent = 0", "layout.resize_step_percent"),
+            ("[layout]\nresize_step_percent = 51", "layout.resize_step_percent"),
+            ("[layout]\nscratchpad_height_percent = 19", "layout.scratchpad_height_percent"),
+            ("[layout]\nscratchpad_height_percent = 101", "layout.scratchpad_height_percent"),
+            ("[layout]\nvisible_rows = 0", "layout.visible_rows"),
+            ("[layout]\nrow_limit = 0", "layout.row_limit"),
+            ("[layout]\nmax_row_width = 0.0", "layout.max_row_width"),
+            ("[appearance]\nrow_dim = 1.5", "appearance.row_dim"),
+            ("[appearance]\nrow_dim = -0.1", "appearance.row_dim"),
+            ("[animations]\nfps = 29", "animations.fps"),
+            ("[animations]\nfps = 241", "animations.fps"),
+            ("[animations]\nduration_ms = 2001", "animations.duration_ms"),
+            ("[animations.move]\nduration_ms = 2001", "animations.move.duration_ms"),
+            ("[animations.exit]\nduration_ms = 5000", "animations.exit.duration_ms"),
+            ("[input]\nkeymap = \"emacs\"", "input.keymap"),
+            ("[input]\npassthrough = [\"  \"]", "input.passthrough"),
+            ("[input]\nhold_ms = 99", "input.hold_ms"),
+            ("[input]\nhold_ms = 2001", "input.hold_ms"),
+            ("[sharing]\napps = [\"\"]", "sharing.apps"),
+            ("[rotation]\nrows = [0, -1]", "rotation.rows"),
+            ("[rotation]\ninterval_secs = 4", "rotation.interval_secs"),
+            ("[[row_displays]]\nrow = -1\ndisplay = \"DISPLAY2\"", "row_displays[0].row"),
+            ("[[row_displays]]\nrow = 1\ndisplay = \" \"", "row_displays[0].display"),
+            ("[remote]\naddress = \"localhost\"", "remote.address"),
+            ("[remote]\nenabled = true\ntoken = \"short\"", "remote.token"),
+            ("[repeat.\"no-such-command\"]\ndelay_ms = 100", "repeat.\"no-such-command\""),
+            ("[repeat.\"pan-left\"]\nspeedup = 0.0", "repeat.\"pan-left\".speedup"),
+            ("[repeat.\"pan-left\"]\nspeedup = 1.5", "repeat.\"pan-left\".speedup"),
+            ("[repeat.\"pan-left\"]\ninterval_ms = 100\nmin_interval_ms = 200", "repeat.\"pan-left\".min_interval_ms"),
+            ("[keybindings]\n\"Win+Nope\" = \"pan-left\"", "keybindings.\"Win+Nope\""),
+            ("[keybindings]\n\"Win+J\" = \"no-such-command\"", "keybindings.\"Win+J\""),
+            ("[hold_keybindings]\n\"J\" = \"pan-left\"", "hold_keybindings.\"J\""),
+        ];
+        for (text, key) in cases {
+            let errors = parse(text).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", text, errors);
+            assert!(errors[0].starts_with(key), "{}: {}", text, errors[0]);
+        }
+    }
+
+    #[test]
+    fn range_limits_themselves_are_accepted() {
+        let text = "[layout]\nmargin_horizontal = 0\nresize_step_percent = 50\nscratchpad_height_percent = 20\nvisible_rows = 1\nrow_limit = 1\n\
+            [appearance]\nrow_dim = 1.0\n[animations]\nfps = 240\nduration_ms = 2000\n[input]\nhold_ms = 100\n\
+            [rotation]\ninterval_secs = 5\n[remote]\nenabled = true\ntoken = \"0123456789abcdef\"\n\
+            [keybindings]\n\"Win+J\" = \"none\"";
+        assert!(parse(text).is_ok());
+    }
+
+    #[test]
+    fn every_mistake_is_reported() {
+        let errors = parse("[layout]\nmargin_horizontal = -1\nrow_limit = 0\n[input]\nhold_ms = 1").unwrap_err();
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn set_overrides() {
+        let config = with_overrides("[layout]\nmargin_horizontal = 10\n", &[
+            ("layout.margin_horizontal", "20"),
+            ("layout.limit_policy", "refuse"),
+            ("input.keymap", "\"i3\""),
+            ("appearance.hud", "false"),
+            ("rotation.rows", "[1, 2]"),
+            ("animations.scroll.duration_ms", "300"),
+        ]).unwrap();
+        assert_eq!(config.layout.margin_horizontal, 20);
+        assert_eq!(config.layout.limit_policy, LimitPolicy::Refuse);
+        assert_eq!(config.input.keymap, "i3");
+        assert!(!config.appearance.hud);
+        assert_eq!(config.rotation.rows, vec![1, 2]);
+        assert_eq!(config.animations.scroll.duration_ms, Some(300));
+
+        // Later overrides of the same key win
+        let config = with_overrides("", &[("layout.row_limit", "3"), ("layout.row_limit", "5")]).unwrap();
+        assert_eq!(config.layout.row_limit, Some(5));
+
+        // Quoted keys, for tables keyed by combo or command
+        let config = with_overrides("", &[("keybindings.\"Win+J\"", "pan-left")]).unwrap();
+        assert_eq!(config.keybindings.get("Win+J").map(String::as_str), Some("pan-left"));
+    }
+
+    #[test]
+    fn set_override_errors() {
+        let errors = with_overrides("[layout]\nmargin_horizontal = 10\n", &[("layout.margin_horizontal.inner", "1")]).unwrap_err();
+        assert_eq!(errors, vec!["--set layout.margin_horizontal.inner: 'margin_horizontal' is not a section".to_string()]);
+
+        let errors = with_overrides("", &[("layout.", "1")]).unwrap_err();
+        assert_eq!(errors, vec!["--set layout.: missing key".to_string()]);
+
+        // Overridden values are validated like the file's own
+        let errors = with_overrides("", &[("layout.row_limit", "0")]).unwrap_err();
+        assert!(errors[0].starts_with("layout.row_limit"));
+        assert!(with_overrides("", &[("layout.no_such_key", "1")]).is_err());
+    }
+
+    #[test]
+    fn override_values() {
+        assert_eq!(override_value("12"), Value::Integer(12));
+        assert_eq!(override_value("0.5"), Value::Float(0.5));
+        assert_eq!(override_value("true"), Value::Boolean(true));
+        assert_eq!(override_value("\"quoted\""), Value::String("quoted".to_string()));
+        assert_eq!(override_value("plain words"), Value::String("plain words".to_string()));
+        assert_eq!(override_value(""), Value::String(String::new()));
+    }
+
+    #[test]
+    fn combos() {
+        let combo = |modifiers, vk: VIRTUAL_KEY| Ok(KeyCombo { modifiers, vk: vk.0 });
+        let cases = [
+            ("Win+Left", combo(WIN, VK_LEFT)),
+            ("win+ctrl+left", combo(WIN | CTRL, VK_LEFT)),
+            ("Control + Alt + Right", combo(CTRL | ALT, VK_RIGHT)),
+            ("Ctrl+Shift+Win+PgUp", combo(CTRL | SHIFT | WIN, VK_PRIOR)),
+            ("Win+j", Ok(KeyCombo { modifiers: WIN, vk: b'J' as u16 })),
+            ("Win+7", Ok(KeyCombo { modifiers: WIN, vk: b'7' as u16 })),
+            ("Win+Numpad7", combo(WIN, VK_NUMPAD7)),
+            ("Win+F1", combo(WIN, VK_F1)),
+            ("Win+F24", combo(WIN, VK_F24)),
+            ("Win+Backtick", combo(WIN, VK_OEM_3)),
+            ("Win+Win+Up", combo(WIN, VK_UP)),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(parse_combo(spec), expected, "{}", spec);
+        }
+
+        let errors = [
+            ("Left", "needs at least one of Win, Ctrl, Alt or Shift"),
+            ("", "missing key"),
+            ("Win+", "missing key"),
+            ("Hyper+Left", "unknown modifier 'Hyper'"),
+            ("Win+F25", "unknown key 'F25'"),
+            ("Win+F0", "unknown key 'F0'"),
+            ("Win+Numpad10", "unknown key 'Numpad10'"),
+            ("Win+Nope", "unknown key 'Nope'"),
+        ];
+        for (spec, message) in errors {
+            assert_eq!(parse_combo(spec), Err(message.to_string()), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn file_bindings_replace_and_remove() {
+        let entries = BTreeMap::from([
+            ("Win+Left".to_string(), "focus-left".to_string()),
+            ("Win+Right".to_string(), "none".to_string()),
+            ("Win+J".to_string(), "pan-down".to_string()),
+        ]);
+        let bindings = overlay(default_bindings(), &entries);
+        let find = |vk: u16| lookup(&bindings, KeyCombo { modifiers: WIN, vk });
+        assert_eq!(find(VK_LEFT.0), Some(TilerCommand::FocusLeft));
+        assert_eq!(find(VK_RIGHT.0), None);
+        assert_eq!(find(b'J' as u16), Some(TilerCommand::PanDown));
+        assert_eq!(find(VK_UP.0), Some(TilerCommand::PanUp));
+        // NumLock on reports the numpad digit for the arrow
+        assert_eq!(find(VK_NUMPAD8.0), Some(TilerCommand::PanUp));
+    }
+}
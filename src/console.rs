@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Storage::FileSystem::*,
+        System::Console::*,
+    },
+};
+
+use crate::appdata;
+
+// Where output went instead of a console, once set up
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn log_path() -> Option<&'static PathBuf> {
+    LOG_PATH.get()
+}
+
+// The binary runs without a console of its own. Client subcommands print into the
+// terminal they were started from, if any.
+pub fn attach_parent() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS).ok();
+    }
+}
+
+// The tiler prints to a console of its own with --console, and otherwise to the log
+// file, which starts afresh every run except a handoff, which carries on the old
+// instance's log. Rust looks the standard handles up on every write, so swapping them
+// sends all later output there.
+pub fn setup(console: bool, handoff: bool) {
+    if console {
+        if unsafe { AllocConsole() }.is_err() {
+            println!("Warning: Failed to open a console");
+        }
+        return;
+    }
+
+    let Some(path) = appdata::data_path("thymeline.log") else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    // Nothing else writes to it outside a handoff, so emptying it here is safe
+    if !handoff {
+        std::fs::File::create(&path).ok();
+    }
+    // Append-only, so every write lands at the current end of the file: a restarted
+    // instance writes to the log while the old one is still winding down, and neither
+    // overwrites the other
+    let file = unsafe {
+        CreateFileW(
+            &HSTRING::from(path.to_string_lossy().into_owned()),
+            FILE_APPEND_DATA.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_ALWAYS,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    };
+    // Left open for the life of the process
+    if let Ok(file) = file {
+        unsafe {
+            SetStdHandle(STD_OUTPUT_HANDLE, file).ok();
+            SetStdHandle(STD_ERROR_HANDLE, file).ok();
+        }
+        LOG_PATH.set(path).ok();
+    }
+}
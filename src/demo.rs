@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use std::thread;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::TilerCommand;
+
+// Steps advance off the main window's timer; ids 1 to 5 are taken elsewhere
+pub const DEMO_TIMER_ID: usize = 6;
+// The caption is shown again every tick so the OSD stays up for the whole step
+pub const TICK_MS: u32 = 1000;
+const TICKS_PER_STEP: u32 = 3;
+
+const WINDOW_COUNT: usize = 4;
+const DEMO_CLASS: PCWSTR = w!("ThymelineDemoWindow");
+static REGISTER_CLASS: Once = Once::new();
+static OPEN_WINDOWS: AtomicUsize = AtomicUsize::new(0);
+
+// Background of each placeholder (COLORREF, 0x00BBGGRR), so tiles are easy to follow
+const COLORS: [u32; WINDOW_COUNT] = [0x00C0_8040, 0x0040_A060, 0x0030_70D0, 0x00A0_50A0];
+
+enum Action {
+    None,
+    Command(TilerCommand, usize),   // Run against the placeholder at this index
+    CloseWindows,
+}
+
+struct Step {
+    caption: &'static str,
+    action: Action,
+}
+
+// Guided tour run by the tiler on placeholder windows of its own, so nothing the user
+// has open is touched. Each step shows a caption and then does what it describes.
+pub struct Demo {
+    windows: Vec<HWND>,
+    steps: VecDeque<Step>,
+    total: usize,
+    current: Option<(&'static str, u32)>,  // Caption and ticks left
+}
+
+// What the tiler does on one tick of the tour
+pub struct Tick {
+    pub caption: &'static str,
+    pub progress: f32,                          // Share of the tour done, for the OSD bar
+    pub command: Option<(TilerCommand, HWND)>,  // Set when a step begins
+}
+
+fn step(caption: &'static str, action: Action) -> Step {
+    Step { caption, action }
+}
+
+unsafe extern "system" fn demo_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            let mut paint = PAINTSTRUCT::default();
+            let dc = BeginPaint(hwnd, &mut paint);
+            let mut rect = RECT::default();
+            GetClientRect(hwnd, &mut rect).ok();
+
+            let color = COLORS[GetWindowLongPtrW(hwnd, GWLP_USERDATA) as usize % WINDOW_COUNT];
+            let brush = CreateSolidBrush(COLORREF(color));
+            FillRect(dc, &rect, brush);
+            DeleteObject(brush);
+
+            let mut title = [0u16; 64];
+            let len = GetWindowTextW(hwnd, &mut title) as usize;
+            SetBkMode(dc, TRANSPARENT);
+            SetTextColor(dc, COLORREF(0x00FF_FFFF));
+            DrawTextW(dc, &mut title[..len], &mut rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE);
+
+            EndPaint(hwnd, &paint);
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if OPEN_WINDOWS.fetch_sub(1, Ordering::Relaxed) == 1 {
+                PostQuitMessage(0);
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+// The placeholders get a thread of their own, so the tiler moving them never waits on
+// its own message loop. The thread ends once they are all closed.
+fn spawn_windows() -> Vec<HWND> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || unsafe {
+        let instance = GetModuleHandleW(None).unwrap_or_default();
+        REGISTER_CLASS.call_once(|| {
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(demo_wndproc),
+                hInstance: instance.into(),
+                hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+                lpszClassName: DEMO_CLASS,
+                ..Default::default()
+            };
+            RegisterClassW(&class);
+        });
+
+        let mut windows = Vec::new();
+        for index in 0..WINDOW_COUNT {
+            let title = HSTRING::from(format!("Thymeline demo window {}", index + 1));
+            let offset = index as i32 * 40;
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                DEMO_CLASS,
+                &title,
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                120 + offset,
+                120 + offset,
+                640,
+                420,
+                HWND::default(),
+                HMENU::default(),
+                instance,
+                None,
+            );
+            if hwnd.0 != 0 {
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, index as isize);
+                OPEN_WINDOWS.fetch_add(1, Ordering::Relaxed);
+                windows.push(hwnd.0);
+            }
+        }
+        let created = !windows.is_empty();
+        sender.send(windows).ok();
+        if !created {
+            return;
+        }
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, HWND::default(), 0, 0).0 > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+    receiver.recv().unwrap_or_default().into_iter().map(HWND).collect()
+}
+
+impl Demo {
+    // None when the placeholder windows couldn't be created
+    pub fn start() -> Option<Self> {
+        let windows = spawn_windows();
+        if windows.len() < WINDOW_COUNT {
+            windows.iter().for_each(|&hwnd| unsafe { PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok(); });
+            return None;
+        }
+
+        let steps: VecDeque<Step> = [
+            step("Welcome! These demo windows are safe to play with", Action::None),
+            step("Win+Shift+T tiles the focused window", Action::Command(TilerCommand::AddWindow, 0)),
+            step("New tiles join the ribbon, side by side", Action::Command(TilerCommand::AddWindow, 1)),
+            step("The ribbon runs on past the screen edge", Action::Command(TilerCommand::AddWindow, 2)),
+            step("There is always room for one more", Action::Command(TilerCommand::AddWindow, 3)),
+            step("Win+Left pans the view along the ribbon", Action::Command(TilerCommand::PanLeft, 3)),
+            step("Win+Right pans back", Action::Command(TilerCommand::PanRight, 3)),
+            step("Win+Ctrl+Shift+Left moves the focused tile", Action::Command(TilerCommand::MoveLeft, 3)),
+            step("Win+Ctrl+Right makes a tile wider", Action::Command(TilerCommand::ResizeRight, 1)),
+            step("Win+Ctrl+Shift+Down moves a tile to the row below", Action::Command(TilerCommand::MoveDown, 2)),
+            step("Win+Up and Win+Down switch rows", Action::Command(TilerCommand::PanUp, 0)),
+            step("Win+S scrolls to the focused window", Action::Command(TilerCommand::ScrollToWindow, 2)),
+            step("That's the tour. Closing the demo windows", Action::CloseWindows),
+        ].into_iter().collect();
+
+        Some(Self {
+            windows,
+            total: steps.len(),
+            steps,
+            current: None,
+        })
+    }
+
+    // Called every tick; None once the tour is over
+    pub fn tick(&mut self) -> Option<Tick> {
+        let progress = |left: usize| (self.total - left) as f32 / self.total as f32;
+
+        if let Some((caption, ticks)) = self.current.as_mut() {
+            if *ticks > 1 {
+                *ticks -= 1;
+                return Some(Tick { caption, progress: progress(self.steps.len()), command: None });
+            }
+        }
+
+        let next = self.steps.pop_front()?;
+        self.current = Some((next.caption, TICKS_PER_STEP));
+        let command = match next.action {
+            Action::None => None,
+            Action::Command(command, index) => Some((command, self.windows[index])),
+            Action::CloseWindows => {
+                self.close();
+                None
+            }
+        };
+        Some(Tick { caption: next.caption, progress: progress(self.steps.len()), command })
+    }
+
+    // Cutting the tour short closes the placeholders too
+    pub fn close(&self) {
+        for &hwnd in &self.windows {
+            unsafe {
+                PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok();
+            }
+        }
+    }
+}
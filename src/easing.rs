@@ -0,0 +1,60 @@
+use std::time::Duration;
+use serde::Deserialize;
+
+// How an animation's progress maps onto the distance covered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    #[default]
+    EaseOutCubic,   // Fast start, gentle landing
+    EaseInOut,      // Gentle at both ends
+    Linear,
+    Overshoot,      // Runs a little past the target and settles back
+}
+
+impl Easing {
+    // `t` runs from 0 to 1; so does the result, apart from an overshoot
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::Linear => t,
+            Easing::Overshoot => {
+                // Back easing; about 10% past the target at its furthest
+                const BACK: f32 = 1.70158;
+                1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+// Length and shape of one kind of animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Curve {
+    // Lands straight away
+    pub const INSTANT: Curve = Curve { duration: Duration::ZERO, easing: Easing::Linear };
+
+    // Eased progress `elapsed` into the animation
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        if elapsed >= self.duration {
+            return 1.0;
+        }
+        self.easing.apply(elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+}
+
+// Each kind of animation the tiler runs, from the [animations] config section
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curves {
+    pub scroll: Curve,   // The ribbon scrolling, and the scratchpad sliding
+    pub moves: Curve,    // A tile moving or resizing within the ribbon
+    pub entry: Curve,    // A tile growing into its slot when added
+    pub exit: Curve,     // A tile shrinking out of the ribbon when removed
+}
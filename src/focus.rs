@@ -0,0 +1,57 @@
+use windows::Win32::{
+    Foundation::*,
+    System::Threading::{AttachThreadInput, GetCurrentThreadId},
+    UI::Input::KeyboardAndMouse::SetFocus,
+    UI::WindowsAndMessaging::*,
+};
+
+// Bring a window to the foreground even when it belongs to another thread.
+// Windows refuses SetForegroundWindow from a thread that doesn't own the
+// foreground, so our input queue is briefly attached to the foreground
+// thread (and the target's) while focus changes hands.
+pub fn focus_window(hwnd: HWND) -> bool {
+    unsafe {
+        if !IsWindow(hwnd).as_bool() {
+            return false;
+        }
+
+        let foreground = GetForegroundWindow();
+        if foreground == hwnd {
+            return true;
+        }
+
+        if IsIconic(hwnd).as_bool() {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+
+        let current_thread = GetCurrentThreadId();
+        let foreground_thread = GetWindowThreadProcessId(foreground, None);
+        let target_thread = GetWindowThreadProcessId(hwnd, None);
+
+        let attach_foreground = foreground_thread != 0 && foreground_thread != current_thread;
+        let attach_target = target_thread != 0
+            && target_thread != current_thread
+            && target_thread != foreground_thread;
+
+        if attach_foreground {
+            AttachThreadInput(current_thread, foreground_thread, true);
+        }
+        if attach_target {
+            AttachThreadInput(current_thread, target_thread, true);
+        }
+
+        AllowSetForegroundWindow(ASFW_ANY).ok();
+        BringWindowToTop(hwnd).ok();
+        let focused = SetForegroundWindow(hwnd).as_bool();
+        SetFocus(hwnd);
+
+        if attach_target {
+            AttachThreadInput(current_thread, target_thread, false);
+        }
+        if attach_foreground {
+            AttachThreadInput(current_thread, foreground_thread, false);
+        }
+
+        focused
+    }
+}
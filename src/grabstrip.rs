@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::sync::atomic::Ordering;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, RECT, WPARAM},
+    UI::WindowsAndMessaging::{
+        PostMessageW, SystemParametersInfoW, SPI_GETWORKAREA, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+        WM_POINTERDOWN, WM_POINTERUP, WM_POINTERUPDATE,
+    },
+};
+
+use crate::compositor::rgba;
+use crate::kinetic::Tracker;
+use crate::overlay::{Layer, Overlay};
+use crate::{MAIN_HWND, WM_TILER_DRAG};
+
+// WM_TILER_DRAG phases, with the pixels dragged so far in lparam
+pub const DRAG_BEGIN: usize = 0;
+pub const DRAG_MOVE: usize = 1;
+pub const DRAG_END: usize = 2;   // Includes the glide after letting go
+
+// Size and placement in 96-DPI units; it sits in the bottom gap of the tablet profile's wide margins
+const HEIGHT: i32 = 28;
+const BOTTOM_GAP: i32 = 12;
+const WIDTH_SHARE: f32 = 0.8;   // Of the screen's width
+const GRIP_WIDTH: f32 = 48.0;
+
+// Set in the high word of wparam for the first finger down, see IS_POINTER_PRIMARY_WPARAM
+const POINTER_FLAG_PRIMARY: usize = 0x2000;
+
+// A bar along the bottom of the screen to drag the ribbon with a finger or pen, shown in
+// tablet posture. Lives on the main thread like the OSD.
+thread_local! {
+    static STRIP: RefCell<Option<Overlay>> = const { RefCell::new(None) };
+    static DRAG: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+}
+
+fn post(phase: usize, pixels: i32) {
+    unsafe {
+        PostMessageW(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), WM_TILER_DRAG,
+            WPARAM(phase), LPARAM(pixels as isize)).ok();
+    }
+}
+
+// Only the first finger down drags; a second one on the strip is ignored
+fn on_pointer(msg: u32, wparam: WPARAM, lparam: LPARAM) {
+    if (wparam.0 >> 16) & POINTER_FLAG_PRIMARY == 0 {
+        return;
+    }
+    let x = (lparam.0 & 0xFFFF) as i16 as i32;
+    DRAG.with(|drag| {
+        let mut drag = drag.borrow_mut();
+        match msg {
+            WM_POINTERDOWN => {
+                *drag = Some(Tracker::new(x));
+                post(DRAG_BEGIN, 0);
+            }
+            // Also sent while a pen hovers, before and after touching down
+            WM_POINTERUPDATE => {
+                if let Some(tracker) = drag.as_mut() {
+                    tracker.push(x);
+                    post(DRAG_MOVE, tracker.distance());
+                }
+            }
+            WM_POINTERUP => {
+                if let Some(mut tracker) = drag.take() {
+                    tracker.push(x);
+                    post(DRAG_END, tracker.distance() + tracker.glide());
+                }
+            }
+            // Capture lost: the ribbon settles where it was dropped
+            _ => {
+                if let Some(tracker) = drag.take() {
+                    post(DRAG_END, tracker.distance());
+                }
+            }
+        }
+    });
+}
+
+fn work_area() -> RECT {
+    let mut area = RECT::default();
+    unsafe {
+        SystemParametersInfoW(SPI_GETWORKAREA, 0, Some(&mut area as *mut RECT as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0)).ok();
+    }
+    area
+}
+
+// Show the strip, or move it after the screen changed size
+pub fn show() {
+    STRIP.with(|strip| {
+        let mut strip = strip.borrow_mut();
+        if strip.is_none() {
+            *strip = Overlay::new(Layer::GrabStrip, false, Box::new(|canvas| {
+                let grip_left = (canvas.width - GRIP_WIDTH) / 2.0;
+                let middle = canvas.height / 2.0;
+                canvas.fill_rounded_rect(0.0, 0.0, canvas.width, canvas.height, canvas.height / 2.0, rgba(0x20, 0x20, 0x20, 0.35));
+                canvas.fill_rounded_rect(grip_left, middle - 2.0, grip_left + GRIP_WIDTH, middle + 2.0, 2.0, rgba(0xFF, 0xFF, 0xFF, 0.7));
+            }));
+            if let Some(overlay) = strip.as_ref() {
+                overlay.on_pointer(on_pointer);
+            }
+        }
+        let Some(overlay) = strip.as_mut() else {
+            return;
+        };
+
+        let area = work_area();
+        let scale = overlay.scale();
+        let width = ((area.right - area.left) as f32 * WIDTH_SHARE) as i32;
+        let height = (HEIGHT as f32 * scale) as i32;
+        let left = area.left + (area.right - area.left - width) / 2;
+        let bottom = area.bottom - (BOTTOM_GAP as f32 * scale) as i32;
+
+        overlay.show_at(RECT {
+            left,
+            top: bottom - height,
+            right: left + width,
+            bottom,
+        });
+    });
+}
+
+pub fn close() {
+    DRAG.with(|drag| *drag.borrow_mut() = None);
+    STRIP.with(|strip| *strip.borrow_mut() = None);
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::*,
+};
+
+use crate::launch::SlotSize;
+use crate::layered::Layering;
+use crate::{grabstrip, hud, osd, rowstrip, switcher, ManagedWindow, ParkedRibbon, RibbonPosition, RibbonTiler, TileSize};
+
+// Everything a new instance needs to carry on managing the old one's windows,
+// including what each window looked like before it was tiled so it can still be
+// released properly later. Window handles stay valid across the two processes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HandoffState {
+    viewport_index: usize,
+    ribbons: Vec<RibbonSnapshot>,   // One per viewport of the primary display
+    #[serde(default)]
+    monitors: Vec<(isize, RibbonSnapshot)>,   // Ribbons of the other displays
+    floating: Vec<FloatingSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RibbonSnapshot {
+    rows: Vec<(i32, Vec<TileSnapshot>)>,   // Row and its tiles left to right
+    ribbon_offset: i32,
+    vertical_offset: i32,
+    current_row: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TileSnapshot {
+    hwnd: isize,
+    full: bool,    // Read when `size` is missing, from an instance before the narrower widths
+    #[serde(default)]
+    size: Option<SlotSize>,
+    #[serde(default)]
+    width: Option<i32>,    // Custom width in ribbon units
+    #[serde(default)]
+    monitor: isize,
+    original_style: u32,
+    original_ex_style: u32,
+    placement: PlacementSnapshot,
+    keep_style: bool,
+    #[serde(default)]
+    own_size: Option<(i32, i32)>,
+    layering: LayeringSnapshot,
+    #[serde(default)]
+    stacked: Vec<TileSnapshot>,   // Windows stacked behind this tile, next one first
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlacementSnapshot {
+    flags: u32,
+    show_cmd: u32,
+    min_position: (i32, i32),
+    max_position: (i32, i32),
+    normal_position: (i32, i32, i32, i32),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum LayeringSnapshot {
+    None,
+    Attributes { key: u32, alpha: u8, flags: u32 },
+    PerPixel,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FloatingSnapshot {
+    hwnd: isize,
+    layering: LayeringSnapshot,
+}
+
+impl From<Layering> for LayeringSnapshot {
+    fn from(layering: Layering) -> Self {
+        match layering {
+            Layering::None => Self::None,
+            Layering::Attributes { key, alpha, flags } => Self::Attributes { key: key.0, alpha, flags: flags.0 },
+            Layering::PerPixel => Self::PerPixel,
+        }
+    }
+}
+
+impl From<&LayeringSnapshot> for Layering {
+    fn from(snapshot: &LayeringSnapshot) -> Self {
+        match *snapshot {
+            LayeringSnapshot::None => Self::None,
+            LayeringSnapshot::Attributes { key, alpha, flags } => Self::Attributes {
+                key: COLORREF(key),
+                alpha,
+                flags: LAYERED_WINDOW_ATTRIBUTES_FLAGS(flags),
+            },
+            LayeringSnapshot::PerPixel => Self::PerPixel,
+        }
+    }
+}
+
+fn tile_snapshot(window: &ManagedWindow, stacks: &HashMap<isize, Vec<ManagedWindow>>) -> TileSnapshot {
+    let placement = &window.original_placement;
+    let normal = placement.rcNormalPosition;
+    TileSnapshot {
+        hwnd: window.hwnd.0,
+        full: window.position.size == TileSize::Full,
+        size: Some(SlotSize::of(window.position.size)),
+        width: window.position.width,
+        monitor: window.position.monitor,
+        original_style: window.original_style.0,
+        original_ex_style: window.original_ex_style.0,
+        placement: PlacementSnapshot {
+            flags: placement.flags.0,
+            show_cmd: placement.showCmd,
+            min_position: (placement.ptMinPosition.x, placement.ptMinPosition.y),
+            max_position: (placement.ptMaxPosition.x, placement.ptMaxPosition.y),
+            normal_position: (normal.left, normal.top, normal.right, normal.bottom),
+        },
+        keep_style: window.keep_style,
+        own_size: window.own_size,
+        layering: window.original_layering.into(),
+        stacked: stacks.get(&window.hwnd.0)
+            .map(|members| members.iter().map(|member| tile_snapshot(member, stacks)).collect())
+            .unwrap_or_default(),
+    }
+}
+
+fn parked_snapshot(parked: &ParkedRibbon, stacks: &HashMap<isize, Vec<ManagedWindow>>) -> RibbonSnapshot {
+    ribbon_snapshot(&parked.windows, &parked.rows, stacks, parked.ribbon_offset, parked.vertical_offset, parked.current_row)
+}
+
+fn ribbon_snapshot(windows: &HashMap<isize, ManagedWindow>, rows: &HashMap<i32, Vec<isize>>,
+                   stacks: &HashMap<isize, Vec<ManagedWindow>>,
+                   ribbon_offset: i32, vertical_offset: i32, current_row: i32) -> RibbonSnapshot {
+    let mut rows: Vec<(i32, Vec<TileSnapshot>)> = rows.iter()
+        .map(|(&row, order)| (row, order.iter().filter_map(|hwnd| windows.get(hwnd)).map(|window| tile_snapshot(window, stacks)).collect()))
+        .collect();
+    rows.sort_by_key(|(row, _)| *row);
+    RibbonSnapshot { rows, ribbon_offset, vertical_offset, current_row }
+}
+
+// Settles the layout first so the new instance starts from where tiles actually are
+pub fn capture(tiler: &mut RibbonTiler) -> HandoffState {
+    tiler.activate_monitor(crate::monitor::primary_handle());
+    tiler.finish_animations();
+
+    let ribbons = (0..tiler.parked_ribbons.len())
+        .map(|index| if index == tiler.viewport_index {
+            ribbon_snapshot(&tiler.windows, &tiler.rows, &tiler.stacks, tiler.ribbon_offset, tiler.vertical_offset, tiler.current_row)
+        } else {
+            parked_snapshot(&tiler.parked_ribbons[index], &tiler.stacks)
+        })
+        .collect();
+
+    HandoffState {
+        viewport_index: tiler.viewport_index,
+        ribbons,
+        monitors: tiler.monitor_ribbons.iter()
+            .map(|(&monitor, parked)| (monitor, parked_snapshot(parked, &tiler.stacks)))
+            .collect(),
+        floating: tiler.floating_windows.values()
+            .map(|f| FloatingSnapshot { hwnd: f.hwnd.0, layering: f.original_layering.into() })
+            .collect(),
+    }
+}
+
+// Let go of the windows without restoring them, for the instance taking over
+pub fn release(tiler: &mut RibbonTiler) {
+    if let Some(tracker) = tiler.time_tracker.as_mut() {
+        tracker.finish_current();
+    }
+    tiler.stats.store();
+    tiler.mirror = None;
+    osd::close();
+    hud::close();
+    grabstrip::close();
+    rowstrip::close();
+    switcher::close();
+    // Shown again where it was; the new instance doesn't know it was the scratchpad
+    if let Some(pad) = tiler.scratchpad.take() {
+        pad.release();
+    }
+    *tiler.animation_stop_requested.lock().unwrap() = true;
+
+    // The new instance marks hung tiles again if they still are
+    for &hwnd in &tiler.hung {
+        RibbonTiler::set_border_color(HWND(hwnd), crate::DWMWA_COLOR_DEFAULT);
+    }
+    tiler.hung.clear();
+
+    tiler.windows.clear();
+    tiler.rows.clear();
+    tiler.stacks.clear();
+    tiler.parked_ribbons.iter_mut().for_each(|parked| *parked = ParkedRibbon::default());
+    tiler.monitor_ribbons.clear();
+    tiler.floating_windows.clear();
+    println!("Handed windows over to the new instance");
+}
+
+fn managed_window(tile: &TileSnapshot, row: i32) -> ManagedWindow {
+    let (min_x, min_y) = tile.placement.min_position;
+    let (max_x, max_y) = tile.placement.max_position;
+    let (left, top, right, bottom) = tile.placement.normal_position;
+    ManagedWindow {
+        hwnd: HWND(tile.hwnd),
+        original_style: WINDOW_STYLE(tile.original_style),
+        original_ex_style: WINDOW_EX_STYLE(tile.original_ex_style),
+        original_placement: WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            flags: WINDOWPLACEMENT_FLAGS(tile.placement.flags),
+            showCmd: tile.placement.show_cmd,
+            ptMinPosition: POINT { x: min_x, y: min_y },
+            ptMaxPosition: POINT { x: max_x, y: max_y },
+            rcNormalPosition: RECT { left, top, right, bottom },
+        },
+        keep_style: tile.keep_style,
+        own_size: tile.own_size,
+        original_layering: (&tile.layering).into(),
+        position: RibbonPosition {
+            x: 0,
+            row,
+            size: match tile.size {
+                Some(size) => size.tile_size(),
+                None if tile.full => TileSize::Full,
+                None => TileSize::Half,
+            },
+            width: tile.width,
+            monitor: tile.monitor,
+        },
+        animation: None,
+        adopted: Instant::now(),
+        touched: true,
+    }
+}
+
+// Tiles of a snapshot, by row, in order
+fn restore_rows(ribbon: &RibbonSnapshot, windows: &mut HashMap<isize, ManagedWindow>,
+                rows: &mut HashMap<i32, Vec<isize>>, stacks: &mut HashMap<isize, Vec<ManagedWindow>>) -> usize {
+    let mut count = 0;
+    for (row, tiles) in &ribbon.rows {
+        for tile in tiles.iter().filter(|tile| unsafe { IsWindow(HWND(tile.hwnd)).as_bool() }) {
+            if windows.contains_key(&tile.hwnd) {
+                continue;
+            }
+            windows.insert(tile.hwnd, managed_window(tile, *row));
+            rows.entry(*row).or_default().push(tile.hwnd);
+            count += 1;
+            let members: Vec<ManagedWindow> = tile.stacked.iter()
+                .filter(|member| unsafe { IsWindow(HWND(member.hwnd)).as_bool() })
+                .map(|member| managed_window(member, *row))
+                .collect();
+            if !members.is_empty() {
+                count += members.len();
+                stacks.insert(tile.hwnd, members);
+            }
+        }
+    }
+
+    // x follows from the row order
+    for (&row, order) in rows.iter() {
+        let mut x = 0;
+        for hwnd in order {
+            if let Some(window) = windows.get_mut(hwnd) {
+                window.position.row = row;
+                window.position.x = x;
+                x += window.position.units();
+            }
+        }
+    }
+    count
+}
+
+// Windows closed during the handover are dropped. Ribbons for viewports or displays
+// this instance doesn't have join the active one, at the end of their rows.
+pub fn resume(tiler: &mut RibbonTiler, state: HandoffState) {
+    let live = |hwnd: isize| unsafe { IsWindow(HWND(hwnd)).as_bool() };
+    let active = state.viewport_index.min(tiler.parked_ribbons.len() - 1);
+    tiler.viewport_index = active;
+    tiler.viewport = RibbonTiler::viewport_bounds(&tiler.viewport_splits, active, tiler.monitor_width);
+
+    let mut count = 0;
+    for (index, ribbon) in state.ribbons.into_iter().enumerate() {
+        let target = if index < tiler.parked_ribbons.len() { index } else { active };
+        count += if target == active {
+            restore_rows(&ribbon, &mut tiler.windows, &mut tiler.rows, &mut tiler.stacks)
+        } else {
+            let parked = &mut tiler.parked_ribbons[target];
+            restore_rows(&ribbon, &mut parked.windows, &mut parked.rows, &mut tiler.stacks)
+        };
+
+        if index == state.viewport_index {
+            tiler.ribbon_offset = ribbon.ribbon_offset;
+            tiler.ribbon_offset_target = ribbon.ribbon_offset;
+            tiler.vertical_offset = ribbon.vertical_offset;
+            tiler.vertical_offset_target = ribbon.vertical_offset;
+            tiler.current_row = ribbon.current_row;
+        } else if target != active {
+            let parked = &mut tiler.parked_ribbons[target];
+            parked.ribbon_offset = ribbon.ribbon_offset;
+            parked.vertical_offset = ribbon.vertical_offset;
+            parked.current_row = ribbon.current_row;
+        }
+    }
+
+    let primary = crate::monitor::primary_handle();
+    for (monitor, ribbon) in state.monitors {
+        if tiler.displays.contains_key(&monitor) {
+            let parked = tiler.monitor_ribbons.entry(monitor).or_default();
+            count += restore_rows(&ribbon, &mut parked.windows, &mut parked.rows, &mut tiler.stacks);
+            parked.ribbon_offset = ribbon.ribbon_offset;
+            parked.vertical_offset = ribbon.vertical_offset;
+            parked.current_row = ribbon.current_row;
+        } else {
+            count += restore_rows(&ribbon, &mut tiler.windows, &mut tiler.rows, &mut tiler.stacks);
+        }
+    }
+    // Everything left in the active ribbon is on the primary display
+    for window in tiler.windows.values_mut() {
+        window.position.monitor = primary;
+    }
+
+    for floating in state.floating.iter().filter(|f| live(f.hwnd)) {
+        tiler.floating_windows.insert(floating.hwnd, crate::FloatingWindow {
+            hwnd: HWND(floating.hwnd),
+            original_layering: (&floating.layering).into(),
+        });
+    }
+
+    println!("Took over {} windows from the previous instance", count);
+    tiler.reapply_rule_transparency();
+    tiler.needs_ribbon_recalc = true;
+}
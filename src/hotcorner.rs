@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::POINT,
+    UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON, VK_RBUTTON},
+    UI::WindowsAndMessaging::{GetCursorPos, GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN},
+};
+
+use crate::TilerCommand;
+
+pub const HOT_CORNER_TIMER_ID: usize = 2;
+pub const HOT_CORNER_POLL_MS: u32 = 100;
+pub const DEFAULT_DWELL_MS: u64 = 300;
+
+// Pixels from the screen edge that count as being in a corner or on an edge
+const HOT_ZONE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Corner {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "top-left" => Some(Self::TopLeft),
+            "top-right" => Some(Self::TopRight),
+            "bottom-left" => Some(Self::BottomLeft),
+            "bottom-right" => Some(Self::BottomRight),
+            "top" => Some(Self::Top),
+            "bottom" => Some(Self::Bottom),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+
+    // Corners win over the two edges they join
+    fn at(point: POINT, width: i32, height: i32) -> Option<Self> {
+        let left = point.x < HOT_ZONE;
+        let right = point.x >= width - HOT_ZONE;
+        let top = point.y < HOT_ZONE;
+        let bottom = point.y >= height - HOT_ZONE;
+
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(Self::TopLeft),
+            (_, true, true, _) => Some(Self::TopRight),
+            (true, _, _, true) => Some(Self::BottomLeft),
+            (_, true, _, true) => Some(Self::BottomRight),
+            (_, _, true, _) => Some(Self::Top),
+            (_, _, _, true) => Some(Self::Bottom),
+            (true, _, _, _) => Some(Self::Left),
+            (_, true, _, _) => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+// Cursor dwell tracking for the primary display's corners and edges
+pub struct HotCorners {
+    bindings: Vec<(Corner, TilerCommand)>,
+    dwell: Duration,
+    hovered: Option<(Corner, Instant)>,
+    fired: bool,    // Set once the dwell triggered, until the cursor leaves
+}
+
+impl HotCorners {
+    pub fn new(bindings: Vec<(Corner, TilerCommand)>, dwell: Duration) -> Self {
+        Self {
+            bindings,
+            dwell,
+            hovered: None,
+            fired: false,
+        }
+    }
+
+    // Called from the poll timer; returns the command to run when a dwell completes
+    pub fn poll(&mut self) -> Option<TilerCommand> {
+        let mut point = POINT::default();
+        let (corner, dragging) = unsafe {
+            if GetCursorPos(&mut point).is_err() {
+                return None;
+            }
+            let width = GetSystemMetrics(SM_CXSCREEN);
+            let height = GetSystemMetrics(SM_CYSCREEN);
+            // Don't fire while a drag or selection is pressed against the edge
+            let dragging = GetAsyncKeyState(VK_LBUTTON.0 as i32) < 0 || GetAsyncKeyState(VK_RBUTTON.0 as i32) < 0;
+            (Corner::at(point, width, height), dragging)
+        };
+
+        let Some(corner) = corner.filter(|c| self.bindings.iter().any(|(bound, _)| bound == c)) else {
+            self.hovered = None;
+            self.fired = false;
+            return None;
+        };
+
+        match self.hovered {
+            Some((hovered, since)) if hovered == corner => {
+                if self.fired || dragging || since.elapsed() < self.dwell {
+                    return None;
+                }
+                self.fired = true;
+                self.bindings.iter().find(|(bound, _)| *bound == corner).map(|(_, command)| *command)
+            }
+            _ => {
+                self.hovered = Some((corner, Instant::now()));
+                self.fired = false;
+                None
+            }
+        }
+    }
+}
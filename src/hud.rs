@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use windows::Win32::{
+    Foundation::RECT,
+    UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN},
+};
+
+use crate::compositor::rgba;
+use crate::osd;
+use crate::overlay::{Layer, Overlay};
+
+const HIDE_AFTER_MS: u32 = 900;
+
+// Size and placement in 96-DPI units
+const WIDTH: i32 = 160;
+const HEIGHT: i32 = 36;
+const TOP_GAP: i32 = 24;
+
+// Where the view is, "Row 2 · 3/7", at the top of the screen while panning. Lives on
+// the main thread like the OSD.
+struct Hud {
+    overlay: Overlay,
+    content: Rc<RefCell<String>>,
+}
+
+impl Hud {
+    fn new() -> Option<Self> {
+        let content = Rc::new(RefCell::new(String::new()));
+        let painted = content.clone();
+        let overlay = Overlay::new(Layer::Hud, true, Box::new(move |canvas| {
+            canvas.fill_rounded_rect(0.0, 0.0, canvas.width, canvas.height, 8.0, rgba(0x20, 0x20, 0x20, 0.8));
+            canvas.text(&painted.borrow(), 0.0, 0.0, canvas.width, canvas.height, 15.0, rgba(0xFF, 0xFF, 0xFF, 1.0));
+        }))?;
+
+        Some(Self { overlay, content })
+    }
+
+    fn show(&mut self, text: &str) {
+        *self.content.borrow_mut() = text.to_string();
+
+        let scale = self.overlay.scale();
+        let screen_width = unsafe { GetSystemMetrics(SM_CXSCREEN) };
+        let width = (WIDTH as f32 * scale) as i32;
+        let height = (HEIGHT as f32 * scale) as i32;
+        let left = (screen_width - width) / 2;
+        let top = (TOP_GAP as f32 * scale) as i32;
+
+        self.overlay.show_at(RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        });
+        self.overlay.hide_after(HIDE_AFTER_MS);
+    }
+}
+
+thread_local! {
+    static HUD: RefCell<Option<Hud>> = const { RefCell::new(None) };
+}
+
+// Show `text`, hiding again shortly after the last call. Held back whenever the OSD is.
+pub fn show(text: &str) {
+    if osd::muted() {
+        return;
+    }
+    HUD.with(|hud| {
+        let mut hud = hud.borrow_mut();
+        if hud.is_none() {
+            *hud = Hud::new();
+        }
+        if let Some(hud) = hud.as_mut() {
+            hud.show(text);
+        }
+    });
+}
+
+pub fn close() {
+    HUD.with(|hud| *hud.borrow_mut() = None);
+}
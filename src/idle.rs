@@ -0,0 +1,25 @@
+use std::time::Duration;
+use windows::Win32::{
+    System::SystemInformation::GetTickCount,
+    UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+};
+
+// How often the message loop checks for idleness
+pub const IDLE_CHECK_INTERVAL_MS: u32 = 30_000;
+pub const IDLE_TIMER_ID: usize = 1;
+
+// Time since the last keyboard or mouse input in this session
+pub fn user_idle_time() -> Duration {
+    unsafe {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return Duration::ZERO;
+        }
+
+        // Tick counts wrap after ~49 days, wrapping_sub keeps the difference correct
+        Duration::from_millis(GetTickCount().wrapping_sub(info.dwTime) as u64)
+    }
+}
@@ -0,0 +1,576 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Security::*,
+        Storage::FileSystem::*,
+        System::Pipes::*,
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+        UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowRect, IsWindow, IsWindowVisible},
+    },
+};
+
+use crate::handoff::{self, HandoffState};
+use crate::launch::{self, StartupApp};
+use crate::stats::Stats;
+use crate::templates;
+use crate::{jsonrpc, labels, post_macro, post_tiler_command, request_shutdown, session, HANDED_OFF, window_info, RibbonTiler, TilerCommand, TILER};
+
+// Bumped whenever a request or response changes shape incompatibly
+pub const PROTOCOL_VERSION: u32 = 1;
+// Oldest client protocol this server still answers
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+// Token of the last layout handed out, waiting for its commit
+static HANDOFF_TOKEN: Mutex<Option<u64>> = Mutex::new(None);
+
+// Optional protocol features, reported in the hello response so clients can feature-detect
+const CAPABILITIES: &[&str] = &["command", "state", "jsonrpc", "stale", "quit", "handoff", "handoff_commit", "macros", "stats", "layouts", "templates"];
+
+pub fn pipe_name() -> String {
+    format!(r"\\.\pipe\{}", session::scoped_name("thymeline"))
+}
+
+// One JSON object per line from the client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Hello {
+        protocol_version: u32,
+        #[serde(default)]
+        client: Option<String>,
+    },
+    Command {
+        command: String,
+        #[serde(default)]
+        hwnd: Option<isize>,   // Defaults to the foreground window
+    },
+    State,
+    Stale,
+    Quit,   // Restore every window and exit, as Ctrl+C in the console does
+    Handoff,   // Send the layout to the caller, which commits to take it over
+    HandoffCommit {
+        token: u64,   // From the handoff response
+    },
+    SaveMacro {
+        name: String,   // Keeps the last recording under this name
+    },
+    PlayMacro {
+        #[serde(default)]
+        name: Option<String>,   // Defaults to the last recording
+        #[serde(default)]
+        navigation_only: bool,   // Skip every step but the navigation commands, for links
+    },
+    Stats,
+    ApplyLayout {
+        apps: Vec<StartupApp>,   // As in a startup layout file
+    },
+    ApplyTemplate {
+        name: String,   // One of the built-in templates
+        #[serde(default)]
+        row: i32,       // Row the template's first row lands on
+    },
+}
+
+// Answers one request; the pipe answers everything, the remote server only some
+pub type Handler = fn(Request) -> Response;
+
+// One JSON object per line back to the client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Hello {
+        protocol_version: u32,
+        min_protocol_version: u32,
+        server_version: &'static str,
+        capabilities: Vec<&'static str>,
+        commands: Vec<&'static str>,
+        args: Vec<String>,   // Command line the instance was started with
+    },
+    Ok,
+    State(LayoutState),
+    Stale(StaleReport),
+    Handoff {
+        token: u64,   // Echoed in the commit, so only the caller that got the layout can end this instance
+        #[serde(flatten)]
+        state: HandoffState,
+    },
+    Stats(Stats),
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    UnsupportedVersion,
+    UnknownCommand,
+    Unavailable,
+    Unauthorized,   // Remote client without the right token
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutState {
+    current_row: i32,
+    ribbon_offset: i32,
+    vertical_offset: i32,
+    monitor_width: i32,
+    monitor_height: i32,
+    dry_run: bool,
+    recording_macro: bool,
+    macros: Vec<String>,
+    windows: Vec<WindowState>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowState {
+    hwnd: isize,
+    row: i32,
+    x: i32,
+    width: i32,
+    size: &'static str,
+    title: String,
+    exe: Option<String>,
+    stacked: usize,   // Windows stacked behind this tile
+    icon: Option<String>,   // App icon as a base64 PNG
+}
+
+// Why a tile looks like it no longer belongs in the ribbon
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleReason {
+    Closed,
+    Hung,       // Not answering messages
+    Hidden,
+    ZeroSize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StaleReport {
+    windows: Vec<StaleWindow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StaleWindow {
+    hwnd: isize,
+    row: i32,
+    reason: StaleReason,
+    title: String,
+    exe: Option<String>,
+}
+
+// Probed without holding the tiler lock, since each hung window costs a timeout
+fn stale_report(tiles: Vec<(HWND, i32)>) -> StaleReport {
+    let windows = tiles.into_iter()
+        .filter_map(|(hwnd, row)| {
+            let reason = unsafe {
+                let mut rect = RECT::default();
+                if !IsWindow(hwnd).as_bool() {
+                    StaleReason::Closed
+                } else if window_info::is_hung(hwnd) {
+                    StaleReason::Hung
+                } else if !IsWindowVisible(hwnd).as_bool() {
+                    StaleReason::Hidden
+                } else if GetWindowRect(hwnd, &mut rect).is_err() || rect.right <= rect.left || rect.bottom <= rect.top {
+                    StaleReason::ZeroSize
+                } else {
+                    return None;
+                }
+            };
+            Some(StaleWindow {
+                hwnd: hwnd.0,
+                row,
+                reason,
+                title: window_info::window_title(hwnd),
+                exe: window_info::process_name(hwnd),
+            })
+        })
+        .collect();
+    StaleReport { windows }
+}
+
+fn layout_state(tiler: &RibbonTiler) -> LayoutState {
+    let mut windows: Vec<WindowState> = tiler.windows.values()
+        .map(|w| WindowState {
+            hwnd: w.hwnd.0,
+            row: w.position.row,
+            x: tiler.to_pixels(w.position.x),
+            width: tiler.to_pixels(tiler.get_tile_width(&w.position)),
+            size: if w.position.width.is_some() { "custom" } else { w.position.size.name() },
+            title: tiler.labels.get(&w.hwnd.0).map_or_else(|| window_info::window_title(w.hwnd), |label| label.title.clone()),
+            exe: window_info::process_name(w.hwnd),
+            icon: tiler.labels.get(&w.hwnd.0).and_then(|label| label.icon.as_ref()).map(|icon| labels::base64(&icon.png())),
+            stacked: tiler.stacks.get(&w.hwnd.0).map_or(0, Vec::len),
+        })
+        .collect();
+    windows.sort_by_key(|w| (w.row, w.x));
+
+    LayoutState {
+        current_row: tiler.current_row,
+        ribbon_offset: tiler.to_pixels(tiler.ribbon_offset_target),
+        vertical_offset: tiler.vertical_offset_target,
+        monitor_width: tiler.monitor_width,
+        monitor_height: tiler.monitor_height,
+        dry_run: tiler.dry_run,
+        recording_macro: tiler.macros.is_recording(),
+        macros: tiler.macros.names(),
+        windows,
+    }
+}
+
+pub fn command_names() -> Vec<&'static str> {
+    TilerCommand::ALL.iter().map(|c| c.name()).collect()
+}
+
+pub fn error(code: ErrorCode, message: impl Into<String>) -> Response {
+    Response::Error {
+        code,
+        message: message.into(),
+    }
+}
+
+// An error reply as one line of the native protocol
+pub fn error_line(code: ErrorCode, message: &str) -> String {
+    serde_json::to_string(&error(code, message)).unwrap_or_default()
+}
+
+pub fn handle_request(request: Request) -> Response {
+    match request {
+        Request::Hello { protocol_version, client } => {
+            if protocol_version < MIN_PROTOCOL_VERSION {
+                return error(
+                    ErrorCode::UnsupportedVersion,
+                    format!("Protocol {} is no longer supported (minimum {})", protocol_version, MIN_PROTOCOL_VERSION),
+                );
+            }
+            if let Some(client) = client {
+                println!("IPC client connected: {} (protocol {})", client, protocol_version);
+            }
+            Response::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                server_version: env!("CARGO_PKG_VERSION"),
+                capabilities: CAPABILITIES.to_vec(),
+                commands: command_names(),
+                args: std::env::args().skip(1).collect(),
+            }
+        }
+        Request::Command { command, hwnd } => {
+            let Some(command) = TilerCommand::from_name(&command) else {
+                return error(ErrorCode::UnknownCommand, format!("Unknown command '{}'", command));
+            };
+            let hwnd = hwnd.map(HWND).unwrap_or_else(|| unsafe { GetForegroundWindow() });
+            if post_tiler_command(command, hwnd) {
+                Response::Ok
+            } else {
+                error(ErrorCode::Unavailable, "Tiler is not running")
+            }
+        }
+        Request::State => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let response = match tiler_arc.lock() {
+                Ok(tiler) => Response::State(layout_state(&tiler)),
+                Err(_) => error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            response
+        }
+        Request::Stale => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let tiles: Vec<(HWND, i32)> = match tiler_arc.lock() {
+                Ok(tiler) => tiler.windows.values().map(|w| (w.hwnd, w.position.row)).collect(),
+                Err(_) => return error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            Response::Stale(stale_report(tiles))
+        }
+        Request::Stats => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let response = match tiler_arc.lock() {
+                Ok(mut tiler) => {
+                    tiler.stats.settle();
+                    Response::Stats(tiler.stats.snapshot())
+                }
+                Err(_) => error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            response
+        }
+        Request::Handoff => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let state = match tiler_arc.lock() {
+                Ok(mut tiler) => handoff::capture(&mut tiler),
+                Err(_) => return error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            // Nothing is let go yet: until the caller commits, this instance carries on as before
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64);
+            let token = nanos ^ ((std::process::id() as u64) << 32);
+            *HANDOFF_TOKEN.lock().unwrap() = Some(token);
+            Response::Handoff { token, state }
+        }
+        Request::HandoffCommit { token } => {
+            let mut pending = HANDOFF_TOKEN.lock().unwrap();
+            if *pending != Some(token) {
+                return error(ErrorCode::BadRequest, "No handoff is waiting for this token");
+            }
+            *pending = None;
+            // Exit without restoring anything, the caller has the layout
+            HANDED_OFF.store(true, Ordering::Relaxed);
+            if request_shutdown() {
+                Response::Ok
+            } else {
+                HANDED_OFF.store(false, Ordering::Relaxed);
+                error(ErrorCode::Unavailable, "Tiler is not running")
+            }
+        }
+        Request::SaveMacro { name } => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let saved = match tiler_arc.lock() {
+                Ok(mut tiler) => tiler.macros.save(&name),
+                Err(_) => return error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            match saved {
+                Ok(steps) => {
+                    println!("Saved a macro of {} commands as '{}'", steps, name);
+                    Response::Ok
+                }
+                Err(message) => error(ErrorCode::BadRequest, message),
+            }
+        }
+        Request::PlayMacro { name, navigation_only } => {
+            let Some(tiler_arc) = TILER.lock().unwrap().clone() else {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            };
+            let queued = match tiler_arc.lock() {
+                Ok(mut tiler) => tiler.macros.play(name.as_deref(), navigation_only),
+                Err(_) => return error(ErrorCode::Unavailable, "Tiler state is poisoned"),
+            };
+            if !queued {
+                let what = match name {
+                    None => "No macro has been recorded".to_string(),
+                    Some(name) if navigation_only => format!("No macro named '{}' with navigation steps a link may run", name),
+                    Some(name) => format!("No macro named '{}'", name),
+                };
+                return error(ErrorCode::BadRequest, what);
+            }
+            post_macro(0);
+            Response::Ok
+        }
+        Request::ApplyLayout { apps } => {
+            if TILER.lock().unwrap().is_none() {
+                return error(ErrorCode::Unavailable, "Tiler is not running");
+            }
+            launch::apply(apps, false);
+            Response::Ok
+        }
+        Request::ApplyTemplate { name, row } => apply_template(&name, row, true),
+        Request::Quit => {
+            if request_shutdown() {
+                Response::Ok
+            } else {
+                error(ErrorCode::Unavailable, "Tiler is not running")
+            }
+        }
+    }
+}
+
+// Untrusted callers get the same confirmation before launching as an imported layout
+pub fn apply_template(name: &str, row: i32, trusted: bool) -> Response {
+    let Some(apps) = templates::get(name, row) else {
+        return error(ErrorCode::BadRequest, format!("No template named '{}'", name));
+    };
+    if TILER.lock().unwrap().is_none() {
+        return error(ErrorCode::Unavailable, "Tiler is not running");
+    }
+    launch::apply(apps, trusted);
+    Response::Ok
+}
+
+// Answer one line in whichever protocol it was written in
+fn respond(line: &str, handler: Handler) -> Option<String> {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) if line.contains("\"jsonrpc\"") => return Some(jsonrpc::parse_error(&e.to_string()).to_string()),
+        Err(e) => return serde_json::to_string(&error(ErrorCode::BadRequest, e.to_string())).ok(),
+    };
+
+    if jsonrpc::is_jsonrpc(&value) {
+        return jsonrpc::handle(value, handler).map(|reply| reply.to_string());
+    }
+
+    let response = match serde_json::from_value::<Request>(value) {
+        Ok(request) => handler(request),
+        Err(e) => error(ErrorCode::BadRequest, e.to_string()),
+    };
+    serde_json::to_string(&response).ok()
+}
+
+// Blocking byte stream over one connected pipe instance
+struct PipeStream(HANDLE);
+
+impl Read for PipeStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0u32;
+        match unsafe { ReadFile(self.0, Some(buf), Some(&mut read), None) } {
+            Ok(()) => Ok(read as usize),
+            Err(e) if e.code() == ERROR_BROKEN_PIPE.to_hresult() => Ok(0),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+}
+
+impl Write for PipeStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0u32;
+        unsafe { WriteFile(self.0, Some(buf), Some(&mut written), None) }
+            .map_err(io::Error::other)?;
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Answer requests line by line until the client hangs up; shared with the remote server
+pub fn serve(reader: impl BufRead, mut writer: impl Write, handler: Handler) {
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(mut payload) = respond(&line, handler) else {
+            continue;
+        };
+        payload.push('\n');
+        if writer.write_all(payload.as_bytes()).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_client(pipe: HANDLE) {
+    serve(BufReader::new(PipeStream(pipe)), PipeStream(pipe), handle_request);
+
+    unsafe {
+        DisconnectNamedPipe(pipe).ok();
+        CloseHandle(pipe).ok();
+    }
+}
+
+// From System::SystemServices, which isn't otherwise needed
+const SECURITY_DESCRIPTOR_REVISION: u32 = 1;
+
+// A security descriptor whose DACL lets only the current user in, kept alive with
+// the buffers it points into
+struct OwnerOnly {
+    _user: Vec<u64>,
+    _acl: Vec<u64>,
+    descriptor: Box<SECURITY_DESCRIPTOR>,
+}
+
+impl OwnerOnly {
+    fn new() -> Option<Self> {
+        unsafe {
+            let mut token = HANDLE::default();
+            OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).ok()?;
+            let mut size = 0u32;
+            GetTokenInformation(token, TokenUser, None, 0, &mut size).ok();
+            // u64s, so the TOKEN_USER and ACL laid over them are aligned
+            let mut user = vec![0u64; (size as usize).div_ceil(8)];
+            let read = GetTokenInformation(token, TokenUser, Some(user.as_mut_ptr() as *mut _), size, &mut size);
+            CloseHandle(token).ok();
+            read.ok()?;
+            let sid = (*(user.as_ptr() as *const TOKEN_USER)).User.Sid;
+
+            let acl_size = std::mem::size_of::<ACL>() + std::mem::size_of::<ACCESS_ALLOWED_ACE>() + GetLengthSid(sid) as usize;
+            let mut acl = vec![0u64; acl_size.div_ceil(8)];
+            let acl_ptr = acl.as_mut_ptr() as *mut ACL;
+            InitializeAcl(acl_ptr, acl_size as u32, ACL_REVISION).ok()?;
+            AddAccessAllowedAce(acl_ptr, ACL_REVISION, GENERIC_ALL.0, sid).ok()?;
+
+            let mut descriptor = Box::<SECURITY_DESCRIPTOR>::default();
+            let pointer = PSECURITY_DESCRIPTOR(&mut *descriptor as *mut SECURITY_DESCRIPTOR as *mut _);
+            InitializeSecurityDescriptor(pointer, SECURITY_DESCRIPTOR_REVISION).ok()?;
+            SetSecurityDescriptorDacl(pointer, true, Some(acl_ptr), false).ok()?;
+
+            Some(Self { _user: user, _acl: acl, descriptor })
+        }
+    }
+
+    fn attributes(&mut self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: &mut *self.descriptor as *mut SECURITY_DESCRIPTOR as *mut _,
+            bInheritHandle: false.into(),
+        }
+    }
+}
+
+// Accept clients on the session's pipe, one thread per connection. The pipe quits,
+// hands off and launches apps, so only this user may open it, and the first instance
+// fails if another process already squats the name.
+pub fn start_server() {
+    thread::spawn(|| {
+        let name = HSTRING::from(pipe_name());
+        let Some(mut security) = OwnerOnly::new() else {
+            println!("Warning: Failed to restrict IPC pipe {} to this user; not listening", name);
+            return;
+        };
+        let attributes = security.attributes();
+        let mut first = true;
+
+        loop {
+            let open_mode = if first { PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE } else { PIPE_ACCESS_DUPLEX };
+            let pipe = unsafe {
+                CreateNamedPipeW(
+                    &name,
+                    open_mode,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    PIPE_UNLIMITED_INSTANCES,
+                    4096,
+                    4096,
+                    0,
+                    Some(&attributes),
+                )
+            };
+            if pipe.is_invalid() {
+                println!("Warning: Failed to create IPC pipe {}{}", name,
+                    if first { " (is another process using the name?)" } else { "" });
+                return;
+            }
+            first = false;
+
+            // A client that connects between create and connect reports ERROR_PIPE_CONNECTED
+            if let Err(e) = unsafe { ConnectNamedPipe(pipe, None) } {
+                if e.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+                    unsafe {
+                        CloseHandle(pipe).ok();
+                    }
+                    continue;
+                }
+            }
+
+            thread::spawn(move || handle_client(pipe));
+        }
+    });
+}
@@ -0,0 +1,186 @@
+use serde_json::{json, Map, Value};
+
+use crate::ipc::{self, ErrorCode, Handler, Request, Response};
+
+// Standard JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// Implementation-defined range for tiler errors
+const SERVER_ERROR: i64 = -32000;
+
+const METHODS: &[&str] = &["hello", "command", "state", "stale", "quit", "save_macro", "play_macro", "stats", "apply_layout", "apply_template", "rpc.discover"];
+
+// True when a line should be answered as JSON-RPC rather than the native protocol
+pub fn is_jsonrpc(value: &Value) -> bool {
+    value.is_array() || value.get("jsonrpc").is_some()
+}
+
+// Answer one JSON-RPC message or batch; None when every entry was a notification
+pub fn handle(message: Value, handler: Handler) -> Option<Value> {
+    match message {
+        Value::Array(batch) if batch.is_empty() => Some(error_response(Value::Null, INVALID_REQUEST, "Empty batch", None)),
+        Value::Array(batch) => {
+            let responses: Vec<Value> = batch.into_iter().filter_map(|call| handle_call(call, handler)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => handle_call(call, handler),
+    }
+}
+
+pub fn parse_error(message: &str) -> Value {
+    error_response(Value::Null, PARSE_ERROR, message, None)
+}
+
+fn handle_call(call: Value, handler: Handler) -> Option<Value> {
+    let Value::Object(mut call) = call else {
+        return Some(error_response(Value::Null, INVALID_REQUEST, "Request must be an object", None));
+    };
+
+    let id = call.remove("id");
+    let respond_with = id.clone().unwrap_or(Value::Null);
+
+    if call.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Some(error_response(respond_with, INVALID_REQUEST, "jsonrpc must be \"2.0\"", None));
+    }
+    let Some(method) = call.get("method").and_then(Value::as_str).map(str::to_string) else {
+        return Some(error_response(respond_with, INVALID_REQUEST, "Missing method", None));
+    };
+
+    let outcome = dispatch(&method, call.remove("params"), handler);
+
+    // Notifications (no id) never get a reply, even on error
+    id.as_ref()?;
+
+    Some(match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": respond_with, "result": result }),
+        Err((code, message, data)) => error_response(respond_with, code, &message, data),
+    })
+}
+
+type CallError = (i64, String, Option<Value>);
+
+fn dispatch(method: &str, params: Option<Value>, handler: Handler) -> Result<Value, CallError> {
+    if method == "rpc.discover" {
+        return Ok(discover());
+    }
+    if !METHODS.contains(&method) {
+        return Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method), None));
+    }
+
+    // Params map onto the native request, with the method name as its type tag
+    let mut fields = match params {
+        None | Some(Value::Null) => Map::new(),
+        Some(Value::Object(fields)) => fields,
+        Some(_) => return Err((INVALID_PARAMS, "params must be an object".to_string(), None)),
+    };
+    fields.insert("type".to_string(), Value::String(method.to_string()));
+
+    let request: Request = serde_json::from_value(Value::Object(fields))
+        .map_err(|e| (INVALID_PARAMS, e.to_string(), None))?;
+
+    match handler(request) {
+        Response::Error { code, message } => {
+            let rpc_code = match code {
+                ErrorCode::UnknownCommand | ErrorCode::BadRequest => INVALID_PARAMS,
+                ErrorCode::UnsupportedVersion | ErrorCode::Unavailable | ErrorCode::Unauthorized => SERVER_ERROR,
+            };
+            Err((rpc_code, message, Some(json!({ "code": code }))))
+        }
+        response => {
+            let mut result = serde_json::to_value(response).unwrap_or(Value::Null);
+            if let Value::Object(fields) = &mut result {
+                fields.remove("type");
+            }
+            Ok(result)
+        }
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+// OpenRPC description of the methods, for client generators
+fn discover() -> Value {
+    json!({
+        "openrpc": "1.2.6",
+        "info": {
+            "title": "Thymeline",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": [
+            {
+                "name": "hello",
+                "params": [
+                    { "name": "protocol_version", "required": true, "schema": { "type": "integer" } },
+                    { "name": "client", "schema": { "type": "string" } },
+                ],
+                "result": { "name": "hello", "schema": { "type": "object" } },
+            },
+            {
+                "name": "command",
+                "params": [
+                    { "name": "command", "required": true, "schema": { "type": "string", "enum": ipc::command_names() } },
+                    { "name": "hwnd", "schema": { "type": "integer" } },
+                ],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+            {
+                "name": "state",
+                "params": [],
+                "result": { "name": "state", "schema": { "type": "object" } },
+            },
+            {
+                "name": "stale",
+                "params": [],
+                "result": { "name": "stale", "schema": { "type": "object" } },
+            },
+            {
+                "name": "quit",
+                "params": [],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+            {
+                "name": "save_macro",
+                "params": [
+                    { "name": "name", "required": true, "schema": { "type": "string" } },
+                ],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+            {
+                "name": "play_macro",
+                "params": [
+                    { "name": "name", "schema": { "type": "string" } },
+                    { "name": "navigation_only", "schema": { "type": "boolean" } },
+                ],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+            {
+                "name": "stats",
+                "params": [],
+                "result": { "name": "stats", "schema": { "type": "object" } },
+            },
+            {
+                "name": "apply_layout",
+                "params": [
+                    { "name": "apps", "required": true, "schema": { "type": "array", "items": { "type": "object" } } },
+                ],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+            {
+                "name": "apply_template",
+                "params": [
+                    { "name": "name", "required": true, "schema": { "type": "string", "enum": crate::templates::TEMPLATES.iter().map(|(name, _)| *name).collect::<Vec<_>>() } },
+                    { "name": "row", "schema": { "type": "integer" } },
+                ],
+                "result": { "name": "ok", "schema": { "type": "object" } },
+            },
+        ],
+    })
+}
@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Only the last stretch of a drag counts towards the speed it is let go at
+const SAMPLE_WINDOW: Duration = Duration::from_millis(100);
+// How quickly a glide slows to a stop, in pixels per second per second
+const DECELERATION: f32 = 5000.0;
+// Let go slower than this, in pixels per second, and the ribbon stays where it was dropped
+const MIN_SPEED: f32 = 200.0;
+
+// Follows a drag along one axis, and works out how much further it would carry on
+// gliding once let go, slowing evenly to a stop
+pub struct Tracker {
+    start: i32,
+    samples: VecDeque<(Instant, i32)>,
+}
+
+impl Tracker {
+    pub fn new(x: i32) -> Self {
+        Self { start: x, samples: VecDeque::from([(Instant::now(), x)]) }
+    }
+
+    pub fn push(&mut self, x: i32) {
+        let now = Instant::now();
+        self.samples.push_back((now, x));
+        // Keep two at least, so a finger held still before lifting reads as slow
+        while self.samples.len() > 2
+            && self.samples.front().is_some_and(|(at, _)| now.duration_since(*at) > SAMPLE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    // Pixels moved since the drag started
+    pub fn distance(&self) -> i32 {
+        self.samples.back().map_or(0, |(_, x)| x - self.start)
+    }
+
+    // Pixels per second over the last stretch
+    fn velocity(&self) -> f32 {
+        let (Some((first_at, first_x)), Some((last_at, last_x))) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let elapsed = last_at.duration_since(*first_at).as_secs_f32();
+        if elapsed < 0.001 {
+            return 0.0;
+        }
+        (last_x - first_x) as f32 / elapsed
+    }
+
+    // How much further, in pixels, letting go now would carry the drag
+    pub fn glide(&self) -> i32 {
+        let velocity = self.velocity();
+        if velocity.abs() < MIN_SPEED {
+            return 0;
+        }
+        (velocity * velocity.abs() / (2.0 * DECELERATION)) as i32
+    }
+}
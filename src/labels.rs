@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::*,
+        Storage::FileSystem::FILE_FLAGS_AND_ATTRIBUTES,
+        UI::Shell::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::window_info;
+
+// Pixel size icons are cached at; overlays scale them down
+pub const ICON_SIZE: u32 = 32;
+const ICON_TIMEOUT_MS: u32 = 250;
+
+// An app icon as premultiplied BGRA pixels, top row first
+#[derive(Debug)]
+pub struct Icon {
+    pub pixels: Vec<u8>,
+}
+
+// What overlays and the pipe show for a tile, cached so they don't have to ask the
+// window again on every paint or report
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub title: String,
+    pub icon: Option<Arc<Icon>>,
+}
+
+impl Label {
+    pub fn of(hwnd: HWND) -> Self {
+        Self {
+            title: window_info::window_title(hwnd),
+            icon: window_icon(hwnd).map(Arc::new),
+        }
+    }
+}
+
+// The icon the window shows in its title bar, or else its executable's
+fn window_icon(hwnd: HWND) -> Option<Icon> {
+    unsafe {
+        for kind in [ICON_BIG, ICON_SMALL2] {
+            let mut icon = 0usize;
+            let answered = SendMessageTimeoutW(
+                hwnd,
+                WM_GETICON,
+                WPARAM(kind as usize),
+                LPARAM(0),
+                SMTO_ABORTIFHUNG | SMTO_BLOCK,
+                ICON_TIMEOUT_MS,
+                Some(&mut icon),
+            ).0 != 0;
+            if answered && icon != 0 {
+                return render(HICON(icon as isize));
+            }
+        }
+        let class_icon = GetClassLongPtrW(hwnd, GCLP_HICON);
+        if class_icon != 0 {
+            return render(HICON(class_icon as isize));
+        }
+
+        // Extracted for us, so ours to destroy
+        let path = window_info::process_path(hwnd)?;
+        let mut info = SHFILEINFOW::default();
+        SHGetFileInfoW(
+            &HSTRING::from(path),
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            Some(&mut info),
+            std::mem::size_of::<SHFILEINFOW>() as u32,
+            SHGFI_ICON | SHGFI_LARGEICON,
+        );
+        if info.hIcon.is_invalid() {
+            return None;
+        }
+        let icon = render(info.hIcon);
+        DestroyIcon(info.hIcon).ok();
+        icon
+    }
+}
+
+// Draw an icon into a 32-bit bitmap. Old icons have no alpha channel; their mask
+// says which pixels are transparent instead.
+unsafe fn render(icon: HICON) -> Option<Icon> {
+    let size = ICON_SIZE as i32;
+    let dc = CreateCompatibleDC(None);
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size,
+            biHeight: -size,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bits = std::ptr::null_mut();
+    let Ok(bitmap) = CreateDIBSection(dc, &info, DIB_RGB_COLORS, &mut bits, None, 0) else {
+        DeleteDC(dc);
+        return None;
+    };
+    let previous = SelectObject(dc, bitmap);
+    let len = (ICON_SIZE * ICON_SIZE * 4) as usize;
+
+    let drawn = DrawIconEx(dc, 0, 0, icon, size, size, 0, None, DI_NORMAL).is_ok();
+    let mut pixels = std::slice::from_raw_parts(bits as *const u8, len).to_vec();
+    if drawn && pixels.chunks(4).all(|pixel| pixel[3] == 0) {
+        std::ptr::write_bytes(bits as *mut u8, 0, len);
+        DrawIconEx(dc, 0, 0, icon, size, size, 0, None, DI_MASK).ok();
+        let mask = std::slice::from_raw_parts(bits as *const u8, len);
+        for (pixel, masked) in pixels.chunks_mut(4).zip(mask.chunks(4)) {
+            if masked[0] == 0 {
+                pixel[3] = 255;
+            } else {
+                pixel.fill(0);
+            }
+        }
+    }
+
+    SelectObject(dc, previous);
+    DeleteObject(bitmap);
+    DeleteDC(dc);
+    drawn.then_some(Icon { pixels })
+}
+
+impl Icon {
+    // As a PNG, for clients of the pipe. Stored without compression; at 32x32 the
+    // whole image fits in one deflate block.
+    pub fn png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.pixels.len() + ICON_SIZE as usize);
+        for row in self.pixels.chunks(ICON_SIZE as usize * 4) {
+            raw.push(0);   // No filter
+            for pixel in row.chunks(4) {
+                // Back from premultiplied BGRA to straight RGBA
+                let alpha = pixel[3] as u32;
+                let straight = |c: u8| (c as u32 * 255).checked_div(alpha).map_or(0, |c| c.min(255) as u8);
+                raw.extend_from_slice(&[straight(pixel[2]), straight(pixel[1]), straight(pixel[0]), pixel[3]]);
+            }
+        }
+
+        let mut zlib = vec![0x78, 0x01, 0x01];
+        zlib.extend_from_slice(&(raw.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(raw.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(&raw);
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&ICON_SIZE.to_be_bytes());
+        header.extend_from_slice(&ICON_SIZE.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);   // 8-bit RGBA, no interlacing
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [(b"IHDR", &header), (b"IDAT", &zlib), (b"IEND", &Vec::new())] {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            let crc = crc32(&png[start..]);
+            png.extend_from_slice(&crc.to_be_bytes());
+        }
+        png
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - index * 6)) as usize & 63] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::{post_tiler_place, window_info, RibbonTiler, TileSize};
+
+// How long to wait for a launched app to show its main window
+const WINDOW_TIMEOUT: Duration = Duration::from_secs(20);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// One entry of the startup layout file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartupApp {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub row: i32,
+    #[serde(default)]
+    pub slot: usize,           // Position in the row, counted from the left
+    #[serde(default)]
+    pub size: SlotSize,
+    #[serde(default)]
+    pub exe: Option<String>,   // Owning executable, for launchers that hand off to another process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>, // Window title at export, for whoever reads the file
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlotSize {
+    Full,
+    TwoThirds,
+    #[default]
+    Half,
+    Third,
+    Quarter,
+}
+
+impl SlotSize {
+    pub fn tile_size(self) -> TileSize {
+        match self {
+            SlotSize::Full => TileSize::Full,
+            SlotSize::TwoThirds => TileSize::TwoThirds,
+            SlotSize::Half => TileSize::Half,
+            SlotSize::Third => TileSize::Third,
+            SlotSize::Quarter => TileSize::Quarter,
+        }
+    }
+
+    pub fn of(size: TileSize) -> Self {
+        match size {
+            TileSize::Full => SlotSize::Full,
+            TileSize::TwoThirds => SlotSize::TwoThirds,
+            TileSize::Half => SlotSize::Half,
+            TileSize::Third => SlotSize::Third,
+            TileSize::Quarter => SlotSize::Quarter,
+        }
+    }
+}
+
+// The active ribbon as a startup layout, so a setup can be shared or rebuilt with
+// --startup-layout elsewhere. Apps are launched by full path and matched by exe name.
+pub fn capture(tiler: &RibbonTiler) -> Vec<StartupApp> {
+    let mut rows: Vec<(&i32, &Vec<isize>)> = tiler.rows.iter().collect();
+    rows.sort_by_key(|(row, _)| **row);
+    rows.into_iter()
+        .flat_map(|(&row, order)| order.iter().filter_map(|hwnd| tiler.windows.get(hwnd)).enumerate()
+            .filter_map(move |(slot, window)| {
+                let path = window_info::process_path(window.hwnd)?;
+                Some(StartupApp {
+                    exe: path.rsplit('\\').next().map(str::to_string),
+                    command: path,
+                    args: Vec::new(),
+                    row,
+                    slot,
+                    size: SlotSize::of(window.position.size),
+                    title: Some(window_info::window_title(window.hwnd)),
+                })
+            }))
+        .collect()
+}
+
+// JSON with one app per line: readable as it is, and loadable as a startup layout
+pub fn to_text(apps: &[StartupApp]) -> String {
+    let lines: Vec<String> = apps.iter()
+        .map(|app| format!("  {}", serde_json::to_string(app).unwrap_or_default()))
+        .collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<StartupApp>> {
+    parse(&fs::read_to_string(path)?)
+}
+
+pub fn parse(text: &str) -> io::Result<Vec<StartupApp>> {
+    serde_json::from_str(text.trim()).map_err(io::Error::other)
+}
+
+// Visible, unowned top-level windows - the ones an app would call its main window
+pub fn main_windows() -> Vec<HWND> {
+    let mut windows = window_info::top_level_windows();
+    windows.retain(|&hwnd| unsafe {
+        IsWindowVisible(hwnd).as_bool()
+            && GetWindow(hwnd, GW_OWNER).0 == 0
+            && GetWindowTextLengthW(hwnd) > 0
+    });
+    windows
+}
+
+fn window_pid(hwnd: HWND) -> u32 {
+    let mut pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    }
+    pid
+}
+
+// Start the app and wait for a new main window belonging to it
+fn launch(app: &StartupApp, claimed: &HashSet<isize>) -> Option<HWND> {
+    let existing: HashSet<isize> = main_windows().iter().map(|hwnd| hwnd.0).collect();
+
+    let child = match Command::new(&app.command).args(&app.args).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            println!("Warning: Failed to launch {}: {}", app.command, e);
+            return None;
+        }
+    };
+    let pid = child.id();
+
+    let deadline = Instant::now() + WINDOW_TIMEOUT;
+    while Instant::now() < deadline {
+        let found = main_windows().into_iter().find(|hwnd| {
+            if existing.contains(&hwnd.0) || claimed.contains(&hwnd.0) {
+                return false;
+            }
+            match &app.exe {
+                Some(exe) => window_info::process_name(*hwnd).is_some_and(|name| name.eq_ignore_ascii_case(exe)),
+                None => window_pid(*hwnd) == pid,
+            }
+        });
+        if found.is_some() {
+            return found;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    println!("Warning: {} showed no window within {}s", app.command, WINDOW_TIMEOUT.as_secs());
+    None
+}
+
+// Image name a layout entry's window runs as
+fn exe_of(app: &StartupApp) -> &str {
+    app.exe.as_deref().unwrap_or_else(|| app.command.rsplit(['\\', '/']).next().unwrap_or(&app.command))
+}
+
+// A layout from the clipboard or a client is just text, and its commands could be
+// anything, so the user sees them before any is run
+fn confirm_launch(apps: &[&StartupApp]) -> bool {
+    let commands: Vec<String> = apps.iter()
+        .map(|app| std::iter::once(&app.command).chain(&app.args).cloned().collect::<Vec<_>>().join(" "))
+        .collect();
+    let text = format!("This layout starts {} apps that aren't running:\n\n{}\n\nRun these commands?",
+        apps.len(), commands.join("\n"));
+    let answer = unsafe {
+        MessageBoxW(HWND::default(), &HSTRING::from(text), w!("Thymeline: apply layout"),
+            MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2 | MB_TOPMOST | MB_SETFOREGROUND)
+    };
+    answer == IDYES
+}
+
+// Bring the ribbon in line with a layout, e.g. one copied on another machine. Apps
+// already running keep their window, preferring one with the title the layout was
+// copied with; only the rest are launched, and only once the user agrees to the
+// commands unless the layout is trusted (the built-in templates). Tiles already in
+// their slot stay put.
+pub fn apply(mut apps: Vec<StartupApp>, trusted: bool) {
+    apps.sort_by_key(|app| (app.row, app.slot));
+
+    thread::spawn(move || {
+        let open: Vec<(HWND, String, String)> = main_windows().into_iter()
+            .map(|hwnd| (hwnd, window_info::process_name(hwnd).unwrap_or_default(), window_info::window_title(hwnd)))
+            .collect();
+        let mut claimed = HashSet::new();
+        let mut found: Vec<Option<HWND>> = vec![None; apps.len()];
+        for exact in [true, false] {
+            for (app, found) in apps.iter().zip(found.iter_mut()).filter(|(_, found)| found.is_none()) {
+                let running = open.iter().find(|(hwnd, exe, title)| {
+                    !claimed.contains(&hwnd.0)
+                        && exe.eq_ignore_ascii_case(exe_of(app))
+                        && (!exact || app.title.as_ref() == Some(title))
+                });
+                if let Some(&(hwnd, ..)) = running {
+                    claimed.insert(hwnd.0);
+                    *found = Some(hwnd);
+                }
+            }
+        }
+
+        let running = found.iter().flatten().count();
+        println!("Applying a layout of {} apps, {} already running", apps.len(), running);
+        // Running ones first, so the slots launched apps go into are already filled in
+        for (app, hwnd) in apps.iter().zip(&found) {
+            if let Some(hwnd) = hwnd {
+                post_tiler_place(*hwnd, app.row, app.slot, app.size.tile_size());
+            }
+        }
+        let missing: Vec<&StartupApp> = apps.iter().zip(&found)
+            .filter(|(_, hwnd)| hwnd.is_none())
+            .map(|(app, _)| app)
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        if !trusted && !confirm_launch(&missing) {
+            println!("Left {} apps of the layout unlaunched", missing.len());
+            return;
+        }
+        for app in missing {
+            if let Some(hwnd) = launch(app, &claimed) {
+                claimed.insert(hwnd.0);
+                post_tiler_place(hwnd, app.row, app.slot, app.size.tile_size());
+            }
+        }
+    });
+}
+
+// Launch every app in slot order and hand each window to the tiler to place
+pub fn start(mut apps: Vec<StartupApp>) {
+    apps.sort_by_key(|app| (app.row, app.slot));
+
+    thread::spawn(move || {
+        let mut claimed = HashSet::new();
+        for app in &apps {
+            if let Some(hwnd) = launch(app, &claimed) {
+                claimed.insert(hwnd.0);
+                post_tiler_place(hwnd, app.row, app.slot, app.size.tile_size());
+            }
+        }
+    });
+}
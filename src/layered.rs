@@ -0,0 +1,136 @@
+use windows::Win32::{
+    Foundation::*,
+    Graphics::Dwm::DwmFlush,
+    Graphics::Gdi::{GetDC, GetPixel, ReleaseDC, CLR_INVALID},
+    UI::WindowsAndMessaging::*,
+};
+
+// How the first application of alpha to a window went
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaCheck {
+    Applied,
+    Refused,    // Layering didn't stick, or the window went black under it; alpha was taken off again
+    Unverified, // Applied, but no visible part of the window to check
+}
+
+// A window's own layering from before the tiler touched it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layering {
+    None,
+    Attributes {
+        key: COLORREF,
+        alpha: u8,
+        flags: LAYERED_WINDOW_ATTRIBUTES_FLAGS,
+    },
+    PerPixel,   // Drawn with UpdateLayeredWindow; SetLayeredWindowAttributes would break it
+}
+
+pub fn capture(hwnd: HWND) -> Layering {
+    unsafe {
+        if GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_LAYERED.0 == 0 {
+            return Layering::None;
+        }
+
+        let mut key = COLORREF(0);
+        let mut alpha = 0u8;
+        let mut flags = LAYERED_WINDOW_ATTRIBUTES_FLAGS(0);
+        // Fails for windows that manage their layering with UpdateLayeredWindow
+        match GetLayeredWindowAttributes(hwnd, Some(&mut key), Some(&mut alpha), Some(&mut flags)) {
+            Ok(()) => Layering::Attributes { key, alpha, flags },
+            Err(_) => Layering::PerPixel,
+        }
+    }
+}
+
+// Put a window's layering back exactly as captured
+pub fn restore(hwnd: HWND, original: Layering) {
+    match original {
+        Layering::None => clear(hwnd),
+        Layering::Attributes { key, alpha, flags } => unsafe {
+            let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+            SetWindowLongW(hwnd, GWL_EXSTYLE, (ex_style | WS_EX_LAYERED.0) as i32);
+            SetLayeredWindowAttributes(hwnd, key, alpha, flags).ok();
+        },
+        Layering::PerPixel => {}
+    }
+}
+
+pub fn set_alpha(hwnd: HWND, alpha: u8) -> bool {
+    unsafe {
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        SetWindowLongW(hwnd, GWL_EXSTYLE, (ex_style | WS_EX_LAYERED.0) as i32);
+        if GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_LAYERED.0 == 0 {
+            return false;
+        }
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA).is_ok()
+    }
+}
+
+pub fn clear(hwnd: HWND) {
+    unsafe {
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        SetWindowLongW(hwnd, GWL_EXSTYLE, (ex_style & !WS_EX_LAYERED.0) as i32);
+    }
+}
+
+// Apply alpha and compare what is on screen before and after. GPU swap chains that
+// can't be layered (some video players, games, protected content) turn solid black.
+pub fn set_alpha_checked(hwnd: HWND, alpha: u8) -> AlphaCheck {
+    let points = sample_points(hwnd);
+    let before = sample(&points);
+
+    if !set_alpha(hwnd, alpha) {
+        clear(hwnd);
+        return AlphaCheck::Refused;
+    }
+    if points.is_empty() {
+        return AlphaCheck::Unverified;
+    }
+
+    unsafe {
+        DwmFlush().ok();
+    }
+    let after = sample(&points);
+
+    let was_drawn = before.iter().any(|&color| color != 0);
+    // Every point may read back as invalid, which says nothing either way
+    let now_black = !after.is_empty() && after.iter().all(|&color| color == 0);
+    if was_drawn && now_black {
+        clear(hwnd);
+        return AlphaCheck::Refused;
+    }
+    AlphaCheck::Applied
+}
+
+// A few screen points the window itself is showing (not covered by anything else)
+fn sample_points(hwnd: HWND) -> Vec<POINT> {
+    let mut rect = RECT::default();
+    unsafe {
+        if GetWindowRect(hwnd, &mut rect).is_err() {
+            return Vec::new();
+        }
+    }
+
+    // 3x3 grid at the quarter points
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    (1..=3).flat_map(|col| (1..=3).map(move |row| (col, row)))
+        .map(|(col, row)| POINT {
+            x: rect.left + width * col / 4,
+            y: rect.top + height * row / 4,
+        })
+        .filter(|&point| unsafe { GetAncestor(WindowFromPoint(point), GA_ROOT) == hwnd })
+        .collect()
+}
+
+fn sample(points: &[POINT]) -> Vec<u32> {
+    unsafe {
+        let dc = GetDC(HWND::default());
+        let colors = points.iter()
+            .map(|point| GetPixel(dc, point.x, point.y).0)
+            .filter(|&color| color != CLR_INVALID)
+            .collect();
+        ReleaseDC(HWND::default(), dc);
+        colors
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{appdata, TilerCommand};
+
+// Replay steps run off the main window's timer; the main loop claims ids 1, 2, 4 and 8,
+// overlays use 3, the demo 6 and the config poll 7
+pub const MACRO_TIMER_ID: usize = 5;
+// Longer than the command throttle, so repeated steps aren't dropped, and long enough
+// for a scroll to settle before the next step reads the focused window
+pub const STEP_INTERVAL_MS: u32 = 150;
+
+// Command sequences recorded from the keyboard (or any other command source) and
+// replayed step by step against whatever window has focus at the time
+#[derive(Default)]
+pub struct Macros {
+    saved: BTreeMap<String, Vec<TilerCommand>>,
+    recording: Option<Vec<TilerCommand>>,
+    last: Vec<TilerCommand>,    // The most recent recording, saved or not
+    playing: VecDeque<TilerCommand>,
+}
+
+// %APPDATA%\Thymeline\macros.json
+const FILE: &str = "macros.json";
+
+impl Macros {
+    // Saved macros are stored by command name; names this version doesn't know are skipped
+    pub fn load() -> Self {
+        let stored: BTreeMap<String, Vec<String>> = appdata::load_json(FILE).unwrap_or_default();
+        let saved = stored.into_iter()
+            .map(|(name, steps)| (name, steps.iter().filter_map(|step| TilerCommand::from_name(step)).collect()))
+            .collect();
+        Self { saved, ..Self::default() }
+    }
+
+    fn store(&self) {
+        let stored: BTreeMap<&str, Vec<&str>> = self.saved.iter()
+            .map(|(name, steps)| (name.as_str(), steps.iter().map(|step| step.name()).collect()))
+            .collect();
+        appdata::store_json(FILE, &stored);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // Start recording, or stop and keep the recording as the last macro.
+    // Returns the number of steps recorded when stopping.
+    pub fn toggle_recording(&mut self) -> Option<usize> {
+        match self.recording.take() {
+            Some(steps) => {
+                let count = steps.len();
+                if !steps.is_empty() {
+                    self.last = steps;
+                }
+                Some(count)
+            }
+            None => {
+                self.playing.clear();
+                self.recording = Some(Vec::new());
+                None
+            }
+        }
+    }
+
+    pub fn record(&mut self, command: TilerCommand) {
+        if matches!(command, TilerCommand::RecordMacro | TilerCommand::ReplayMacro) {
+            return;
+        }
+        if let Some(steps) = self.recording.as_mut() {
+            steps.push(command);
+        }
+    }
+
+    // Keep the last recording under a name, replacing any macro already called that
+    pub fn save(&mut self, name: &str) -> Result<usize, String> {
+        if name.trim().is_empty() {
+            return Err("A macro needs a name".to_string());
+        }
+        if self.last.is_empty() {
+            return Err("Nothing has been recorded yet".to_string());
+        }
+        self.saved.insert(name.to_string(), self.last.clone());
+        self.store();
+        Ok(self.last.len())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.saved.keys().cloned().collect()
+    }
+
+    // Queue a macro's steps; None plays the last recording. Nothing plays while recording,
+    // so a macro can never end up inside itself. Macros played from a link keep only their
+    // navigation steps, the same as a link naming a command.
+    pub fn play(&mut self, name: Option<&str>, navigation_only: bool) -> bool {
+        if self.is_recording() {
+            return false;
+        }
+        let steps = match name {
+            Some(name) => self.saved.get(name),
+            None => Some(&self.last),
+        };
+        let steps: VecDeque<TilerCommand> = steps.into_iter().flatten()
+            .copied()
+            .filter(|step| !navigation_only || step.is_navigation())
+            .collect();
+        if steps.is_empty() {
+            return false;
+        }
+        self.playing = steps;
+        true
+    }
+
+    pub fn next_step(&mut self) -> Option<TilerCommand> {
+        self.playing.pop_front()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.playing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_macro(steps: &[TilerCommand]) -> Macros {
+        Macros {
+            saved: BTreeMap::from([("review".to_string(), steps.to_vec())]),
+            ..Macros::default()
+        }
+    }
+
+    #[test]
+    fn links_play_only_navigation_steps() {
+        let mut macros = with_macro(&[TilerCommand::PanRight, TilerCommand::RemoveWindow, TilerCommand::FocusLeft, TilerCommand::CopyLayout]);
+        assert!(macros.play(Some("review"), true));
+        assert_eq!(macros.next_step(), Some(TilerCommand::PanRight));
+        assert_eq!(macros.next_step(), Some(TilerCommand::FocusLeft));
+        assert_eq!(macros.next_step(), None);
+
+        assert!(macros.play(Some("review"), false));
+        assert_eq!(macros.playing.len(), 4);
+    }
+
+    #[test]
+    fn links_refuse_macros_without_navigation() {
+        let mut macros = with_macro(&[TilerCommand::ToggleDryRun, TilerCommand::ImportLayout]);
+        assert!(!macros.play(Some("review"), true));
+        assert!(!macros.is_playing());
+        assert!(!macros.play(Some("missing"), false));
+    }
+}
@@ -1,6 +1,9 @@
-use std::collections::HashMap;
+// No console window of its own; see console.rs
+#![windows_subsystem = "windows"]
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
+use std::sync::atomic::{AtomicUsize, AtomicBool, AtomicIsize, AtomicU32, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use std::thread;
 use windows::{
@@ -10,23 +13,143 @@ use windows::{
         System::LibraryLoader::*,
         System::Threading::*,
         System::Console::*,
+        System::Com::*,
+        Graphics::Dwm::{DwmSetWindowAttribute, DWMWA_BORDER_COLOR, DWMWA_TRANSITIONS_FORCEDISABLED},
+        UI::Accessibility::*,
         UI::WindowsAndMessaging::*,
         UI::Input::KeyboardAndMouse::*,
     },
 };
 
+mod appdata;
+mod cli;
+mod client;
+mod clipboard;
+mod compat;
+mod compositor;
+mod config;
+mod console;
+mod demo;
+mod easing;
+mod focus;
+mod grabstrip;
+mod hud;
+mod handoff;
+mod hotcorner;
+mod idle;
+mod ipc;
+mod jsonrpc;
+mod kinetic;
+mod labels;
+mod layered;
+mod launch;
+mod macros;
+mod mirror;
+mod monitor;
+mod osd;
+mod overlay;
+mod prefs;
+mod priority;
+mod pwsh;
+mod remote;
+mod restore;
+mod rowstrip;
+mod rules;
+mod schedule;
+mod scratchpad;
+mod session;
+mod sharing;
+mod shellhook;
+mod stats;
+mod switcher;
+mod templates;
+mod timetrack;
+mod tray;
+mod uri;
+mod window_info;
+
+use cli::{FramePacing, LimitPolicy, Options, ScrollFocus, Subcommand};
+use compat::Quirk;
+use config::Config;
+use easing::{Curve, Curves};
+use hotcorner::HotCorners;
+use layered::Layering;
+use mirror::Mirror;
+use prefs::Prefs;
+use timetrack::TimeTracker;
+
 // Define WM constants
 const WM_USER: u32 = 0x0400;
 const WM_KEYDOWN: u32 = 0x0100;
 const WM_SYSKEYDOWN: u32 = 0x0104;
+const WM_KEYUP: u32 = 0x0101;
+const WM_SYSKEYUP: u32 = 0x0105;
+const WM_TIMER: u32 = 0x0113;
 
 // Custom messages for deferred operations
 const WM_TILER_COMMAND: u32 = WM_USER + 2;
 const WM_TILER_SHUTDOWN: u32 = WM_USER + 3;
-const WM_TILER_RECALC: u32 = WM_USER + 4;
+const WM_TILER_GONE: u32 = WM_USER + 4;
+const WM_TILER_FOREGROUND: u32 = WM_USER + 5;
+const WM_TILER_RESUME: u32 = WM_USER + 6;
+const WM_TILER_PLACE: u32 = WM_USER + 7;
+const WM_TILER_ROW_PICK: u32 = WM_USER + 8;
+const WM_TILER_MACRO: u32 = WM_USER + 9;
+const WM_TILER_TRAY: u32 = WM_USER + 10;
+const WM_TILER_SHOWN: u32 = WM_USER + 11;
+const WM_TILER_UNMINIMIZED: u32 = WM_USER + 12;
+const WM_TILER_SHELL: u32 = WM_USER + 13;
+const WM_TILER_RENAMED: u32 = WM_USER + 14;
+const WM_TILER_SWITCH: u32 = WM_USER + 15;
+const WM_TILER_POSTURE: u32 = WM_USER + 16;
+const WM_TILER_DRAG: u32 = WM_USER + 17;
+
+// WM_TILER_ROW_PICK actions
+const ROW_PICK_UP: usize = 0;
+const ROW_PICK_DOWN: usize = 1;
+const ROW_PICK_COMMIT: usize = 2;
+
+// WM_TILER_SWITCH actions
+const SWITCH_NEXT: usize = 0;
+const SWITCH_COMMIT: usize = 1;
+const SWITCH_CANCEL: usize = 2;
+
+// Tiles remembered in the focus history
+const FOCUS_HISTORY_LEN: usize = 32;
+
+// Screens at least this wide relative to their height honour --viewports
+const ULTRAWIDE_ASPECT: f32 = 2.0;
+
+// One viewport width in ribbon units. Ribbon x positions and offsets use these
+// units so a resolution change only changes how they map to pixels.
+const RIBBON_SPAN: i32 = 10_000;
+
+// How long a --handoff instance waits for the old one to exit after handing over
+const HANDOFF_GUARD_TIMEOUT_MS: u32 = 10_000;
+
+// Tiles are checked for a hung app this often, on the main window's timer
+const HUNG_CHECK_TIMER_ID: usize = 4;
+const HUNG_CHECK_INTERVAL_MS: u32 = 2000;
+// Minimized tiles wait this long on the main window's timer before leaving the ribbon,
+// to tell show desktop, which minimizes them all at once, from a single minimize
+const SHOW_DESKTOP_TIMER_ID: usize = 8;
+const SHOW_DESKTOP_SETTLE_MS: u32 = 250;
+// Border drawn around a quarantined tile (COLORREF, 0x00BBGGRR)
+const HUNG_BORDER_COLOR: u32 = 0x0000_30E0;
+const DWMWA_COLOR_DEFAULT: u32 = 0xFFFF_FFFF;
+
+// Repeated transparency presses closer together than this count as holding the key
+const TRANSPARENCY_RAMP_WINDOW: Duration = Duration::from_millis(300);
+
+// Delay-adopt apps are tiled once their rect stops changing for this long
+const DEFAULT_ADOPT_SETTLE: Duration = Duration::from_millis(300);
+// Never hold back an adoption longer than this, even if the app keeps moving
+const ADOPT_SETTLE_TIMEOUT: Duration = Duration::from_secs(3);
+// How long show-heatmap leaves the row strip up
+const HEATMAP_SHOW_MS: u32 = 4000;
 
 // Command types for deferred execution
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u32)]
 enum TilerCommand {
     PanLeft = 0,
@@ -48,6 +171,177 @@ enum TilerCommand {
     RemoveWindow = 20,
     CycleFPS = 21,
     ForceRecalc = 22,
+    ToggleMirror = 23,
+    SendToMonitorLeft = 24,
+    SendToMonitorRight = 25,
+    CycleViewport = 26,
+    CancelAnimations = 27,
+    ToggleInstant = 28,
+    PullWindow = 29,
+    DropWindow = 30,
+    RetryWindow = 31,
+    ToggleDryRun = 32,
+    RecordMacro = 33,
+    ReplayMacro = 34,
+    Demo = 35,
+    ShowHeatmap = 36,
+    PanStart = 37,
+    PanEnd = 38,
+    ToggleFollow = 39,
+    ResizeGrow = 40,
+    ResizeShrink = 41,
+    StackWindow = 42,
+    UnstackWindow = 43,
+    CycleStack = 44,
+    ToggleFloat = 45,
+    TogglePresentation = 46,
+    SetScratchpad = 47,
+    ToggleScratchpad = 48,
+    FocusLeft = 49,
+    FocusRight = 50,
+    FocusUp = 51,
+    FocusDown = 52,
+    CopyLayout = 53,
+    SwitchWindow = 54,
+    ImportLayout = 55,
+    ToggleAnimations = 56,
+    RetryTransparency = 57,
+}
+
+impl TilerCommand {
+    const ALL: [TilerCommand; 54] = [
+        TilerCommand::PanLeft,
+        TilerCommand::PanRight,
+        TilerCommand::PanUp,
+        TilerCommand::PanDown,
+        TilerCommand::ResizeLeft,
+        TilerCommand::ResizeRight,
+        TilerCommand::MoveUp,
+        TilerCommand::MoveDown,
+        TilerCommand::MoveLeft,
+        TilerCommand::MoveRight,
+        TilerCommand::AddWindow,
+        TilerCommand::IncreaseTransparency,
+        TilerCommand::DecreaseTransparency,
+        TilerCommand::ScrollToWindow,
+        TilerCommand::IncreaseMargins,
+        TilerCommand::DecreaseMargins,
+        TilerCommand::RemoveWindow,
+        TilerCommand::CycleFPS,
+        TilerCommand::ForceRecalc,
+        TilerCommand::ToggleMirror,
+        TilerCommand::SendToMonitorLeft,
+        TilerCommand::SendToMonitorRight,
+        TilerCommand::CycleViewport,
+        TilerCommand::CancelAnimations,
+        TilerCommand::ToggleInstant,
+        TilerCommand::PullWindow,
+        TilerCommand::DropWindow,
+        TilerCommand::RetryWindow,
+        TilerCommand::ToggleDryRun,
+        TilerCommand::RecordMacro,
+        TilerCommand::ReplayMacro,
+        TilerCommand::Demo,
+        TilerCommand::ShowHeatmap,
+        TilerCommand::PanStart,
+        TilerCommand::PanEnd,
+        TilerCommand::ToggleFollow,
+        TilerCommand::ResizeGrow,
+        TilerCommand::ResizeShrink,
+        TilerCommand::StackWindow,
+        TilerCommand::UnstackWindow,
+        TilerCommand::CycleStack,
+        TilerCommand::ToggleFloat,
+        TilerCommand::TogglePresentation,
+        TilerCommand::SetScratchpad,
+        TilerCommand::ToggleScratchpad,
+        TilerCommand::FocusLeft,
+        TilerCommand::FocusRight,
+        TilerCommand::FocusUp,
+        TilerCommand::FocusDown,
+        TilerCommand::CopyLayout,
+        TilerCommand::SwitchWindow,
+        TilerCommand::ImportLayout,
+        TilerCommand::ToggleAnimations,
+        TilerCommand::RetryTransparency,
+    ];
+
+    fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| *c as u32 == value)
+    }
+
+    // Stable external name, used by the IPC protocol
+    fn name(self) -> &'static str {
+        match self {
+            TilerCommand::PanLeft => "pan-left",
+            TilerCommand::PanRight => "pan-right",
+            TilerCommand::PanUp => "pan-up",
+            TilerCommand::PanDown => "pan-down",
+            TilerCommand::ResizeLeft => "resize-left",
+            TilerCommand::ResizeRight => "resize-right",
+            TilerCommand::MoveUp => "move-up",
+            TilerCommand::MoveDown => "move-down",
+            TilerCommand::MoveLeft => "move-left",
+            TilerCommand::MoveRight => "move-right",
+            TilerCommand::AddWindow => "add-window",
+            TilerCommand::IncreaseTransparency => "increase-transparency",
+            TilerCommand::DecreaseTransparency => "decrease-transparency",
+            TilerCommand::ScrollToWindow => "scroll-to-window",
+            TilerCommand::IncreaseMargins => "increase-margins",
+            TilerCommand::DecreaseMargins => "decrease-margins",
+            TilerCommand::RemoveWindow => "remove-window",
+            TilerCommand::CycleFPS => "cycle-fps",
+            TilerCommand::ForceRecalc => "force-recalc",
+            TilerCommand::ToggleMirror => "toggle-mirror",
+            TilerCommand::SendToMonitorLeft => "send-to-monitor-left",
+            TilerCommand::SendToMonitorRight => "send-to-monitor-right",
+            TilerCommand::CycleViewport => "cycle-viewport",
+            TilerCommand::CancelAnimations => "cancel-animations",
+            TilerCommand::ToggleInstant => "toggle-instant",
+            TilerCommand::PullWindow => "pull-window",
+            TilerCommand::DropWindow => "drop-window",
+            TilerCommand::RetryWindow => "retry-window",
+            TilerCommand::ToggleDryRun => "toggle-dry-run",
+            TilerCommand::RecordMacro => "record-macro",
+            TilerCommand::ReplayMacro => "replay-macro",
+            TilerCommand::Demo => "demo",
+            TilerCommand::ShowHeatmap => "show-heatmap",
+            TilerCommand::PanStart => "pan-start",
+            TilerCommand::PanEnd => "pan-end",
+            TilerCommand::ToggleFollow => "toggle-follow",
+            TilerCommand::ResizeGrow => "resize-grow",
+            TilerCommand::ResizeShrink => "resize-shrink",
+            TilerCommand::StackWindow => "stack-window",
+            TilerCommand::UnstackWindow => "unstack-window",
+            TilerCommand::CycleStack => "cycle-stack",
+            TilerCommand::ToggleFloat => "toggle-float",
+            TilerCommand::TogglePresentation => "toggle-presentation",
+            TilerCommand::SetScratchpad => "set-scratchpad",
+            TilerCommand::ToggleScratchpad => "toggle-scratchpad",
+            TilerCommand::FocusLeft => "focus-left",
+            TilerCommand::FocusRight => "focus-right",
+            TilerCommand::FocusUp => "focus-up",
+            TilerCommand::FocusDown => "focus-down",
+            TilerCommand::CopyLayout => "copy-layout",
+            TilerCommand::SwitchWindow => "switch-window",
+            TilerCommand::ImportLayout => "import-layout",
+            TilerCommand::ToggleAnimations => "toggle-animations",
+            TilerCommand::RetryTransparency => "retry-transparency",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.name() == name)
+    }
+
+    // Moves the view or the focus and nothing else; all that remote clients and
+    // thymeline:// links may run
+    fn is_navigation(self) -> bool {
+        matches!(self,
+            TilerCommand::PanLeft | TilerCommand::PanRight | TilerCommand::PanUp | TilerCommand::PanDown
+            | TilerCommand::PanStart | TilerCommand::PanEnd | TilerCommand::ScrollToWindow
+            | TilerCommand::FocusLeft | TilerCommand::FocusRight | TilerCommand::FocusUp | TilerCommand::FocusDown)
+    }
 }
 
 // Animation state for smooth transitions
@@ -56,7 +350,7 @@ struct AnimationState {
     start_rect: RECT,
     target_rect: RECT,
     start_time: Instant,
-    duration: Duration,
+    curve: Curve,
     animation_type: AnimationType,
 }
 
@@ -75,22 +369,129 @@ struct ScrollAnimation {
     target_x: i32,
     target_y: i32,
     start_time: Instant,
-    duration: Duration,
+    curve: Curve,
+}
+
+// Horizontal slice of the screen that hosts the ribbon (the whole screen unless split)
+#[derive(Debug, Clone, Copy)]
+struct Viewport {
+    left: i32,
+    width: i32,
+}
+
+impl Viewport {
+    // Tiles scrolled out of the viewport are pushed past the screen edge
+    // so they never cover a neighbouring viewport
+    fn screen_x(&self, base_x: i32, tile_width: i32, screen_width: i32) -> i32 {
+        if base_x + tile_width <= 0 {
+            base_x
+        } else if base_x >= self.width {
+            base_x + screen_width - self.width
+        } else {
+            self.left + base_x
+        }
+    }
+}
+
+// Ribbon of a viewport or display that isn't currently active
+#[derive(Default)]
+struct ParkedRibbon {
+    windows: HashMap<isize, ManagedWindow>,
+    rows: HashMap<i32, Vec<isize>>,
+    ribbon_offset: i32,
+    vertical_offset: i32,
+    current_row: i32,
+}
+
+impl ParkedRibbon {
+    // Drop a tile that closed or hid while its ribbon was out of sight
+    fn remove(&mut self, hwnd: isize) -> Option<ManagedWindow> {
+        for order in self.rows.values_mut() {
+            order.retain(|&h| h != hwnd);
+        }
+        self.rows.retain(|_, order| !order.is_empty());
+        self.windows.remove(&hwnd)
+    }
 }
 
 // Window size variants - simplified to just width variations
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum TileSize {
-    Full,           // Full screen width
+    Quarter,        // A quarter of the screen width
+    Third,          // A third of the screen width
     Half,           // Half screen width
+    TwoThirds,      // Two thirds of the screen width
+    Full,           // Full screen width
+}
+
+impl TileSize {
+    // Narrowest first; resizing steps through them in order
+    const WIDTHS: [TileSize; 5] = [TileSize::Quarter, TileSize::Third, TileSize::Half, TileSize::TwoThirds, TileSize::Full];
+
+    // Width in ribbon units
+    fn units(self) -> i32 {
+        match self {
+            TileSize::Quarter => RIBBON_SPAN / 4,
+            TileSize::Third => RIBBON_SPAN / 3,
+            TileSize::Half => RIBBON_SPAN / 2,
+            TileSize::TwoThirds => RIBBON_SPAN * 2 / 3,
+            TileSize::Full => RIBBON_SPAN,
+        }
+    }
+
+    // Stable external name, used by the IPC protocol and the layout files
+    fn name(self) -> &'static str {
+        match self {
+            TileSize::Quarter => "quarter",
+            TileSize::Third => "third",
+            TileSize::Half => "half",
+            TileSize::TwoThirds => "two-thirds",
+            TileSize::Full => "full",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::WIDTHS.iter().position(|&size| size == self).unwrap_or(0)
+    }
+
+    // The next width up or down from a tile this many units wide, wrapping around at
+    // either end
+    fn step(units: i32, wider: bool) -> Self {
+        let widths = Self::WIDTHS;
+        if wider {
+            widths.into_iter().find(|size| size.units() > units).unwrap_or(widths[0])
+        } else {
+            widths.into_iter().rev().find(|size| size.units() < units).unwrap_or(widths[widths.len() - 1])
+        }
+    }
+
+    // The fixed width this many units make up, if any
+    fn of_units(units: i32) -> Option<Self> {
+        Self::WIDTHS.into_iter().find(|size| size.units() == units)
+    }
 }
 
-// Position in the ribbon (x is the virtual position, row is the vertical row)
+// Position in the ribbon (x is derived from the row order, row is the vertical row)
 #[derive(Debug, Clone, Copy)]
 struct RibbonPosition {
-    x: i32,         // Virtual x position in ribbon
+    x: i32,         // Virtual x position in ribbon units (RIBBON_SPAN per viewport width)
     row: i32,       // Row number (0, 1, 2, etc.)
     size: TileSize,
+    width: Option<i32>, // Width in ribbon units set by resize-grow/-shrink, in place of size
+    monitor: isize, // Display the tile is shown on (HMONITOR); laid out on the primary's geometry
+}
+
+impl RibbonPosition {
+    // Width in ribbon units
+    fn units(&self) -> i32 {
+        self.width.unwrap_or_else(|| self.size.units())
+    }
+
+    // Back to one of the fixed widths
+    fn set_size(&mut self, size: TileSize) {
+        self.size = size;
+        self.width = None;
+    }
 }
 
 // Managed window information
@@ -99,9 +500,30 @@ struct ManagedWindow {
     hwnd: HWND,
     original_style: WINDOW_STYLE,
     original_ex_style: WINDOW_EX_STYLE,
-    original_rect: RECT,
+    original_placement: WINDOWPLACEMENT, // Position and show state to return to when released
+    keep_style: bool,       // Caption buttons left alone (restore-style quirk)
+    own_size: Option<(i32, i32)>, // Kept and centred in the slot rather than resized (no-resize quirk)
+    original_layering: Layering, // The app's own layered alpha, put back on release
     position: RibbonPosition,
     animation: Option<AnimationState>,
+    adopted: Instant,
+    touched: bool,          // Style and placement really changed; false while adopted under dry run
+}
+
+// The slot a tile left when toggle-float took it out of the ribbon
+#[derive(Debug, Clone, Copy)]
+struct FloatedTile {
+    row: i32,
+    slot: usize,
+    size: TileSize,
+    width: Option<i32>,
+}
+
+// Popup or dialog that floats above the ribbon instead of being tiled
+#[derive(Debug, Clone, Copy)]
+struct FloatingWindow {
+    hwnd: HWND,
+    original_layering: Layering,
 }
 
 // Command queue entry
@@ -114,43 +536,123 @@ struct QueuedCommand {
 // Main tiler state
 struct RibbonTiler {
     windows: HashMap<isize, ManagedWindow>,
-    floating_windows: HashMap<isize, HWND>,
-    ribbon_offset: i32,
+    rows: HashMap<i32, Vec<isize>>,    // Left-to-right tile order of each row; x follows from it
+    floating_windows: HashMap<isize, FloatingWindow>,
+    ribbon_offset: i32,                // Horizontal scroll, in ribbon units
     ribbon_offset_target: i32,
     vertical_offset: i32,              // Current vertical scroll offset
     vertical_offset_target: i32,       // Target vertical scroll offset
     scroll_animation: Option<ScrollAnimation>, // Combined scroll animation
     current_row: i32,                  // Currently visible row
     row_height: i32,                   // Height of each row
+    visible_rows: Option<i32>,         // Rows on screen at once; None picks by orientation
     monitor_width: i32,
     monitor_height: i32,
+    viewport: Viewport,                // Region of the screen the active ribbon lays out in
+    viewport_splits: Vec<f32>,         // Viewport boundaries as fractions of screen width
+    viewport_index: usize,
+    parked_ribbons: Vec<ParkedRibbon>, // One slot per viewport; the active one's is empty
+    monitor: isize,                    // Display whose ribbon is active (HMONITOR); viewports belong to the primary
+    monitor_ribbons: HashMap<isize, ParkedRibbon>, // Ribbons of the other displays
     last_resolution_check: Instant,
+    displays: HashMap<isize, RECT>,    // Work areas of the non-primary displays, by HMONITOR
     resolution_check_throttle_ms: u64,
     margin_horizontal: i32,
     margin_vertical: i32,
     transparency: u8,
+    curves: Curves,                    // Length and easing of each kind of animation
+    command_throttle_ms: u128,
+    config: Config,                    // Last valid config, to tell what an edit changed
+    config_overrides: Vec<(String, String)>, // --set values, kept over every reload
+    config_modified: Option<std::time::SystemTime>,
     animation_running: Arc<Mutex<bool>>,
     animation_stop_requested: Arc<Mutex<bool>>,
     main_thread_id: u32,
     main_hwnd: HWND,
     command_queue: Vec<QueuedCommand>,
     last_command_time: HashMap<u32, Instant>,
-    animation_fps: u64,
+    animation_fps: Arc<AtomicU64>,     // Read by the animation thread every tick
+    fps_chosen: bool,                  // Picked by the user, so display changes leave it alone
+    refresh_rates: HashMap<isize, u64>, // Of each display's ribbon, by HMONITOR
+    frame_pacing: FramePacing,
     needs_ribbon_recalc: bool,
-    last_ribbon_recalc: Instant,
+    time_tracker: Option<TimeTracker>,
+    idle_threshold: Option<Duration>,  // None disables idle suspension
+    session_id: u32,                   // Windows from other sessions are never managed
+    desktops: session::Desktops,       // Windows on other virtual desktops aren't either
+    mirror: Option<Mirror>,            // Presentation copy of the viewport on a second monitor
+    throw_focus_follows: bool,         // Focus goes with a window sent to another monitor
+    scroll_focus: ScrollFocus,         // Which tile gets focus when a scroll lands
+    row_focus: Option<HashMap<i32, isize>>, // Last focused tile per row; None when disabled
+    row_focus_pending: Option<isize>,  // Tile to focus when the current row switch lands
+    row_pick: Option<i32>,             // Row a held row-switch key lands on when released
+    focus_history: VecDeque<isize>,    // Tiles by when they last had focus, most recent first
+    focused: isize,                    // Foreground window, kept opaque with appearance.dim_unfocused
+    switch_pick: Option<(Vec<isize>, usize)>, // Tiles the open switcher lists, and the one picked
+    row_limit: Option<usize>,          // Most tiles a row takes before limit_policy applies
+    max_row_width: Option<i32>,        // Widest a row may grow, in ribbon units
+    limit_policy: LimitPolicy,
+    stacks: HashMap<isize, Vec<ManagedWindow>>, // Windows stacked behind a tile, next one shown first
+    labels: HashMap<isize, labels::Label>, // Title and icon of every tile, stacked ones included
+    hung: HashSet<isize>,              // Tiles whose app stopped responding; never moved until it recovers
+    fullscreen: HashSet<isize>,        // Tiles gone fullscreen; left alone until they leave it
+    replacing: Option<isize>,          // Window the shell said is about to take another's place
+    force_manage: Vec<String>,         // Classes / exes managed regardless of the style heuristics
+    quirks: Vec<(String, Option<Quirk>)>, // User overrides of the compatibility database
+    opaque_apps: Vec<String>,          // Apps found to go black when layered, remembered in prefs.json
+    rules: Vec<rules::Rule>,           // The config file's per-app rules
+    alpha_overrides: HashMap<isize, u8>, // Alpha given to a window by its rule
+    row_alphas: HashMap<i32, u8>,      // Alpha the config's schedule gives rows right now
+    delayed_adoptions: HashMap<isize, Instant>, // Windows waiting to settle before being tiled, and the earliest they may be
+    rule_floated: HashSet<isize>,      // Tiles a float rule took out once their title matched
+    floated: HashMap<isize, FloatedTile>, // Tiles floated by toggle-float, and where they go back to
+    scratchpad: Option<scratchpad::Scratchpad>, // Window that drops down over the ribbon, never a tile
+    auto_tile: bool,                   // Tile new windows as they appear
+    preexisting: HashSet<isize>,       // Open before auto-tile came on, so left to Win+Shift+T
+    presenting: Option<bool>,          // Presentation mode, holding whether auto-tile was on before it
+    presented_row: i32,                // The one row presentation mode leaves visible
+    sharing: Option<String>,           // Screen sharing app seen running while sharing.guard is on
+    rotated_at: Instant,               // Last row switch of rotation.rows, or input that held it back
+    declined: HashSet<isize>,          // Untiled by hand, so auto-tile leaves them alone
+    minimizing: Vec<isize>,            // Tiles minimized since SHOW_DESKTOP_TIMER_ID was set
+    desktop_shown: bool,               // Tiles are minimized by show desktop and keep their slots
+    adopt_settle: Option<Duration>,    // Settle wait applied to every window; None for delay-adopt apps only
+    transparency_checked: HashSet<isize>, // Windows that took layered alpha without going black
+    opaque_windows: HashSet<isize>,    // Windows found to go black when layered
+    transparency_step: u8,             // Alpha change per keypress before ramping
+    transparency_floor: u8,            // Most transparent the tiles may get
+    transparency_ramp: Option<(Instant, u32)>, // Last adjustment and how many presses in a row
+    native_release: bool,              // Skip the exit animation and let DWM animate the restore
+    instant: bool,                     // Rapid-fire mode: no throttling, animation or transparency
+    animate: bool,                     // Off for reduced motion: everything moves straight into place
+    desktop_profile: Option<bool>, // Follow mode to go back to; Some in tablet posture
+    drag_origin: Option<i32>, // Ribbon offset a drag on the grab strip started from, while one is under way
+    follow: bool,                      // Keep scrolling to whichever tile takes focus
+    dry_run: bool,                     // Keep the layout but only log what would be done to windows
+    macros: macros::Macros,
+    demo: Option<demo::Demo>,          // Guided tour in progress
+    stats: stats::Stats,
+    saved_layout: Option<restore::SavedLayout>,   // Last written to disk, to skip unchanged saves
 }
 
 impl RibbonTiler {
-    fn new() -> Self {
+    fn new(options: &Options, config: &Config) -> Self {
         let (width, height) = Self::get_monitor_dimensions();
+        let viewport_splits = if width as f32 / height.max(1) as f32 >= ULTRAWIDE_ASPECT {
+            options.viewport_splits.clone()
+        } else {
+            Vec::new()
+        };
         let main_thread_id = unsafe { GetCurrentThreadId() };
+        let prefs = Prefs::load();
         
-        // Create a hidden window for message processing
+        // Create a hidden window for message processing, named per session
+        let window_title = HSTRING::from(session::scoped_name("RibbonTilerMessageWindow"));
         let main_hwnd = unsafe {
             let hwnd = CreateWindowExW(
                 WINDOW_EX_STYLE(0),
                 w!("STATIC"),
-                w!("RibbonTilerMessageWindow"),
+                &window_title,
                 WS_OVERLAPPED,
                 0, 0, 0, 0,
                 HWND::default(),
@@ -167,6 +669,7 @@ impl RibbonTiler {
         
         Self {
             windows: HashMap::new(),
+            rows: HashMap::new(),
             floating_windows: HashMap::new(),
             ribbon_offset: 0,
             ribbon_offset_target: 0,
@@ -174,23 +677,101 @@ impl RibbonTiler {
             vertical_offset_target: 0,
             scroll_animation: None,
             current_row: 0,
-            row_height: height,  // Each row is full monitor height
+            row_height: Self::row_height_for(width, height, config.layout.visible_rows),
+            visible_rows: config.layout.visible_rows,
             monitor_width: width,
             monitor_height: height,
+            viewport: Self::viewport_bounds(&viewport_splits, 0, width),
+            parked_ribbons: (0..=viewport_splits.len()).map(|_| ParkedRibbon::default()).collect(),
+            monitor: monitor::primary_handle(),
+            monitor_ribbons: HashMap::new(),
+            viewport_splits,
+            viewport_index: 0,
             last_resolution_check: Instant::now(),
+            displays: monitor::secondary_work_areas(),
             resolution_check_throttle_ms: 1000,
-            margin_horizontal: 40,
-            margin_vertical: 80,
-            transparency: 255,
+            margin_horizontal: config.layout.margin_horizontal,
+            margin_vertical: config.layout.margin_vertical,
+            transparency: config.appearance.transparency,
+            curves: config.animations.curves(),
+            command_throttle_ms: config.input.command_throttle_ms as u128,
+            config: config.clone(),
+            config_overrides: options.config_overrides.clone(),
+            config_modified: config::modified(),
             animation_running: Arc::new(Mutex::new(false)),
             animation_stop_requested: Arc::new(Mutex::new(false)),
             main_thread_id,
             main_hwnd,
             command_queue: Vec::new(),
             last_command_time: HashMap::new(),
-            animation_fps: 90,
+            animation_fps: Arc::new(AtomicU64::new(
+                prefs.animation_fps.or(config.animations.fps)
+                    .or_else(|| Self::get_refresh_rate(monitor::primary_handle()))
+                    .unwrap_or(90),
+            )),
+            fps_chosen: prefs.animation_fps.is_some() || config.animations.fps.is_some(),
+            refresh_rates: Self::refresh_rates(&monitor::secondary_work_areas()),
+            frame_pacing: options.frame_pacing,
             needs_ribbon_recalc: false,
-            last_ribbon_recalc: Instant::now(),
+            time_tracker: options.time_tracking.clone().map(TimeTracker::new),
+            idle_threshold: (options.idle_minutes > 0)
+                .then(|| Duration::from_secs(options.idle_minutes * 60)),
+            session_id: session::current_session_id(),
+            desktops: session::Desktops::new(),
+            mirror: None,
+            throw_focus_follows: options.throw_focus_follows,
+            scroll_focus: options.scroll_focus,
+            row_focus: options.row_focus_memory.then(HashMap::new),
+            row_focus_pending: None,
+            row_pick: None,
+            focus_history: VecDeque::new(),
+            focused: unsafe { GetForegroundWindow() }.0,
+            switch_pick: None,
+            row_limit: config.layout.row_limit,
+            max_row_width: Self::row_width_units(config.layout.max_row_width),
+            limit_policy: config.layout.limit_policy,
+            stacks: HashMap::new(),
+            labels: HashMap::new(),
+            hung: HashSet::new(),
+            fullscreen: HashSet::new(),
+            replacing: None,
+            force_manage: options.force_manage.clone(),
+            quirks: options.quirks.clone(),
+            opaque_apps: prefs.opaque_apps.clone(),
+            rules: rules::compile(&config.rules),
+            alpha_overrides: HashMap::new(),
+            row_alphas: HashMap::new(),
+            delayed_adoptions: HashMap::new(),
+            rule_floated: HashSet::new(),
+            floated: HashMap::new(),
+            scratchpad: None,
+            auto_tile: options.auto_tile,
+            preexisting: if options.auto_tile { Self::open_windows() } else { HashSet::new() },
+            presenting: None,
+            presented_row: 0,
+            sharing: None,
+            rotated_at: Instant::now(),
+            declined: HashSet::new(),
+            minimizing: Vec::new(),
+            desktop_shown: false,
+            adopt_settle: (options.adopt_settle_ms > 0)
+                .then(|| Duration::from_millis(options.adopt_settle_ms)),
+            transparency_checked: HashSet::new(),
+            opaque_windows: HashSet::new(),
+            transparency_step: options.transparency_step,
+            transparency_floor: options.transparency_floor,
+            transparency_ramp: None,
+            native_release: options.native_release,
+            instant: options.instant,
+            animate: config.animations.enabled.unwrap_or_else(Self::system_animations),
+            desktop_profile: None,
+            drag_origin: None,
+            follow: false,
+            dry_run: options.dry_run,
+            macros: macros::Macros::load(),
+            stats: stats::Stats::load(),
+            saved_layout: None,
+            demo: None,
         }
     }
 
@@ -202,6 +783,65 @@ impl RibbonTiler {
         }
     }
 
+    // layout.max_row_width is in screen widths
+    fn row_width_units(screens: Option<f32>) -> Option<i32> {
+        screens.map(|width| (width * RIBBON_SPAN as f32) as i32)
+    }
+    
+    // Landscape screens show one full-height row; portrait screens stack two by default
+    fn row_height_for(width: i32, height: i32, visible_rows: Option<i32>) -> i32 {
+        let rows = visible_rows.unwrap_or(if height > width { 2 } else { 1 });
+        height / rows.max(1)
+    }
+
+    // Bounds of one virtual viewport, from the split fractions
+    fn viewport_bounds(splits: &[f32], index: usize, screen_width: i32) -> Viewport {
+        let edge = |i: usize| match i {
+            0 => 0,
+            i if i > splits.len() => screen_width,
+            i => (splits[i - 1] * screen_width as f32) as i32,
+        };
+        let left = edge(index);
+        Viewport {
+            left,
+            width: edge(index + 1) - left,
+        }
+    }
+
+    // Windows' "Animation effects" setting, which people who want less motion turn off
+    fn system_animations() -> bool {
+        let mut enabled = BOOL(1);
+        unsafe {
+            SystemParametersInfoW(SPI_GETCLIENTAREAANIMATION, 0, Some(&mut enabled as *mut BOOL as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0)).ok();
+        }
+        enabled.as_bool()
+    }
+    
+    // Refresh rate of a display, clamped to sane animation rates
+    fn get_refresh_rate(monitor: isize) -> Option<u64> {
+        monitor::refresh_rate(monitor).map(|hz| (hz as u64).clamp(30, 240))
+    }
+    
+    // Rates of the primary and every display in `displays` that reports one
+    fn refresh_rates(displays: &HashMap<isize, RECT>) -> HashMap<isize, u64> {
+        std::iter::once(monitor::primary_handle())
+            .chain(displays.keys().copied())
+            .filter_map(|monitor| Some((monitor, Self::get_refresh_rate(monitor)?)))
+            .collect()
+    }
+    
+    // Animations tick at the rate of the display whose ribbon is active, unless the
+    // user picked one
+    fn follow_refresh_rate(&self) {
+        if self.fps_chosen {
+            return;
+        }
+        if let Some(&fps) = self.refresh_rates.get(&self.monitor) {
+            self.animation_fps.store(fps, Ordering::Relaxed);
+        }
+    }
+
     // Process queued commands - called from message loop
     fn process_command_queue(&mut self) {
         let commands = std::mem::take(&mut self.command_queue);
@@ -209,16 +849,18 @@ impl RibbonTiler {
         
         // Clean up closed windows before processing commands
         self.clean_closed_windows();
+        self.check_hung();
         
         for queued in commands {
             let should_throttle = match queued.command {
                 TilerCommand::PanLeft | TilerCommand::PanRight | TilerCommand::PanUp | TilerCommand::PanDown => false,
+                TilerCommand::FocusLeft | TilerCommand::FocusRight | TilerCommand::FocusUp | TilerCommand::FocusDown => false,
                 _ => true,
             };
             
-            if should_throttle {
+            if should_throttle && !self.instant {
                 if let Some(&last_time) = self.last_command_time.get(&(queued.command as u32)) {
-                    if now.duration_since(last_time).as_millis() < 50 {
+                    if now.duration_since(last_time).as_millis() < self.command_throttle_ms {
                         continue;
                     }
                 }
@@ -226,6 +868,13 @@ impl RibbonTiler {
             
             self.last_command_time.insert(queued.command as u32, now);
             
+            // Commands act on the ribbon of the display the window is on, and for a tile
+            // in another viewport, on that viewport's ribbon
+            self.activate_monitor(self.monitor_for(queued.hwnd));
+            if let Some(index) = self.parked_viewport_of(queued.hwnd) {
+                self.activate_viewport(index);
+            }
+            
             match queued.command {
                 TilerCommand::PanLeft => self.pan_ribbon(Direction::Left),
                 TilerCommand::PanRight => self.pan_ribbon(Direction::Right),
@@ -237,9 +886,12 @@ impl RibbonTiler {
                 TilerCommand::MoveDown => self.move_window(queued.hwnd, Direction::Down),
                 TilerCommand::MoveLeft => self.move_window(queued.hwnd, Direction::Left),
                 TilerCommand::MoveRight => self.move_window(queued.hwnd, Direction::Right),
-                TilerCommand::AddWindow => { self.add_window(queued.hwnd); },
-                TilerCommand::IncreaseTransparency => self.adjust_transparency(10),
-                TilerCommand::DecreaseTransparency => self.adjust_transparency(-10),
+                TilerCommand::AddWindow => {
+                    self.declined.remove(&queued.hwnd.0);
+                    self.add_window(queued.hwnd);
+                },
+                TilerCommand::IncreaseTransparency => self.adjust_transparency(1),
+                TilerCommand::DecreaseTransparency => self.adjust_transparency(-1),
                 TilerCommand::ScrollToWindow => {
                     if self.windows.contains_key(&queued.hwnd.0) {
                         self.scroll_to_window(queued.hwnd);
@@ -248,6 +900,7 @@ impl RibbonTiler {
                 TilerCommand::IncreaseMargins => self.adjust_margins(5),
                 TilerCommand::DecreaseMargins => self.adjust_margins(-5),
                 TilerCommand::RemoveWindow => {
+                    self.declined.insert(queued.hwnd.0);
                     self.remove_window(queued.hwnd);
                     if self.needs_ribbon_recalc {
                         self.recalculate_ribbon();
@@ -258,8 +911,385 @@ impl RibbonTiler {
                     self.clean_closed_windows();
                     self.recalculate_ribbon();
                 },
+                TilerCommand::ToggleMirror => self.toggle_mirror(),
+                TilerCommand::SendToMonitorLeft => self.send_to_monitor(queued.hwnd, Direction::Left),
+                TilerCommand::SendToMonitorRight => self.send_to_monitor(queued.hwnd, Direction::Right),
+                TilerCommand::CycleViewport => {
+                    let next = (self.viewport_index + 1) % self.parked_ribbons.len();
+                    self.activate_viewport(next);
+                },
+                TilerCommand::CancelAnimations => self.cancel_animations(),
+                TilerCommand::ToggleInstant => self.toggle_instant(),
+                TilerCommand::PullWindow => self.pull_window(queued.hwnd),
+                TilerCommand::DropWindow => self.drop_window(queued.hwnd),
+                TilerCommand::RetryWindow => self.retry_window(queued.hwnd),
+                TilerCommand::ToggleDryRun => self.toggle_dry_run(),
+                TilerCommand::RecordMacro => self.toggle_macro_recording(),
+                TilerCommand::ReplayMacro => self.play_macro(None),
+                TilerCommand::Demo => self.start_demo(),
+                TilerCommand::ShowHeatmap => self.show_heatmap(),
+                TilerCommand::PanStart => self.pan_ribbon_to_edge(Direction::Left),
+                TilerCommand::PanEnd => self.pan_ribbon_to_edge(Direction::Right),
+                TilerCommand::ToggleFollow => self.toggle_follow(queued.hwnd),
+                TilerCommand::ResizeGrow => self.resize_window_by(queued.hwnd, 1),
+                TilerCommand::ResizeShrink => self.resize_window_by(queued.hwnd, -1),
+                TilerCommand::StackWindow => self.stack_window(queued.hwnd),
+                TilerCommand::UnstackWindow => self.unstack_window(queued.hwnd),
+                TilerCommand::CycleStack => self.cycle_stack(queued.hwnd),
+                TilerCommand::ToggleFloat => self.toggle_float(queued.hwnd),
+                TilerCommand::TogglePresentation => self.toggle_presentation(),
+                TilerCommand::SetScratchpad => self.set_scratchpad(queued.hwnd),
+                TilerCommand::ToggleScratchpad => self.toggle_scratchpad(),
+                TilerCommand::FocusLeft => self.focus_neighbour(queued.hwnd, Direction::Left),
+                TilerCommand::FocusRight => self.focus_neighbour(queued.hwnd, Direction::Right),
+                TilerCommand::FocusUp => self.focus_neighbour(queued.hwnd, Direction::Up),
+                TilerCommand::FocusDown => self.focus_neighbour(queued.hwnd, Direction::Down),
+                TilerCommand::CopyLayout => self.copy_layout(),
+                // From the pipe or a macro there is no key to release, so go straight to
+                // the last tile used before this one
+                TilerCommand::SwitchWindow => {
+                    if let Some(&hwnd) = self.recent_tiles().get(1) {
+                        self.go_to_tile(HWND(hwnd));
+                    }
+                },
+                TilerCommand::ImportLayout => self.import_layout(),
+                TilerCommand::ToggleAnimations => self.toggle_animations(),
+                TilerCommand::RetryTransparency => self.retry_transparency(queued.hwnd),
+            }
+        }
+
+        self.update_presentation();
+        self.update_mirror();
+        self.update_tray();
+    }
+
+    // Status in the notification area icon's tooltip
+    fn update_tray(&self) {
+        let mut status = format!("Thymeline\n{} tiles, row {}", self.windows.len(), self.current_row + 1);
+        for (on, note) in [
+            (IDLE_SUSPENDED.load(Ordering::Relaxed), "Paused while idle"),
+            (self.macros.is_recording(), "Recording a macro"),
+            (self.instant, "Instant mode"),
+            (!self.animate, "Animations off"),
+            (self.desktop_profile.is_some(), "Tablet profile"),
+            (self.follow, "Following focus"),
+            (self.presenting.is_some(), "Presentation mode"),
+            (self.sharing.is_some(), "Screen sharing guard"),
+            (!self.config.rotation.rows.is_empty(), "Rotating rows"),
+            (self.dry_run, "Dry run"),
+        ] {
+            if on {
+                status.push('\n');
+                status.push_str(note);
+            }
+        }
+        tray::set_status(&status);
+    }
+
+    // Called from the message loop whenever the foreground window changes
+    fn on_foreground_changed(&mut self, hwnd: HWND) {
+        if let Some(index) = self.parked_viewport_of(hwnd) {
+            self.activate_viewport(index);
+        }
+        
+        self.update_passthrough(hwnd);
+        self.end_show_desktop(hwnd);
+        // A stacked window activated from the taskbar or Alt+Tab comes to the front
+        self.raise_stacked(hwnd);
+        self.auto_tile_window(hwnd);
+        
+        let unfocused = std::mem::replace(&mut self.focused, hwnd.0);
+        if self.config.appearance.dim_unfocused && unfocused != hwnd.0 {
+            self.refresh_alpha(HWND(unfocused));
+            self.refresh_alpha(hwnd);
+        }
+        
+        let managed = self.windows.contains_key(&hwnd.0);
+        if let Some(tracker) = self.time_tracker.as_mut() {
+            tracker.on_focus(hwnd, managed);
+        }
+        
+        if let (Some(row_focus), Some(window)) = (self.row_focus.as_mut(), self.windows.get(&hwnd.0)) {
+            row_focus.insert(window.position.row, hwnd.0);
+        }
+        if managed {
+            self.focus_history.retain(|&h| h != hwnd.0);
+            self.focus_history.push_front(hwnd.0);
+            self.focus_history.truncate(FOCUS_HISTORY_LEN);
+        }
+        
+        let tile = self.windows.get(&hwnd.0).map(|w| (w.position.row, Self::ribbon_columns(&w.position)));
+        self.stats.focus(tile);
+        
+        if self.follow && managed && self.sharing.is_none() {
+            self.scroll_to_window(hwnd);
+        }
+    }
+
+    // With --auto-tile, a new window that appears or takes focus is tiled if it would be
+    // on Win+Shift+T. Windows untiled by hand stay untiled, and ones already open when
+    // auto-tile came on are left as they are.
+    fn auto_tile_window(&mut self, hwnd: HWND) {
+        if !self.auto_tile || hwnd.0 == 0 || self.declined.contains(&hwnd.0) || self.preexisting.contains(&hwnd.0) {
+            return;
+        }
+        if self.windows.contains_key(&hwnd.0) || self.floating_windows.contains_key(&hwnd.0)
+            || self.delayed_adoptions.contains_key(&hwnd.0) {
+            return;
+        }
+        // Only top-level windows; the event hooks report child objects too
+        if unsafe { GetAncestor(hwnd, GA_ROOT) } != hwnd {
+            return;
+        }
+        if self.should_manage_window(hwnd) {
+            self.add_window(hwnd);
+        }
+    }
+    
+    fn open_windows() -> HashSet<isize> {
+        window_info::top_level_windows().into_iter().map(|hwnd| hwnd.0).collect()
+    }
+    
+    // Called from the idle timer - goes quiet once the user has been away long enough
+    fn check_idle(&mut self) {
+        let Some(threshold) = self.idle_threshold else {
+            return;
+        };
+        
+        let idle_time = idle::user_idle_time();
+        
+        if IDLE_SUSPENDED.load(Ordering::Relaxed) {
+            // Mouse input doesn't pass through the keyboard hook, so the timer catches it
+            if idle_time < threshold {
+                self.resume_from_idle();
+            }
+            return;
+        }
+        
+        // Rotating rows is the work being done while nobody is at the keyboard
+        if idle_time < threshold || *self.animation_running.lock().unwrap() || !self.config.rotation.rows.is_empty() {
+            return;
+        }
+        
+        println!("Idle for {} min, suspending background work", idle_time.as_secs() / 60);
+        IDLE_SUSPENDED.store(true, Ordering::Relaxed);
+        *self.animation_stop_requested.lock().unwrap() = true;
+        
+        if let Some(tracker) = self.time_tracker.as_mut() {
+            tracker.finish_current();
+        }
+        self.stats.focus(None);
+        self.update_tray();
+    }
+    
+    fn resume_from_idle(&mut self) {
+        if let Some(threshold) = self.idle_threshold {
+            // Foreground changes can come from apps rather than the user
+            if idle::user_idle_time() >= threshold {
+                IDLE_SUSPENDED.store(true, Ordering::Relaxed);
+                return;
+            }
+        }
+        
+        IDLE_SUSPENDED.store(false, Ordering::Relaxed);
+        println!("Input detected, resuming");
+        
+        self.clean_closed_windows();
+        self.clean_minimized_windows();
+        self.recalculate_ribbon();
+        
+        let foreground = unsafe { GetForegroundWindow() };
+        self.on_foreground_changed(foreground);
+    }
+
+    // Viewport whose parked ribbon holds this window, if it isn't the active one
+    fn parked_viewport_of(&self, hwnd: HWND) -> Option<usize> {
+        self.parked_ribbons.iter().position(|parked| parked.windows.contains_key(&hwnd.0))
+    }
+
+    // Park the active ribbon where it stands and make `next` the active one. In-flight
+    // animations are settled first so parked tiles sit exactly where the layout says.
+    fn swap_ribbon(&mut self, next: ParkedRibbon) -> ParkedRibbon {
+        self.finish_animations();
+        
+        let parked = ParkedRibbon {
+            windows: std::mem::replace(&mut self.windows, next.windows),
+            rows: std::mem::replace(&mut self.rows, next.rows),
+            ribbon_offset: self.ribbon_offset,
+            vertical_offset: self.vertical_offset,
+            current_row: self.current_row,
+        };
+        self.ribbon_offset = next.ribbon_offset;
+        self.ribbon_offset_target = next.ribbon_offset;
+        self.vertical_offset = next.vertical_offset;
+        self.vertical_offset_target = next.vertical_offset;
+        self.current_row = next.current_row;
+        parked
+    }
+
+    // Hand the keyboard to another viewport's ribbon
+    fn activate_viewport(&mut self, index: usize) {
+        if index == self.viewport_index || index >= self.parked_ribbons.len() {
+            return;
+        }
+        // Viewports split the primary display only
+        if self.displays.contains_key(&self.monitor) {
+            return;
+        }
+        
+        let next = std::mem::take(&mut self.parked_ribbons[index]);
+        self.parked_ribbons[self.viewport_index] = self.swap_ribbon(next);
+        self.viewport_index = index;
+        self.viewport = Self::viewport_bounds(&self.viewport_splits, index, self.monitor_width);
+        
+        println!("Viewport {}/{} active", index + 1, self.parked_ribbons.len());
+        self.recalculate_ribbon();
+    }
+
+    // Display whose ribbon a command about this window should act on
+    fn monitor_for(&self, hwnd: HWND) -> isize {
+        if self.windows.contains_key(&hwnd.0) {
+            return self.monitor;
+        }
+        if self.parked_viewport_of(hwnd).is_some() {
+            return monitor::primary_handle();
+        }
+        self.monitor_ribbons.iter()
+            .find(|(_, ribbon)| ribbon.windows.contains_key(&hwnd.0))
+            .map(|(&monitor, _)| monitor)
+            .unwrap_or_else(|| monitor::of_window(hwnd))
+    }
+
+    // Each display keeps its own ribbon; switching parks the current one like a viewport
+    fn activate_monitor(&mut self, monitor: isize) {
+        if monitor == self.monitor {
+            return;
+        }
+        
+        let next = self.monitor_ribbons.remove(&monitor).unwrap_or_default();
+        let parked = self.swap_ribbon(next);
+        self.monitor_ribbons.insert(self.monitor, parked);
+        self.monitor = monitor;
+        self.follow_refresh_rate();
+        
+        // Other displays are never split into viewports
+        self.viewport = if self.displays.contains_key(&monitor) {
+            Self::viewport_bounds(&[], 0, self.monitor_width)
+        } else {
+            Self::viewport_bounds(&self.viewport_splits, self.viewport_index, self.monitor_width)
+        };
+        self.recalculate_ribbon();
+    }
+
+    // Display a window should be tiled on, if that display is attached: its rule's, or else
+    // the one row_displays gives `row` of the primary's ribbon
+    fn preferred_display(&self, hwnd: HWND, row: Option<i32>) -> Option<isize> {
+        let name = rules::lookup(&self.rules, hwnd).and_then(|rule| rule.display.as_deref())
+            .or_else(|| {
+                let row = row?;
+                self.config.row_displays.iter().find(|preference| preference.row == row)
+                    .map(|preference| preference.display.as_str())
+            })?;
+        monitor::named(name)
+    }
+
+    // Take a tile out of whichever display's ribbon holds it, leaving the gap for layout
+    fn take_tile(&mut self, monitor: isize, hwnd: isize) -> Option<ManagedWindow> {
+        if monitor == self.monitor {
+            self.detach_from_row(hwnd);
+            return self.windows.remove(&hwnd);
+        }
+        self.monitor_ribbons.get_mut(&monitor)?.remove(hwnd)
+    }
+
+    // Put a tile at the end of its row on a display's ribbon
+    fn put_tile(&mut self, monitor: isize, mut window: ManagedWindow) {
+        window.position.monitor = monitor;
+        let (hwnd, row) = (window.hwnd.0, window.position.row);
+        let (windows, rows) = if monitor == self.monitor {
+            (&mut self.windows, &mut self.rows)
+        } else {
+            let ribbon = self.monitor_ribbons.entry(monitor).or_default();
+            (&mut ribbon.windows, &mut ribbon.rows)
+        };
+        windows.insert(hwnd, window);
+        rows.entry(row).or_default().push(hwnd);
+    }
+
+    // After a display came or went: tiles whose rule or row prefers a display that is
+    // attached now move onto its ribbon, keeping their row
+    fn apply_display_preferences(&mut self) {
+        if self.rules.iter().all(|rule| rule.display.is_none()) && self.config.row_displays.is_empty() {
+            return;
+        }
+        let primary = monitor::primary_handle();
+        let tiles: Vec<(isize, HWND, i32)> = self.windows.values()
+            .map(|w| (self.monitor, w))
+            .chain(self.monitor_ribbons.iter().flat_map(|(&monitor, ribbon)| ribbon.windows.values().map(move |w| (monitor, w))))
+            .map(|(monitor, w)| (monitor, w.hwnd, w.position.row))
+            .collect();
+        let moves: Vec<(HWND, isize, isize)> = tiles.into_iter()
+            .filter_map(|(from, hwnd, row)| {
+                // Row preferences are about the primary's rows only
+                let to = self.preferred_display(hwnd, (from == primary).then_some(row))?;
+                (to != from).then_some((hwnd, from, to))
+            })
+            .collect();
+        if moves.is_empty() {
+            return;
+        }
+        
+        let home = self.monitor;
+        let mut changed = HashSet::new();
+        for (hwnd, from, to) in moves {
+            if let Some(window) = self.take_tile(from, hwnd.0) {
+                println!("Moving '{}' to its preferred display", window_info::window_title(hwnd));
+                self.put_tile(to, window);
+                changed.extend([from, to]);
+            }
+        }
+        for monitor in changed {
+            self.activate_monitor(monitor);
+            self.layout_rows();
+            self.apply_all_windows(false);
+        }
+        self.activate_monitor(home);
+    }
+
+    // Tiles of a display that went away join the primary's ribbon, at the end of their rows
+    fn rehome_lost_displays(&mut self) {
+        let primary = monitor::primary_handle();
+        if self.monitor != primary && !self.displays.contains_key(&self.monitor) {
+            self.activate_monitor(primary);
+        }
+        
+        let lost: Vec<isize> = self.monitor_ribbons.keys()
+            .copied()
+            .filter(|&m| m != primary && !self.displays.contains_key(&m))
+            .collect();
+        if lost.is_empty() {
+            return;
+        }
+        self.activate_monitor(primary);
+        
+        for monitor in lost {
+            let Some(mut ribbon) = self.monitor_ribbons.remove(&monitor) else {
+                continue;
+            };
+            let mut rows: Vec<(i32, Vec<isize>)> = ribbon.rows.drain().collect();
+            rows.sort_by_key(|(row, _)| *row);
+            for (row, order) in rows {
+                for hwnd in order {
+                    if let Some(mut window) = ribbon.windows.remove(&hwnd) {
+                        window.position.monitor = primary;
+                        self.windows.insert(hwnd, window);
+                        self.rows.entry(row).or_default().push(hwnd);
+                    }
+                }
             }
         }
+        self.layout_rows();
+        self.apply_all_windows(false);
     }
 
     fn queue_command(&mut self, command: TilerCommand, hwnd: HWND) {
@@ -270,11 +1300,6 @@ impl RibbonTiler {
         });
     }
 
-    fn ease_out_cubic(t: f32) -> f32 {
-        let t = t - 1.0;
-        t * t * t + 1.0
-    }
-
     fn lerp(start: i32, end: i32, t: f32) -> i32 {
         start + ((end - start) as f32 * t) as i32
     }
@@ -282,8 +1307,8 @@ impl RibbonTiler {
     fn is_window_visible(&self, pos: &RibbonPosition) -> bool {
         // Check horizontal visibility
         let window_start = pos.x - self.ribbon_offset;
-        let window_end = window_start + self.get_tile_width(&pos.size);
-        let h_visible = window_end >= -self.monitor_width && window_start <= self.monitor_width * 2;
+        let window_end = window_start + self.get_tile_width(pos);
+        let h_visible = window_end >= -RIBBON_SPAN && window_start <= RIBBON_SPAN * 2;
         
         // Check vertical visibility
         let window_top = pos.row * self.row_height - self.vertical_offset;
@@ -293,42 +1318,352 @@ impl RibbonTiler {
         h_visible && v_visible
     }
 
-    // Update animations
-    fn update_animations(&mut self) {
-        let now = Instant::now();
-        let mut animations_complete = Vec::new();
-        let mut window_updates = Vec::new();
-        let mut need_reposition = false;
-
-        // Update combined scroll animation
-        if let Some(scroll_anim) = &self.scroll_animation {
-            let elapsed = now.duration_since(scroll_anim.start_time);
-            
-            if elapsed >= scroll_anim.duration {
-                self.ribbon_offset = scroll_anim.target_x;
-                self.ribbon_offset_target = scroll_anim.target_x;
-                self.vertical_offset = scroll_anim.target_y;
-                self.vertical_offset_target = scroll_anim.target_y;
-                self.scroll_animation = None;
-                self.focus_visible_window();
-                self.needs_ribbon_recalc = true;
-            } else {
-                let t = elapsed.as_secs_f32() / scroll_anim.duration.as_secs_f32();
-                let eased_t = Self::ease_out_cubic(t);
-                
-                self.ribbon_offset = Self::lerp(scroll_anim.start_x, scroll_anim.target_x, eased_t);
-                self.vertical_offset = Self::lerp(scroll_anim.start_y, scroll_anim.target_y, eased_t);
-            }
-            need_reposition = true;
+    // Jump every in-flight animation straight to where it was heading, for anything that
+    // needs a steady layout before carrying on. Focus stays put; callers decide whether
+    // a cut-short scroll should still move it.
+    fn finish_animations(&mut self) {
+        self.scroll_animation = None;
+        self.ribbon_offset = self.ribbon_offset_target;
+        self.vertical_offset = self.vertical_offset_target;
+        
+        let exiting: Vec<ManagedWindow> = self.windows.values()
+            .filter(|w| w.animation.as_ref().is_some_and(|a| a.animation_type == AnimationType::Exit))
+            .cloned()
+            .collect();
+        for window in &exiting {
+            self.windows.remove(&window.hwnd.0);
+            self.restore_window(window);
         }
-
-        // Collect ribbon repositions if needed
-        if need_reposition {
-            for window in self.windows.values() {
-                if window.animation.is_none() {
-                    let rect = self.ribbon_to_screen(&window.position);
-                    window_updates.push((window.hwnd, rect));
-                }
+        for window in self.windows.values_mut() {
+            window.animation = None;
+        }
+        if let Some(pad) = self.scratchpad.as_mut() {
+            pad.step(Curve::INSTANT);
+        }
+        if !exiting.is_empty() {
+            // Close the gaps now rather than at the next recalculation
+            self.layout_rows();
+        }
+        self.apply_all_windows(false);
+        
+        *self.animation_stop_requested.lock().unwrap() = true;
+    }
+
+    // Like finish_animations, but a cut-short scroll still moves focus as if it had landed
+    fn cancel_animations(&mut self) {
+        let scrolling = self.scroll_animation.is_some();
+        self.finish_animations();
+        if scrolling {
+            self.apply_scroll_focus();
+        }
+    }
+
+    fn toggle_instant(&mut self) {
+        self.instant = !self.instant;
+        if self.instant {
+            self.cancel_animations();
+            println!("Instant mode on: no animation, throttling or transparency");
+        } else {
+            println!("Instant mode off");
+        }
+        self.reapply_transparency();
+    }
+
+    // A convertible folded into a tablet, or with its keyboard detached. Desktops without
+    // a built-in touch screen report slate mode too, so they never count.
+    fn in_tablet_posture() -> bool {
+        unsafe {
+            GetSystemMetrics(SM_DIGITIZER) as u32 & NID_INTEGRATED_TOUCH != 0
+                && GetSystemMetrics(SM_CONVERTIBLESLATEMODE) == 0
+        }
+    }
+    
+    // Called at startup and on WM_SETTINGCHANGE: switch to the touch-friendly profile when
+    // the device becomes a tablet, and back when the keyboard returns
+    fn check_posture(&mut self) {
+        let tablet = self.config.tablet.auto && Self::in_tablet_posture();
+        if tablet == self.desktop_profile.is_some() {
+            return;
+        }
+        if !tablet {
+            self.leave_tablet_profile();
+            return;
+        }
+        TABLET_POSTURE.store(true, Ordering::Relaxed);
+        self.desktop_profile = Some(self.follow);
+        self.margin_horizontal = self.config.tablet.margin_horizontal;
+        self.margin_vertical = self.config.tablet.margin_vertical;
+        self.follow = self.config.tablet.follow;
+        println!("Tablet posture, switching to the tablet profile");
+        osd::show("Tablet profile", 0.0);
+        if self.config.tablet.grab_strip {
+            grabstrip::show();
+        }
+        self.apply_all_windows(false);
+        self.update_tray();
+    }
+    
+    fn leave_tablet_profile(&mut self) {
+        let Some(follow) = self.desktop_profile.take() else {
+            return;
+        };
+        TABLET_POSTURE.store(false, Ordering::Relaxed);
+        grabstrip::close();
+        self.drag_origin = None;
+        // Margins come from the config, which may have been reloaded in tablet posture
+        self.margin_horizontal = self.config.layout.margin_horizontal;
+        self.margin_vertical = self.config.layout.margin_vertical;
+        self.follow = follow;
+        println!("Back to the desktop profile");
+        osd::show("Desktop profile", 0.0);
+        self.apply_all_windows(false);
+        self.update_tray();
+    }
+    
+    // Reduced motion: tiles and scrolls land at once, while transparency and throttling
+    // stay as they are, unlike instant mode
+    fn toggle_animations(&mut self) {
+        self.animate = !self.animate;
+        if self.animate {
+            osd::show("Animations on", 0.0);
+        } else {
+            self.cancel_animations();
+            osd::show("Animations off", 0.0);
+        }
+        self.update_tray();
+    }
+    
+    // Whether moves slide into place
+    fn animates(&self) -> bool {
+        self.animate && !self.instant
+    }
+
+    // Follow mode scrolls to each tile as it takes focus, as if Win+S were pressed every
+    // time; held Win+S turns it on and off by default
+    fn toggle_follow(&mut self, hwnd: HWND) {
+        self.follow = !self.follow;
+        // Toggled in tablet posture, it stays that way back on the desktop
+        if let Some(follow) = self.desktop_profile.as_mut() {
+            *follow = self.follow;
+        }
+        if self.follow {
+            println!("Following focus");
+            osd::show("Following focus", 0.0);
+            if self.windows.contains_key(&hwnd.0) {
+                self.scroll_to_window(hwnd);
+            }
+        } else {
+            println!("Stopped following focus");
+            osd::show("Stopped following focus", 0.0);
+        }
+    }
+
+    // For screen sharing: no auto-tiling, no on-screen messages, every tile opaque and
+    // only the current row visible. Turning it off puts all of it back at once.
+    fn toggle_presentation(&mut self) {
+        match self.presenting.take() {
+            Some(auto_tile) => {
+                self.auto_tile = auto_tile;
+                // Windows opened during the presentation weren't new to auto-tile
+                if auto_tile {
+                    self.preexisting = Self::open_windows();
+                }
+                osd::set_muted(self.sharing.is_some());
+                osd::show("Presentation mode off", 0.0);
+            }
+            None => {
+                self.presenting = Some(std::mem::replace(&mut self.auto_tile, false));
+                osd::set_muted(true);
+                rowstrip::close();
+            }
+        }
+        self.presented_row = self.current_row;
+        self.reapply_transparency();
+        self.update_tray();
+    }
+
+    // The row shown in presentation mode follows the current one. Only alpha changes,
+    // so a scroll under way carries on.
+    fn update_presentation(&mut self) {
+        if self.presenting.is_none() || self.presented_row == self.current_row {
+            return;
+        }
+        self.presented_row = self.current_row;
+        let hwnds: Vec<HWND> = self.windows.values()
+            .filter(|w| !self.is_frozen(w.hwnd.0))
+            .map(|w| w.hwnd)
+            .collect();
+        for hwnd in hwnds {
+            self.apply_transparency(hwnd);
+        }
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        match self.macros.toggle_recording() {
+            None => {
+                println!("Recording macro");
+                osd::show("Recording macro", 1.0);
+            }
+            Some(steps) => {
+                println!("Recorded a macro of {} commands", steps);
+                osd::show(&format!("Macro recorded: {} commands", steps), 0.0);
+            }
+        }
+    }
+
+    // None replays the last recording
+    fn play_macro(&mut self, name: Option<&str>) {
+        if self.macros.play(name, false) {
+            post_macro(0);
+        } else if self.macros.is_recording() {
+            println!("Stop recording before playing a macro");
+        } else {
+            println!("No macro {}", name.map_or("recorded yet".to_string(), |name| format!("named '{}'", name)));
+        }
+    }
+
+    fn start_demo(&mut self) {
+        if self.demo.is_some() {
+            return;
+        }
+        match demo::Demo::start() {
+            Some(tour) => {
+                println!("Starting the demo");
+                self.demo = Some(tour);
+                unsafe {
+                    SetTimer(self.main_hwnd, demo::DEMO_TIMER_ID, demo::TICK_MS, None);
+                }
+            }
+            None => println!("Warning: Failed to create the demo windows"),
+        }
+    }
+
+    // Called from the demo timer: keep the caption up and run each step's command
+    fn advance_demo(&mut self) {
+        let Some(tour) = self.demo.as_mut() else {
+            return;
+        };
+        match tour.tick() {
+            Some(tick) => {
+                osd::show(tick.caption, tick.progress);
+                if let Some((command, hwnd)) = tick.command {
+                    focus::focus_window(hwnd);
+                    post_tiler_command(command, hwnd);
+                }
+            }
+            None => {
+                self.demo = None;
+                unsafe {
+                    KillTimer(self.main_hwnd, demo::DEMO_TIMER_ID).ok();
+                }
+                println!("Demo finished");
+            }
+        }
+    }
+
+    // Leaving dry-run moves every window to where the layout has it by then
+    fn toggle_dry_run(&mut self) {
+        self.dry_run = !self.dry_run;
+        if self.dry_run {
+            self.cancel_animations();
+            println!("Dry run on: logging window changes instead of making them");
+        } else {
+            println!("Dry run off: applying the layout");
+            // Windows adopted during the dry run were only logged; they get their styles
+            // taken and their places now, those in other ribbons and stacks included
+            let elsewhere = self.parked_ribbons.iter_mut()
+                .chain(self.monitor_ribbons.values_mut())
+                .flat_map(|ribbon| ribbon.windows.values_mut());
+            for window in elsewhere.filter(|w| !w.touched) {
+                Self::take_style(window);
+                window.touched = true;
+            }
+            let stacked: Vec<HWND> = self.stacks.values_mut()
+                .flatten()
+                .filter(|w| !w.touched)
+                .map(|window| {
+                    Self::take_style(window);
+                    window.touched = true;
+                    window.hwnd
+                })
+                .collect();
+            for hwnd in stacked {
+                self.park_stacked(hwnd);
+            }
+            for window in self.windows.values_mut() {
+                window.touched = true;
+            }
+            self.apply_all_windows(false);
+            self.reapply_transparency();
+        }
+    }
+    
+    // Un-maximize a tile and take its minimize and maximize buttons, which would
+    // otherwise pull it out of the ribbon
+    fn take_style(window: &ManagedWindow) {
+        if window.keep_style {
+            return;
+        }
+        unsafe {
+            if IsZoomed(window.hwnd).as_bool() {
+                ShowWindow(window.hwnd, SW_RESTORE);
+            }
+            let style = WINDOW_STYLE(window.original_style.0 & !WS_MINIMIZEBOX.0 & !WS_MAXIMIZEBOX.0 & !WS_MAXIMIZE.0);
+            SetWindowLongW(window.hwnd, GWL_STYLE, style.0 as i32);
+            SetWindowPos(window.hwnd, HWND_TOP, 0, 0, 0, 0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOACTIVATE).ok();
+        }
+    }
+
+    fn log_dry_run(hwnd: HWND, action: &str) {
+        let exe = window_info::process_name(hwnd).unwrap_or_default();
+        println!("[dry run] '{}' ({}) {}", window_info::window_title(hwnd), exe, action);
+    }
+
+    fn log_dry_run_rect(hwnd: HWND, rect: &RECT) {
+        Self::log_dry_run(hwnd, &format!("-> {},{} {}x{}",
+            rect.left, rect.top, rect.right - rect.left, rect.bottom - rect.top));
+    }
+
+    // Update animations
+    fn update_animations(&mut self) {
+        self.update_presentation();
+        let now = Instant::now();
+        let mut animations_complete = Vec::new();
+        let mut window_updates = Vec::new();
+        let mut need_reposition = false;
+
+        if let Some(pad) = self.scratchpad.as_mut() {
+            pad.step(self.curves.scroll);
+        }
+
+        // Update combined scroll animation
+        if let Some(scroll_anim) = &self.scroll_animation {
+            let elapsed = now.duration_since(scroll_anim.start_time);
+            
+            if elapsed >= scroll_anim.curve.duration {
+                self.ribbon_offset = scroll_anim.target_x;
+                self.ribbon_offset_target = scroll_anim.target_x;
+                self.vertical_offset = scroll_anim.target_y;
+                self.vertical_offset_target = scroll_anim.target_y;
+                self.scroll_animation = None;
+                self.apply_scroll_focus();
+                self.needs_ribbon_recalc = true;
+            } else {
+                let eased_t = scroll_anim.curve.progress(elapsed);
+                
+                self.ribbon_offset = Self::lerp(scroll_anim.start_x, scroll_anim.target_x, eased_t);
+                self.vertical_offset = Self::lerp(scroll_anim.start_y, scroll_anim.target_y, eased_t);
+            }
+            need_reposition = true;
+        }
+
+        // Collect ribbon repositions if needed
+        if need_reposition {
+            for window in self.windows.values() {
+                if window.animation.is_none() {
+                    window_updates.push((window.hwnd, self.tile_rect(window)));
+                }
             }
         }
 
@@ -337,7 +1672,7 @@ impl RibbonTiler {
             if let Some(anim) = &window.animation {
                 let elapsed = now.duration_since(anim.start_time);
                 
-                if elapsed >= anim.duration {
+                if elapsed >= anim.curve.duration {
                     match anim.animation_type {
                         AnimationType::Exit => {
                             animations_complete.push(*hwnd_val);
@@ -348,8 +1683,7 @@ impl RibbonTiler {
                         }
                     }
                 } else {
-                    let t = elapsed.as_secs_f32() / anim.duration.as_secs_f32();
-                    let eased_t = Self::ease_out_cubic(t);
+                    let eased_t = anim.curve.progress(elapsed);
                     
                     let current_rect = match anim.animation_type {
                         AnimationType::Move => {
@@ -402,38 +1736,16 @@ impl RibbonTiler {
             if let Some(window) = self.windows.get(&hwnd_val) {
                 if let Some(anim) = &window.animation {
                     if anim.animation_type == AnimationType::Exit {
-                        windows_to_remove.push((hwnd_val, window.clone(), anim.target_rect));
+                        windows_to_remove.push((hwnd_val, window.clone()));
                     }
                 }
             }
         }
         
         // Now remove and restore windows
-        for (hwnd_val, window_copy, target_rect) in windows_to_remove {
+        for (hwnd_val, window_copy) in windows_to_remove {
             self.windows.remove(&hwnd_val);
-            
-            unsafe {
-                SetWindowLongW(window_copy.hwnd, GWL_STYLE, window_copy.original_style.0 as i32);
-                SetWindowLongW(window_copy.hwnd, GWL_EXSTYLE, window_copy.original_ex_style.0 as i32);
-                
-                if (window_copy.original_ex_style & WS_EX_LAYERED).0 == 0 {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(window_copy.hwnd, GWL_EXSTYLE) as u32);
-                    SetWindowLongW(window_copy.hwnd, GWL_EXSTYLE, 
-                        (ex_style.0 & !WS_EX_LAYERED.0) as i32);
-                }
-                
-                SetWindowPos(
-                    window_copy.hwnd,
-                    HWND_TOP,
-                    target_rect.left,
-                    target_rect.top,
-                    target_rect.right - target_rect.left,
-                    target_rect.bottom - target_rect.top,
-                    SWP_NOZORDER | SWP_FRAMECHANGED,
-                ).ok();
-                
-                ShowWindow(window_copy.hwnd, SW_RESTORE);
-            }
+            self.restore_window(&window_copy);
             
             self.reflow_ribbon();
             self.needs_ribbon_recalc = true;
@@ -447,19 +1759,151 @@ impl RibbonTiler {
 
         // Check if all animations are complete
         let all_complete = self.scroll_animation.is_none() &&
-            self.windows.values().all(|w| w.animation.is_none());
+            self.windows.values().all(|w| w.animation.is_none()) &&
+            !self.scratchpad.as_ref().is_some_and(|pad| pad.sliding());
         
         if all_complete {
             *self.animation_running.lock().unwrap() = false;
             *self.animation_stop_requested.lock().unwrap() = true;
             
+            // Nothing polls for this any more, so catch up now that nothing is moving
             if self.needs_ribbon_recalc {
-                let now = Instant::now();
-                if now.duration_since(self.last_ribbon_recalc).as_millis() > 500 {
-                    self.recalculate_ribbon();
+                self.recalculate_ribbon();
+            }
+        }
+
+        self.update_mirror();
+    }
+
+    fn toggle_mirror(&mut self) {
+        if self.mirror.take().is_some() {
+            println!("Presentation mirror closed");
+            return;
+        }
+
+        self.mirror = Mirror::open();
+        if self.mirror.is_some() {
+            println!("Presentation mirror opened on second monitor");
+            self.update_mirror();
+        } else {
+            println!("Presentation mirror needs a second monitor");
+        }
+    }
+
+    // Throw a window onto the neighbouring display. A tile leaves the ribbon and lands
+    // floating at the equivalent spot.
+    fn send_to_monitor(&mut self, hwnd: HWND, direction: Direction) {
+        let managed_rect = self.windows.get(&hwnd.0).map(|w| self.tile_rect(w));
+
+        let (rect, source) = match managed_rect {
+            Some(rect) => (rect, monitor::containing(&rect)),
+            None => {
+                let mut rect = RECT::default();
+                unsafe {
+                    if GetWindowRect(hwnd, &mut rect).is_err() {
+                        return;
+                    }
                 }
+                (rect, monitor::containing(&rect))
+            }
+        };
+
+        let Some(source) = source else {
+            return;
+        };
+        let Some(target) = monitor::adjacent(&source, direction) else {
+            return;
+        };
+        let target_rect = monitor::scale_rect(&rect, &source.rcWork, &target.rcWork);
+
+        if managed_rect.is_some() {
+            self.move_to_monitor_ribbon(hwnd, monitor::handle_of(&target));
+            return;
+        }
+        if self.dry_run {
+            Self::log_dry_run_rect(hwnd, &target_rect);
+        } else {
+            unsafe {
+                SetWindowPos(
+                    hwnd,
+                    HWND::default(),
+                    target_rect.left,
+                    target_rect.top,
+                    target_rect.right - target_rect.left,
+                    target_rect.bottom - target_rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                ).ok();
             }
         }
+
+        if self.throw_focus_follows {
+            focus::focus_window(hwnd);
+        } else {
+            self.focus_visible_window();
+        }
+    }
+
+    // A tile sent to another display joins that display's ribbon, in its current row at
+    // the spot on screen it left. Ribbon positions are worked out on the primary's
+    // geometry, so the slot scales to the other display by itself. The window keeps
+    // its original style and layering for when it is released.
+    fn move_to_monitor_ribbon(&mut self, hwnd: HWND, target: isize) {
+        let source = self.monitor;
+        if target == source {
+            return;
+        }
+        self.settle_scroll();
+        
+        // The next window of its stack keeps the slot, as when a tile is removed
+        if let Some(next) = self.promote_stack(hwnd.0) {
+            self.apply_window_position(HWND(next), false);
+        }
+        self.detach_from_row(hwnd.0);
+        let Some(mut window) = self.windows.remove(&hwnd.0) else {
+            return;
+        };
+        self.hung.remove(&hwnd.0);
+        self.fullscreen.remove(&hwnd.0);
+        let on_screen_x = window.position.x - self.ribbon_offset;
+        self.layout_rows();
+        self.apply_all_windows(false);
+        
+        self.activate_monitor(target);
+        let row = self.current_row;
+        let x = (self.ribbon_offset + on_screen_x).max(0);
+        window.animation = None;
+        window.position.monitor = target;
+        window.position.row = row;
+        window.position.x = x;
+        self.windows.insert(hwnd.0, window);
+        self.insert_into_row(hwnd.0, row, x);
+        self.layout_rows();
+        self.apply_all_windows(true);
+        println!("Moved {:?} to the ribbon of another display", hwnd);
+        
+        if self.throw_focus_follows {
+            focus::focus_window(hwnd);
+        } else {
+            self.activate_monitor(source);
+            self.focus_visible_window();
+        }
+    }
+
+    // Keep the mirror's thumbnails in step with the tiles on the primary display
+    fn update_mirror(&mut self) {
+        let Some(mirror) = self.mirror.as_mut() else {
+            return;
+        };
+        // The primary's active viewport is parked with the other displays while one of those is active
+        let active = match self.monitor_ribbons.get(&monitor::primary_handle()) {
+            Some(primary) if self.displays.contains_key(&self.monitor) => &primary.windows,
+            _ => &self.windows,
+        };
+        let tiles: Vec<HWND> = active.values()
+            .chain(self.parked_ribbons.iter().flat_map(|parked| parked.windows.values()))
+            .map(|w| w.hwnd)
+            .collect();
+        mirror.update(&tiles, self.monitor_width, self.monitor_height);
     }
 
     // Batch window position updates for better performance
@@ -467,20 +1911,32 @@ impl RibbonTiler {
         if updates.is_empty() {
             return;
         }
+        if self.dry_run {
+            for (hwnd, rect) in updates {
+                Self::log_dry_run_rect(*hwnd, rect);
+            }
+            return;
+        }
 
         unsafe {
+            // Every display, so tiles on a secondary one aren't skipped
+            let desktop_left = GetSystemMetrics(SM_XVIRTUALSCREEN).min(0);
+            let desktop_top = GetSystemMetrics(SM_YVIRTUALSCREEN).min(0);
+            let desktop_right = (desktop_left + GetSystemMetrics(SM_CXVIRTUALSCREEN)).max(self.monitor_width);
+            let desktop_bottom = (desktop_top + GetSystemMetrics(SM_CYVIRTUALSCREEN)).max(self.monitor_height);
+            
             match BeginDeferWindowPos(updates.len() as i32) {
                 Ok(hdwp) => {
                     let mut hdwp_current = hdwp;
                     
-                    for (hwnd, rect) in updates {
+                    for (hwnd, rect) in updates.iter().filter(|(hwnd, _)| !self.is_frozen(hwnd.0)) {
                         let width = rect.right - rect.left;
                         let height = rect.bottom - rect.top;
                         
                         // Check if window is visible on screen
                         if width > 0 && height > 0 && 
-                           rect.left < self.monitor_width * 2 && rect.right > -self.monitor_width &&
-                           rect.top < self.monitor_height * 2 && rect.bottom > -self.monitor_height {
+                           rect.left < desktop_right + self.monitor_width && rect.right > desktop_left - self.monitor_width &&
+                           rect.top < desktop_bottom + self.monitor_height && rect.bottom > desktop_top - self.monitor_height {
                             match DeferWindowPos(
                                 hdwp_current,
                                 *hwnd,
@@ -556,7 +2012,14 @@ impl RibbonTiler {
     }
 
     // Start animation timer if not already running
-    fn start_animation_timer(&self) {
+    fn start_animation_timer(&mut self) {
+        // Instant mode and reduced motion land every animation the moment it starts; so
+        // does dry-run, which has nothing to animate
+        if !self.animates() || self.dry_run {
+            self.cancel_animations();
+            return;
+        }
+        
         let mut running = self.animation_running.lock().unwrap();
         if !*running {
             *running = true;
@@ -565,13 +2028,16 @@ impl RibbonTiler {
             let animation_running = self.animation_running.clone();
             let animation_stop_requested = self.animation_stop_requested.clone();
             let main_hwnd = self.main_hwnd;
+            let animation_fps = self.animation_fps.clone();
+            let frame_pacing = self.frame_pacing;
             
             thread::spawn(move || {
-                let start_time = Instant::now();
-                let mut last_recalc_check = Instant::now();
+                let mut next_tick = Instant::now();
+                let mut last_tick = Instant::now();
+                priority::hold_awake();
                 
                 loop {
-                    if *animation_stop_requested.lock().unwrap() {
+                    if *animation_stop_requested.lock().unwrap() || IDLE_SUSPENDED.load(Ordering::Relaxed) {
                         break;
                     }
                     
@@ -583,47 +2049,68 @@ impl RibbonTiler {
                                 WPARAM(0),
                                 LPARAM(0)
                             ).ok();
-                            
-                            let now = Instant::now();
-                            if now.duration_since(last_recalc_check).as_millis() > 500 {
-                                PostMessageW(
-                                    main_hwnd,
-                                    WM_TILER_RECALC,
-                                    WPARAM(0),
-                                    LPARAM(0)
-                                ).ok();
-                                last_recalc_check = now;
-                            }
                         }
                         
-                        let elapsed = Instant::now().duration_since(start_time);
-                        let interval = if elapsed.as_millis() < 200 {
-                            16  // 60 FPS
-                        } else {
-                            let extra = ((elapsed.as_millis() - 200) / 100).min(4);
-                            16 + extra as u64
-                        };
-                        
-                        thread::sleep(Duration::from_millis(interval));
+                        // Picks up a changed FPS mid-animation
+                        let fps = animation_fps.load(Ordering::Relaxed).max(1);
+                        let frame = Duration::from_secs_f64(1.0 / fps as f64);
+                        let now = Instant::now();
+                        stats::record_frame(now.duration_since(last_tick), frame);
+                        last_tick = now;
+                        match frame_pacing {
+                            FramePacing::Fixed => thread::sleep(frame),
+                            FramePacing::Compensated => {
+                                next_tick += frame;
+                                let now = Instant::now();
+                                if next_tick <= now {
+                                    // Fell behind; drop the missed frames rather than bursting to catch up
+                                    next_tick = now + frame;
+                                }
+                                thread::sleep(next_tick - now);
+                            }
+                        }
                     } else {
                         break;
                     }
                 }
                 
+                priority::release_awake();
                 *animation_running.lock().unwrap() = false;
                 *animation_stop_requested.lock().unwrap() = false;
             });
         }
     }
 
+    // Where a tile's window goes: its slot, or for a no-resize window its own size centred there
+    fn tile_rect(&self, window: &ManagedWindow) -> RECT {
+        let slot = self.ribbon_to_screen(&window.position);
+        let Some((width, height)) = window.own_size else {
+            return slot;
+        };
+        let left = slot.left + (slot.right - slot.left - width) / 2;
+        let top = slot.top + (slot.bottom - slot.top - height) / 2;
+        RECT { left, top, right: left + width, bottom: top + height }
+    }
+
+    // Where a tile's slot is on its own display
     fn ribbon_to_screen(&self, pos: &RibbonPosition) -> RECT {
-        let base_x = pos.x - self.ribbon_offset;
+        let rect = self.ribbon_rect(pos);
+        match self.displays.get(&pos.monitor) {
+            Some(work_area) => {
+                let primary = RECT { left: 0, top: 0, right: self.monitor_width, bottom: self.monitor_height };
+                monitor::map_rect(&rect, &primary, work_area)
+            }
+            None => rect,
+        }
+    }
+
+    // A tile's rect as if every tile were on the primary display, for comparing against the viewport
+    fn ribbon_rect(&self, pos: &RibbonPosition) -> RECT {
+        let base_x = self.to_pixels(pos.x - self.ribbon_offset);
         let base_y = pos.row * self.row_height - self.vertical_offset;
         
-        let w = match pos.size {
-            TileSize::Full => self.monitor_width,
-            TileSize::Half => self.monitor_width / 2,
-        };
+        let w = self.to_pixels(self.get_tile_width(pos));
+        let base_x = self.viewport.screen_x(base_x, w, self.monitor_width);
 
         RECT {
             left: base_x + self.margin_horizontal / 2,
@@ -634,16 +2121,33 @@ impl RibbonTiler {
     }
 
     fn should_manage_window(&self, hwnd: HWND) -> bool {
+        if self.scratchpad.as_ref().is_some_and(|pad| pad.hwnd == hwnd) {
+            return false;
+        }
         unsafe {
             if !IsWindowVisible(hwnd).as_bool() {
                 return false;
             }
 
+            // Fast user switching / RDP: never touch another session's windows
+            if session::window_session_id(hwnd).is_some_and(|id| id != self.session_id) {
+                return false;
+            }
+
+            if !self.desktops.is_current(hwnd) {
+                return false;
+            }
+
             let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
             if (style & WS_MINIMIZE).0 != 0 {
                 return false;
             }
 
+            // Opted in by the user: skip the heuristics that reject custom-chrome apps
+            if self.is_force_managed(hwnd) {
+                return true;
+            }
+
             let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
             if (ex_style & WS_EX_TOOLWINDOW).0 != 0 {
                 return false;
@@ -676,7 +2180,13 @@ impl RibbonTiler {
                 return false;
             }
 
-            if len == 0 && !class_str.contains("Chrome") && !class_str.contains("Firefox") {
+            // Browsers and Electron apps often set their title late, so judge their frames by
+            // process and shape; anything else untitled is a helper window
+            if window_info::is_browser_frame_class(&class_str) {
+                if !window_info::is_browser_frame(hwnd, &class_str) {
+                    return false;
+                }
+            } else if len == 0 {
                 return false;
             }
             
@@ -692,17 +2202,35 @@ impl RibbonTiler {
                 return true;
             }
 
-            println!("Window added to ribbon (row {})", self.current_row);
-            
             true
         }
     }
 
-    fn is_popup_window(&self, hwnd: HWND) -> bool {
-        unsafe {
-            let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
-            let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-            
+    // Window class or exe named by --force-manage
+    fn is_force_managed(&self, hwnd: HWND) -> bool {
+        if self.force_manage.is_empty() {
+            return false;
+        }
+        
+        let mut class_name = [0u16; 256];
+        let class_len = unsafe { GetClassNameW(hwnd, &mut class_name) };
+        let class_str = String::from_utf16_lossy(&class_name[..class_len.max(0) as usize]);
+        let exe = window_info::process_name(hwnd);
+        
+        self.force_manage.iter().any(|name| {
+            *name == class_str || exe.as_deref().is_some_and(|exe| exe.eq_ignore_ascii_case(name))
+        })
+    }
+
+    fn is_popup_window(&self, hwnd: HWND) -> bool {
+        if self.is_force_managed(hwnd) {
+            return false;
+        }
+        
+        unsafe {
+            let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
+            let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
+            
             let is_popup_no_caption = (style & WS_POPUP).0 != 0 && (style & WS_CAPTION).0 == 0;
             let is_dialog = (ex_style & WS_EX_DLGMODALFRAME).0 != 0;
             
@@ -720,20 +2248,22 @@ impl RibbonTiler {
 
     fn track_floating_window(&mut self, hwnd: HWND) {
         if !self.floating_windows.contains_key(&hwnd.0) {
-            self.floating_windows.insert(hwnd.0, hwnd);
+            self.floating_windows.insert(hwnd.0, FloatingWindow {
+                hwnd,
+                original_layering: layered::capture(hwnd),
+            });
             
-            if self.transparency < 255 {
-                unsafe {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-                    SetWindowLongW(hwnd, GWL_EXSTYLE, 
-                        (ex_style.0 | WS_EX_LAYERED.0) as i32);
-                    SetLayeredWindowAttributes(hwnd, COLORREF(0), self.transparency, LWA_ALPHA).ok();
-                }
+            if self.window_alpha(hwnd) < 255 {
+                self.apply_transparency(hwnd);
             }
         }
     }
 
     fn clean_minimized_windows(&mut self) {
+        // Show desktop may be under way; settle_minimized decides
+        if self.desktop_shown || !self.minimizing.is_empty() {
+            return;
+        }
         let mut minimized = Vec::new();
         
         unsafe {
@@ -754,6 +2284,122 @@ impl RibbonTiler {
         }
     }
 
+    // A window closed, hid or minimized. Tiles free their slot and the ribbon closes the
+    // gap straight away, rather than when something next looks.
+    fn on_window_gone(&mut self, hwnd: HWND, event: u32) {
+        if event == EVENT_OBJECT_DESTROY {
+            self.delayed_adoptions.remove(&hwnd.0);
+            self.declined.remove(&hwnd.0);
+            self.preexisting.remove(&hwnd.0);
+            self.alpha_overrides.remove(&hwnd.0);
+            self.labels.remove(&hwnd.0);
+            self.rule_floated.remove(&hwnd.0);
+            self.floated.remove(&hwnd.0);
+            self.focus_history.retain(|&h| h != hwnd.0);
+            if self.scratchpad.as_ref().is_some_and(|pad| pad.hwnd == hwnd) {
+                self.scratchpad = None;
+            }
+            for members in self.stacks.values_mut() {
+                members.retain(|w| w.hwnd != hwnd);
+            }
+            self.stacks.retain(|_, members| !members.is_empty());
+        }
+        // Apps hide and re-show their window briefly when its frame changes
+        if event == EVENT_OBJECT_HIDE && unsafe { IsWindowVisible(hwnd).as_bool() } {
+            return;
+        }
+        if event != EVENT_SYSTEM_MINIMIZESTART {
+            let parked = self.parked_ribbons.iter_mut()
+                .chain(self.monitor_ribbons.values_mut())
+                .find_map(|ribbon| ribbon.remove(hwnd.0));
+            let stacked = self.stacks.values_mut().find_map(|members| {
+                let index = members.iter().position(|w| w.hwnd == hwnd)?;
+                Some(members.remove(index))
+            });
+            self.stacks.retain(|_, members| !members.is_empty());
+            if let Some(window) = parked.or(stacked) {
+                self.release_hidden(window);
+                return;
+            }
+        }
+        if !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        if event == EVENT_SYSTEM_MINIMIZESTART {
+            if !self.desktop_shown {
+                self.minimizing.push(hwnd.0);
+                unsafe {
+                    SetTimer(self.main_hwnd, SHOW_DESKTOP_TIMER_ID, SHOW_DESKTOP_SETTLE_MS, None);
+                }
+            }
+            return;
+        }
+        
+        if let Some(window) = self.windows.remove(&hwnd.0) {
+            self.release_hidden(window);
+        }
+        self.needs_ribbon_recalc = true;
+        if !*self.animation_running.lock().unwrap() {
+            self.recalculate_ribbon();
+        }
+    }
+
+    // A window that hid rather than closed may be shown again, and should come back as
+    // the app left it rather than stripped and sized to its old slot. It stays hidden.
+    fn release_hidden(&self, mut window: ManagedWindow) {
+        if unsafe { !IsWindow(window.hwnd).as_bool() } {
+            return;
+        }
+        window.original_placement.showCmd = SW_HIDE.0 as u32;
+        self.restore_window(&window);
+    }
+
+    // SHOW_DESKTOP_TIMER_ID: several tiles minimized together, or the desktop taking focus,
+    // is show desktop (Win+D), and the tiles stay in their slots until it ends. A tile
+    // minimized on its own leaves the ribbon.
+    fn settle_minimized(&mut self) {
+        unsafe {
+            KillTimer(self.main_hwnd, SHOW_DESKTOP_TIMER_ID).ok();
+        }
+        let minimized = std::mem::take(&mut self.minimizing);
+        let desktop = matches!(window_info::class_name(unsafe { GetForegroundWindow() }).as_str(), "WorkerW" | "Progman");
+        if minimized.len() > 1 || desktop {
+            self.desktop_shown = true;
+            println!("Desktop shown; tiles keep their slots");
+            return;
+        }
+        
+        for hwnd in minimized {
+            if self.windows.remove(&hwnd).is_some() {
+                self.needs_ribbon_recalc = true;
+            }
+        }
+        if self.needs_ribbon_recalc && !*self.animation_running.lock().unwrap() {
+            self.recalculate_ribbon();
+        }
+    }
+    
+    // A tile came back from show desktop, by Win+D again or from the taskbar: bring the
+    // rest back too, into the slots they never left
+    fn end_show_desktop(&mut self, hwnd: HWND) {
+        if !self.desktop_shown || !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        self.desktop_shown = false;
+        
+        let minimized: Vec<HWND> = self.windows.values()
+            .map(|w| w.hwnd)
+            .filter(|&hwnd| unsafe { IsIconic(hwnd).as_bool() })
+            .collect();
+        for hwnd in minimized {
+            unsafe {
+                ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+        self.apply_all_windows(false);
+        println!("Desktop hidden; tiles restored");
+    }
+
     // Clean up windows that were closed externally
     fn clean_closed_windows(&mut self) {
         let mut closed_windows = Vec::new();
@@ -769,10 +2415,75 @@ impl RibbonTiler {
         
         if !closed_windows.is_empty() {
             for hwnd_val in &closed_windows {
-                self.windows.remove(hwnd_val);
+                if let Some(window) = self.windows.remove(hwnd_val) {
+                    self.release_hidden(window);
+                }
             }
             self.needs_ribbon_recalc = true;
         }
+        
+        self.delayed_adoptions.retain(|hwnd, _| unsafe { IsWindow(HWND(*hwnd)).as_bool() });
+        self.update_labels();
+    }
+
+    // Labels for tiles that came in without adoption (handoff, stacking), and none for
+    // windows no longer managed
+    fn update_labels(&mut self) {
+        let stacked = self.stacks.values().flatten().map(|w| w.hwnd.0);
+        let parked = self.parked_ribbons.iter().chain(self.monitor_ribbons.values()).flat_map(|parked| parked.windows.keys().copied());
+        let managed: HashSet<isize> = self.windows.keys().copied().chain(stacked).chain(parked).collect();
+        self.labels.retain(|hwnd, _| managed.contains(hwnd));
+        for hwnd in managed {
+            self.labels.entry(hwnd).or_insert_with(|| labels::Label::of(HWND(hwnd)));
+        }
+    }
+
+    // WM_TILER_RENAMED: a window's title changed. Rules matching on the title are
+    // looked at again, so a tile can float or leave once it turns into, say, a call,
+    // and a tile floated that way comes back when it stops matching.
+    fn on_title_changed(&mut self, hwnd: HWND) {
+        if let Some(label) = self.labels.get_mut(&hwnd.0) {
+            label.title = window_info::window_title(hwnd);
+        }
+        if !self.rules.iter().any(rules::Rule::has_title) {
+            return;
+        }
+        let tiled = self.windows.contains_key(&hwnd.0);
+        if !tiled && !self.rule_floated.contains(&hwnd.0) {
+            return;
+        }
+        
+        let rule = rules::lookup(&self.rules, hwnd).cloned();
+        let alpha = rule.as_ref().and_then(|rule| rule.transparency);
+        if self.alpha_overrides.get(&hwnd.0).copied() != alpha {
+            match alpha {
+                Some(alpha) => self.alpha_overrides.insert(hwnd.0, alpha),
+                None => self.alpha_overrides.remove(&hwnd.0),
+            };
+            self.apply_transparency(hwnd);
+        }
+        
+        match self.quirk_of(hwnd) {
+            Some(Quirk::Ignore) if tiled => {
+                println!("'{}' matches a rule now; releasing it", window_info::window_title(hwnd));
+                self.remove_window(hwnd);
+            }
+            Some(Quirk::Float) if tiled => {
+                println!("'{}' matches a float rule now", window_info::window_title(hwnd));
+                self.remove_window(hwnd);
+                self.track_floating_window(hwnd);
+                self.rule_floated.insert(hwnd.0);
+            }
+            Some(Quirk::Float) => {}
+            _ if !tiled => {
+                self.rule_floated.remove(&hwnd.0);
+                if let Some(floating) = self.floating_windows.remove(&hwnd.0) {
+                    layered::restore(hwnd, floating.original_layering);
+                }
+                self.adopt_window(hwnd);
+            }
+            _ => {}
+        }
     }
 
     // Get all rows that have windows
@@ -785,14 +2496,50 @@ impl RibbonTiler {
         rows
     }
 
-    // Check if any row has a window at the given x position
-    fn is_x_position_occupied(&self, x: i32, width: i32) -> bool {
-        self.windows.values().any(|w| {
-            let window_start = w.position.x;
-            let window_end = w.position.x + self.get_tile_width(&w.position.size);
-            // Check if ranges overlap
-            !(x + width <= window_start || x >= window_end)
-        })
+    // Recompute every tile's x from its row order - the only place x is assigned
+    fn layout_rows(&mut self) {
+        // A stack whose front window went away brings its next window forward
+        let fronts: Vec<isize> = self.rows.values().flatten()
+            .filter(|hwnd| !self.windows.contains_key(hwnd) && self.stacks.contains_key(hwnd))
+            .copied()
+            .collect();
+        for front in fronts {
+            self.promote_stack(front);
+        }
+        
+        let windows = &mut self.windows;
+        
+        for (&row, order) in self.rows.iter_mut() {
+            order.retain(|hwnd| windows.contains_key(hwnd));
+            
+            let mut x = 0;
+            for hwnd in order.iter() {
+                if let Some(window) = windows.get_mut(hwnd) {
+                    window.position.row = row;
+                    window.position.x = x;
+                    x += window.position.units();
+                }
+            }
+        }
+        
+        self.rows.retain(|_, order| !order.is_empty());
+    }
+
+    // Take a tile out of its row's order (the tile itself stays managed)
+    fn detach_from_row(&mut self, hwnd: isize) {
+        for order in self.rows.values_mut() {
+            order.retain(|h| *h != hwnd);
+        }
+    }
+
+    // Slot a tile into a row before every tile that starts at or after x
+    fn insert_into_row(&mut self, hwnd: isize, row: i32, x: i32) {
+        let windows = &self.windows;
+        let order = self.rows.entry(row).or_default();
+        let index = order.iter()
+            .take_while(|h| windows.get(h).is_some_and(|w| w.position.x < x))
+            .count();
+        order.insert(index, hwnd);
     }
 
     // Recalculate entire ribbon layout
@@ -800,6 +2547,7 @@ impl RibbonTiler {
         self.clean_closed_windows();
         
         if self.windows.is_empty() {
+            self.rows.clear();
             self.ribbon_offset = 0;
             self.ribbon_offset_target = 0;
             self.vertical_offset = 0;
@@ -807,73 +2555,28 @@ impl RibbonTiler {
             return;
         }
         
-        // Get all non-exiting windows grouped by row
-        let mut rows: HashMap<i32, Vec<(isize, RibbonPosition)>> = HashMap::new();
-        
-        for (hwnd, w) in self.windows.iter() {
-            if w.animation.as_ref().map_or(true, |a| a.animation_type != AnimationType::Exit) {
-                rows.entry(w.position.row)
-                    .or_insert_with(Vec::new)
-                    .push((*hwnd, w.position));
-            }
-        }
-        
-        // Sort each row by x position
-        for windows in rows.values_mut() {
-            windows.sort_by_key(|(_, pos)| pos.x);
-        }
-        
-        // Find all unique x positions across all rows
-        let mut x_positions: Vec<(i32, i32)> = Vec::new(); // (x, width)
-        
-        for windows in rows.values() {
-            for (_, pos) in windows {
-                x_positions.push((pos.x, self.get_tile_width(&pos.size)));
-            }
-        }
-        
-        // Sort and deduplicate x positions
-        x_positions.sort_by_key(|(x, _)| *x);
-        
-        // Build a new layout with no gaps
-        let mut new_x_mapping: HashMap<i32, i32> = HashMap::new();
-        let mut current_x = 0;
-        
-        for (old_x, width) in x_positions {
-            if !new_x_mapping.contains_key(&old_x) {
-                new_x_mapping.insert(old_x, current_x);
-                // Only advance if this x position is actually used
-                if self.is_x_position_occupied(old_x, width) {
-                    current_x += width;
-                }
-            }
-        }
+        self.layout_rows();
         
         // Update all window positions
         let mut positions_to_update = Vec::new();
         
-        for (_hwnd, window) in self.windows.iter_mut() {
-            if let Some(&new_x) = new_x_mapping.get(&window.position.x) {
-                window.position.x = new_x;
-                
-                if window.animation.is_none() && window.position.row == self.current_row {
-                    positions_to_update.push((window.hwnd, window.position));
-                }
+        for window in self.windows.values() {
+            if window.animation.is_none() && window.position.row == self.current_row && !self.is_frozen(window.hwnd.0) && !self.dry_run {
+                positions_to_update.push((window.hwnd, self.tile_rect(window)));
             }
         }
         
         // Apply position updates
-        for (hwnd, position) in positions_to_update {
-            let rect = self.ribbon_to_screen(&position);
+        for (hwnd, rect) in positions_to_update {
             Self::set_window_rect(hwnd, &rect);
         }
         
         // Ensure ribbon offset is within bounds
         let max_x = self.windows.values()
-            .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
             .max()
             .unwrap_or(0);
-        let max_offset = (max_x - self.monitor_width).max(0);
+        let max_offset = (max_x - RIBBON_SPAN).max(0);
         
         if self.ribbon_offset > max_offset {
             self.ribbon_offset = max_offset;
@@ -882,7 +2585,6 @@ impl RibbonTiler {
         }
         
         self.needs_ribbon_recalc = false;
-        self.last_ribbon_recalc = Instant::now();
     }
 
     fn reflow_ribbon(&mut self) {
@@ -890,91 +2592,191 @@ impl RibbonTiler {
     }
 
     fn add_window(&mut self, hwnd: HWND) -> bool {
+        if self.windows.contains_key(&hwnd.0) {
+            return false;
+        }
+        
+        // Second pass once the window has settled. Asking again sooner, by hand or as it
+        // takes focus, leaves it waiting.
+        if let Some(&earliest) = self.delayed_adoptions.get(&hwnd.0) {
+            if Instant::now() < earliest {
+                return false;
+            }
+            self.delayed_adoptions.remove(&hwnd.0);
+            return self.adopt_window(hwnd);
+        }
+        let quirk = self.quirk_of(hwnd);
+        if quirk == Some(Quirk::Ignore) {
+            return false;
+        }
+        
+        let settle = match self.adopt_settle {
+            Some(settle) => Some(settle),
+            None => (quirk == Some(Quirk::DelayAdopt)).then_some(DEFAULT_ADOPT_SETTLE),
+        };
+        let Some(settle) = settle else {
+            return self.adopt_window(hwnd);
+        };
+        
+        // Tiling now would fight the app's own startup move; try again once it stops
+        self.delayed_adoptions.insert(hwnd.0, Instant::now() + settle);
+        let hwnd_value = hwnd.0;
+        thread::spawn(move || {
+            if window_info::wait_until_settled(HWND(hwnd_value), settle, ADOPT_SETTLE_TIMEOUT) {
+                post_tiler_command(TilerCommand::AddWindow, HWND(hwnd_value));
+            }
+        });
+        false
+    }
+
+    // How a window is handled: a config rule's quirk comes first, then --quirk, then the
+    // built-in list
+    fn quirk_of(&self, hwnd: HWND) -> Option<Quirk> {
+        match rules::lookup(&self.rules, hwnd).and_then(|rule| rule.quirk) {
+            Some(quirk) => quirk,
+            None => compat::lookup(hwnd, &self.quirks, &self.opaque_apps),
+        }
+    }
+
+    // Tile a window straight away, skipping any adoption delay
+    fn adopt_window(&mut self, hwnd: HWND) -> bool {
         self.check_monitor_dimensions();
         
-        if self.windows.contains_key(&hwnd.0) {
+        if self.windows.contains_key(&hwnd.0) || self.parked_viewport_of(hwnd).is_some()
+            || self.monitor_ribbons.values().any(|ribbon| ribbon.windows.contains_key(&hwnd.0))
+            || self.stack_of(hwnd.0).is_some() {
+            return false;
+        }
+        
+        // A window joins the ribbon of the display it opened on, unless it prefers another
+        // that is attached
+        let rule = rules::lookup(&self.rules, hwnd).cloned();
+        let display = self.preferred_display(hwnd, rule.as_ref().and_then(|rule| rule.row));
+        self.activate_monitor(display.unwrap_or_else(|| monitor::of_window(hwnd)));
+        self.settle_scroll();
+        
+        let quirk = self.quirk_of(hwnd);
+        if quirk == Some(Quirk::Ignore) {
             return false;
         }
         
         if !self.should_manage_window(hwnd) {
             return false;
         }
+        
+        match rule.as_ref().and_then(|rule| rule.transparency) {
+            Some(alpha) => self.alpha_overrides.insert(hwnd.0, alpha),
+            None => self.alpha_overrides.remove(&hwnd.0),
+        };
 
-        if self.is_popup_window(hwnd) {
+        if quirk == Some(Quirk::Float) || self.is_popup_window(hwnd) {
             self.track_floating_window(hwnd);
-            unsafe {
-                SetForegroundWindow(hwnd);
-            }
+            focus::focus_window(hwnd);
             return true;
         }
+        
+        // Spilling to the next row is handled when the position is picked. A rule's row
+        // takes the window regardless of the caps.
+        let rule_row = rule.as_ref().and_then(|rule| rule.row);
+        if rule_row.is_none() && self.row_is_full(self.current_row) {
+            match self.limit_policy {
+                LimitPolicy::Spill => {}
+                LimitPolicy::Refuse => {
+                    osd::show("Row is full", 1.0);
+                    return false;
+                }
+                LimitPolicy::ReplaceOldest => {
+                    let oldest = self.rows.get(&self.current_row).into_iter().flatten()
+                        .filter_map(|hwnd| self.windows.get(hwnd))
+                        .min_by_key(|w| w.adopted)
+                        .map(|w| w.hwnd);
+                    if let Some(oldest) = oldest {
+                        self.remove_window(oldest);
+                    }
+                }
+            }
+        }
 
         unsafe {
             let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
             let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
             
-            let mut rect = RECT::default();
-            GetWindowRect(hwnd, &mut rect).ok();
-            
-            let screen_width = GetSystemMetrics(SM_CXSCREEN);
-            let screen_height = GetSystemMetrics(SM_CYSCREEN);
-            let width = rect.right - rect.left;
-            let height = rect.bottom - rect.top;
-            
-            if rect.left < -width + 100 || rect.left > screen_width - 100 ||
-               rect.top < -height + 100 || rect.top > screen_height - 100 {
-                rect.left = (screen_width - width) / 2;
-                rect.top = (screen_height - height) / 2;
-                rect.right = rect.left + width;
-                rect.bottom = rect.top + height;
-            }
+            let mut placement = WINDOWPLACEMENT {
+                length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                ..Default::default()
+            };
+            GetWindowPlacement(hwnd, &mut placement).ok();
+            let original_layering = layered::capture(hwnd);
             
-            if IsZoomed(hwnd).as_bool() {
+            if self.dry_run {
+                Self::log_dry_run(hwnd, "would be adopted");
+            } else if IsZoomed(hwnd).as_bool() {
                 ShowWindow(hwnd, SW_RESTORE);
             }
             
-            let new_style = WINDOW_STYLE(style.0 & !WS_MINIMIZEBOX.0 & !WS_MAXIMIZEBOX.0 & !WS_MAXIMIZE.0);
-            SetWindowLongW(hwnd, GWL_STYLE, new_style.0 as i32);
-            
-            SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0, 
-                SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOACTIVATE);
+            let keep_style = quirk == Some(Quirk::RestoreStyle);
+            let own_size = (quirk == Some(Quirk::NoResize)).then(|| {
+                let normal = placement.rcNormalPosition;
+                (normal.right - normal.left, normal.bottom - normal.top)
+            });
+            if !keep_style && !self.dry_run {
+                let new_style = WINDOW_STYLE(style.0 & !WS_MINIMIZEBOX.0 & !WS_MAXIMIZEBOX.0 & !WS_MAXIMIZE.0);
+                SetWindowLongW(hwnd, GWL_STYLE, new_style.0 as i32);
+                
+                SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0, 
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOACTIVATE);
+            }
             
-            let position = self.find_viewport_position();
+            let mut position = self.find_viewport_position();
+            position.monitor = self.monitor;
+            if let Some(row) = rule_row {
+                position.row = row;
+                position.x = self.row_end(row);
+            }
+            if let Some(size) = rule.as_ref().and_then(|rule| rule.size) {
+                position.set_size(size);
+            }
+            let changed_row = position.row != self.current_row;
+            self.current_row = position.row;
             
             let window = ManagedWindow {
                 hwnd,
                 original_style: style,
                 original_ex_style: ex_style,
-                original_rect: rect,
+                original_placement: placement,
+                keep_style,
+                own_size,
+                original_layering,
                 position,
                 animation: None,
+                adopted: Instant::now(),
+                touched: !self.dry_run,
             };
 
             self.windows.insert(hwnd.0, window);
+            self.labels.insert(hwnd.0, labels::Label::of(hwnd));
+            self.stats.record_window();
+            if !self.dry_run {
+                let forced = if self.is_force_managed(hwnd) { ", forced" } else { "" };
+                println!("Window added to ribbon (row {}{})", position.row, forced);
+            }
             
-            let new_window_width = self.get_tile_width(&position.size);
-            let insertion_x = position.x;
+            // Slot into the row at the chosen edge; layout shifts the rest along
+            self.insert_into_row(hwnd.0, position.row, position.x);
+            self.layout_rows();
             
-            // Shift windows on the same row
-            let windows_to_shift: Vec<isize> = self.windows.iter()
-                .filter(|(h, w)| **h != hwnd.0 && w.position.row == position.row && w.position.x >= insertion_x)
-                .map(|(h, _)| *h)
-                .collect();
-                
-            for hwnd_to_shift in windows_to_shift {
-                if let Some(w) = self.windows.get_mut(&hwnd_to_shift) {
-                    w.position.x += new_window_width;
-                }
-            }
+            let new_window_width = self.get_tile_width(&position);
+            let insertion_x = self.windows.get(&hwnd.0).map_or(0, |w| w.position.x);
             
             let window_end = insertion_x + new_window_width;
-            if insertion_x < self.ribbon_offset || window_end > self.ribbon_offset + self.monitor_width {
-                let center_offset = insertion_x + new_window_width / 2 - self.monitor_width / 2;
+            if changed_row || insertion_x < self.ribbon_offset || window_end > self.ribbon_offset + RIBBON_SPAN {
+                let center_offset = insertion_x + new_window_width / 2 - RIBBON_SPAN / 2;
                 
                 let max_x = self.windows.values()
-                    .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+                    .map(|w| w.position.x + self.get_tile_width(&w.position))
                     .max()
                     .unwrap_or(0);
-                let max_offset = (max_x - self.monitor_width).max(0);
+                let max_offset = (max_x - RIBBON_SPAN).max(0);
                 
                 self.ribbon_offset = center_offset.clamp(0, max_offset);
                 self.ribbon_offset_target = self.ribbon_offset;
@@ -1000,10 +2802,22 @@ impl RibbonTiler {
     }
 
     fn find_viewport_position(&self) -> RibbonPosition {
+        // A full current row sends the window to the end of the next row with room
+        let row = self.row_with_room();
+        if row != self.current_row {
+            return RibbonPosition {
+                x: self.row_end(row),
+                row,
+                size: TileSize::Half,
+                width: None,
+                monitor: 0,
+            };
+        }
+        
         let focused_hwnd = unsafe { GetForegroundWindow() };
         let focused_center = self.windows.get(&focused_hwnd.0)
-            .map(|w| w.position.x + self.get_tile_width(&w.position.size) / 2)
-            .unwrap_or_else(|| self.ribbon_offset + self.monitor_width / 2);
+            .map(|w| w.position.x + self.get_tile_width(&w.position) / 2)
+            .unwrap_or_else(|| self.ribbon_offset + RIBBON_SPAN / 2);
         
         let mut best_position = self.ribbon_offset;
         let mut best_distance = i32::MAX;
@@ -1011,7 +2825,7 @@ impl RibbonTiler {
         // Only check windows on the current row
         for window in self.windows.values().filter(|w| w.position.row == self.current_row) {
             let left_edge = window.position.x;
-            let right_edge = window.position.x + self.get_tile_width(&window.position.size);
+            let right_edge = window.position.x + self.get_tile_width(&window.position);
             
             let left_distance = (left_edge - focused_center).abs();
             if left_distance < best_distance {
@@ -1030,48 +2844,209 @@ impl RibbonTiler {
             x: best_position,
             row: self.current_row,
             size: TileSize::Half,
+            width: None,
+            monitor: 0,
+        }
+    }
+
+    // Where a tile appended to a row would start
+    fn row_end(&self, row: i32) -> i32 {
+        self.rows.get(&row).into_iter().flatten()
+            .filter_map(|hwnd| self.windows.get(hwnd))
+            .map(|w| w.position.x + w.position.units())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Whether another half-width tile would take a row past --row-limit or --max-row-width
+    fn row_is_full(&self, row: i32) -> bool {
+        let order = self.rows.get(&row).map_or(&[][..], Vec::as_slice);
+        let width: i32 = order.iter()
+            .filter_map(|hwnd| self.windows.get(hwnd))
+            .map(|w| w.position.units())
+            .sum();
+        self.row_limit.is_some_and(|limit| order.len() >= limit)
+            || self.max_row_width.is_some_and(|max| width + TileSize::Half.units() > max)
+    }
+    
+    // First row from the current one down with room, when the policy is to spill
+    fn row_with_room(&self) -> i32 {
+        if self.limit_policy != LimitPolicy::Spill {
+            return self.current_row;
         }
+        (self.current_row..)
+            .find(|&row| !self.row_is_full(row))
+            .unwrap_or(self.current_row)
+    }
+
+    // Quarantine tiles whose app stopped responding so layout never blocks on them, and
+    // put them back in their slot once they answer again
+    fn check_hung(&mut self) {
+        let hwnds: Vec<HWND> = self.windows.values().map(|w| w.hwnd).collect();
+        for hwnd in hwnds {
+            let hung = unsafe { IsHungAppWindow(hwnd).as_bool() };
+            if hung && self.hung.insert(hwnd.0) {
+                println!("'{}' stopped responding; no longer moving it", window_info::window_title(hwnd));
+                if !self.dry_run {
+                    Self::set_border_color(hwnd, HUNG_BORDER_COLOR);
+                }
+            } else if !hung && self.hung.remove(&hwnd.0) {
+                println!("'{}' is responding again", window_info::window_title(hwnd));
+                if !self.dry_run {
+                    Self::set_border_color(hwnd, DWMWA_COLOR_DEFAULT);
+                }
+                self.apply_window_position(hwnd, false);
+            }
+        }
+        self.hung.retain(|hwnd| self.windows.contains_key(hwnd));
+        self.fullscreen.retain(|hwnd| self.windows.contains_key(hwnd));
+    }
+
+    // On the hung-check timer. While the screen may be going out to others, nothing pops
+    // up and nothing scrolls without a key press: the OSD is muted, follow mode holds
+    // still and the row strip stays hidden, so titles and apps from other rows stay
+    // off the shared display.
+    fn check_sharing(&mut self) {
+        let guard = &self.config.sharing;
+        let sharing = if guard.guard { sharing::running(&guard.apps) } else { None };
+        if sharing == self.sharing {
+            return;
+        }
+        match &sharing {
+            Some(app) => println!("{} is running; holding back pop-ups and automatic scrolls", app),
+            None => println!("Screen sharing ended"),
+        }
+        if sharing.is_some() {
+            rowstrip::close();
+        }
+        osd::set_muted(sharing.is_some() || self.presenting.is_some());
+        self.sharing = sharing;
+    }
+
+    // Tiles that are never moved for now: hung, or fullscreen
+    fn is_frozen(&self, hwnd: isize) -> bool {
+        self.hung.contains(&hwnd) || self.fullscreen.contains(&hwnd)
+    }
+
+    // A shell hook notification (WM_TILER_SHELL). These come from the taskbar's own
+    // bookkeeping, so they cover what the WinEvent hooks can't tell apart.
+    fn on_shell_event(&mut self, code: u32, hwnd: HWND) {
+        match code {
+            // Some apps swap in a new top-level window, e.g. on a theme or renderer
+            // change. The new one takes over the old tile's slot and width.
+            HSHELL_WINDOWREPLACING => {
+                self.replacing = Some(hwnd.0);
+            }
+            HSHELL_WINDOWREPLACED => {
+                let Some(new) = self.replacing.take() else {
+                    return;
+                };
+                let Some(old) = self.windows.get(&hwnd.0).map(|w| w.position) else {
+                    return;
+                };
+                if self.windows.contains_key(&new) {
+                    return;
+                }
+                let slot = self.rows.get(&old.row).and_then(|order| order.iter().position(|&h| h == hwnd.0)).unwrap_or(usize::MAX);
+                println!("'{}' replaced its window; moving its tile over", window_info::window_title(HWND(new)));
+                self.detach_from_row(hwnd.0);
+                self.windows.remove(&hwnd.0);
+                self.hung.remove(&hwnd.0);
+                self.fullscreen.remove(&hwnd.0);
+                if let Some(members) = self.stacks.remove(&hwnd.0) {
+                    self.stacks.insert(new, members);
+                }
+                self.place_window(HWND(new), old.row, slot, old.size);
+                if let Some(window) = self.windows.get_mut(&new) {
+                    window.position.width = old.width;
+                }
+            }
+            // Off-screen tiles can't show their flashing, so say which one it is
+            shellhook::HSHELL_FLASH if self.windows.get(&hwnd.0).is_some_and(|w| !self.is_on_screen(&w.position)) => {
+                osd::show(&format!("{} wants attention", window_info::window_title(hwnd)), 1.0);
+            }
+            shellhook::HSHELL_FULLSCREEN_ENTER if self.windows.contains_key(&hwnd.0) && self.fullscreen.insert(hwnd.0) => {
+                println!("'{}' went fullscreen; no longer moving it", window_info::window_title(hwnd));
+            }
+            shellhook::HSHELL_FULLSCREEN_EXIT if self.fullscreen.remove(&hwnd.0) => {
+                self.apply_window_position(hwnd, false);
+            }
+            _ => {}
+        }
+    }
+    
+    // Drawn by DWM, so it works on a window that isn't pumping messages (Windows 11 only)
+    fn set_border_color(hwnd: HWND, color: u32) {
+        unsafe {
+            DwmSetWindowAttribute(
+                hwnd,
+                DWMWA_BORDER_COLOR,
+                &color as *const u32 as *const _,
+                std::mem::size_of::<u32>() as u32,
+            ).ok();
+        }
+    }
+    
+    // Forget a tile without touching its window, for ones that are hung or gone bad;
+    // restoring it could block on a window that never answers
+    fn drop_window(&mut self, hwnd: HWND) {
+        if let Some(next) = self.promote_stack(hwnd.0) {
+            self.apply_window_position(HWND(next), false);
+        }
+        self.detach_from_row(hwnd.0);
+        if self.windows.remove(&hwnd.0).is_some() {
+            println!("Dropped window {:?} from the ribbon", hwnd);
+            self.recalculate_ribbon();
+        }
+    }
+    
+    // Show a hidden or collapsed tile again and put it back in its slot
+    fn retry_window(&mut self, hwnd: HWND) {
+        if !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        if window_info::is_hung(hwnd) {
+            println!("Window {:?} is still not responding", hwnd);
+            return;
+        }
+        unsafe {
+            if !IsWindowVisible(hwnd).as_bool() && !self.dry_run {
+                ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+        self.apply_window_position(hwnd, false);
     }
 
     fn remove_window(&mut self, hwnd: HWND) {
+        // The next window of its stack keeps the slot; otherwise neighbours close the
+        // gap at the next recalculation
+        if let Some(next) = self.promote_stack(hwnd.0) {
+            self.apply_window_position(HWND(next), false);
+        }
+        self.detach_from_row(hwnd.0);
+        
+        if self.native_release {
+            if let Some(window) = self.windows.remove(&hwnd.0) {
+                self.restore_window(&window);
+                self.reflow_ribbon();
+                self.needs_ribbon_recalc = true;
+            }
+            return;
+        }
+        
         if let Some(window) = self.windows.get_mut(&hwnd.0) {
             let mut current_rect = RECT::default();
             unsafe {
                 GetWindowRect(hwnd, &mut current_rect).ok();
             }
             
-            let width = window.original_rect.right - window.original_rect.left;
-            let height = window.original_rect.bottom - window.original_rect.top;
-            
-            let screen_width = unsafe { GetSystemMetrics(SM_CXSCREEN) };
-            let screen_height = unsafe { GetSystemMetrics(SM_CYSCREEN) };
-            
-            let mut left = window.original_rect.left;
-            let mut top = window.original_rect.top;
-            
-            if left < -width + 100 || 
-               left > screen_width - 100 ||
-               top < -height + 100 || 
-               top > screen_height - 100 {
-                left = (screen_width - width) / 2;
-                top = (screen_height - height) / 2;
-            }
-            
-            left = left.clamp(-width + 100, screen_width - 100);
-            top = top.clamp(-height + 100, screen_height - 100);
-            
-            let target_rect = RECT {
-                left,
-                top,
-                right: left + width,
-                bottom: top + height,
-            };
+            let target_rect = Self::release_rect(window);
             
             window.animation = Some(AnimationState {
                 start_rect: current_rect,
                 target_rect,
                 start_time: Instant::now(),
-                duration: Duration::from_millis(200),
+                curve: self.curves.exit,
                 animation_type: AnimationType::Exit,
             });
             
@@ -1079,108 +3054,135 @@ impl RibbonTiler {
         }
     }
 
-    fn restore_window(&self, window: &ManagedWindow) {
+    // Screen rect a released window ends up in, for the exit animation
+    fn release_rect(window: &ManagedWindow) -> RECT {
+        let placement = &window.original_placement;
+        let normal = placement.rcNormalPosition;
+        let Some(monitor) = monitor::containing(&normal) else {
+            return normal;
+        };
+        
+        if placement.showCmd == SW_SHOWMAXIMIZED.0 as u32 {
+            return monitor.rcWork;
+        }
+        
+        // Placement rects are in work-area coordinates, except for tool windows
+        if (window.original_ex_style & WS_EX_TOOLWINDOW).0 != 0 {
+            return normal;
+        }
+        let dx = monitor.rcWork.left - monitor.rcMonitor.left;
+        let dy = monitor.rcWork.top - monitor.rcMonitor.top;
+        RECT {
+            left: normal.left + dx,
+            top: normal.top + dy,
+            right: normal.right + dx,
+            bottom: normal.bottom + dy,
+        }
+    }
+
+    fn restore_window(&self, window: &ManagedWindow) {
+        // Every call below would wait on the frozen app
+        if self.hung.contains(&window.hwnd.0) {
+            println!("Leaving {:?} where it is; it is not responding", window.hwnd);
+            return;
+        }
+        // Adopted under dry run, so there is nothing to undo. One adopted before dry run
+        // was turned on was changed for real, and is put back even now.
+        if !window.touched {
+            Self::log_dry_run(window.hwnd, "was never changed, so stays as it is");
+            return;
+        }
+        
         unsafe {
             SetWindowLongW(window.hwnd, GWL_STYLE, window.original_style.0 as i32);
             SetWindowLongW(window.hwnd, GWL_EXSTYLE, window.original_ex_style.0 as i32);
+            layered::restore(window.hwnd, window.original_layering);
             
-            if (window.original_ex_style & WS_EX_LAYERED).0 == 0 {
-                let ex_style = WINDOW_EX_STYLE(GetWindowLongW(window.hwnd, GWL_EXSTYLE) as u32);
-                SetWindowLongW(window.hwnd, GWL_EXSTYLE, 
-                    (ex_style.0 & !WS_EX_LAYERED.0) as i32);
-            }
-            
-            let width = window.original_rect.right - window.original_rect.left;
-            let height = window.original_rect.bottom - window.original_rect.top;
-            
-            let screen_width = GetSystemMetrics(SM_CXSCREEN);
-            let screen_height = GetSystemMetrics(SM_CYSCREEN);
-            
-            let mut left = window.original_rect.left;
-            let mut top = window.original_rect.top;
-            
-            if left < -width + 100 || 
-               left > screen_width - 100 ||
-               top < -height + 100 || 
-               top > screen_height - 100 {
-                left = (screen_width - width) / 2;
-                top = (screen_height - height) / 2;
-            }
-            
-            left = left.clamp(-width + 100, screen_width - 100);
-            top = top.clamp(-height + 100, screen_height - 100);
+            SetWindowPos(window.hwnd, HWND_TOP, 0, 0, 0, 0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_FRAMECHANGED).ok();
             
-            SetWindowPos(
-                window.hwnd,
-                HWND_TOP,
-                left,
-                top,
-                width,
-                height,
-                SWP_NOZORDER | SWP_FRAMECHANGED,
+            // Handles maximized and minimized windows and keeps the window on a connected display.
+            // After our exit animation, DWM's maximize animation would replay on top of it.
+            Self::set_transitions(window.hwnd, self.native_release);
+            SetWindowPlacement(window.hwnd, &window.original_placement).ok();
+            Self::set_transitions(window.hwnd, true);
+        }
+    }
+    
+    // DWM's own minimize/maximize/show animations. Turning them back on clears our
+    // override; there's no way to read an app's own setting first.
+    fn set_transitions(hwnd: HWND, enabled: bool) {
+        let disabled = BOOL::from(!enabled);
+        unsafe {
+            DwmSetWindowAttribute(
+                hwnd,
+                DWMWA_TRANSITIONS_FORCEDISABLED,
+                &disabled as *const BOOL as *const _,
+                std::mem::size_of::<BOOL>() as u32,
             ).ok();
-            
-            ShowWindow(window.hwnd, SW_RESTORE);
         }
     }
 
     fn shutdown(&mut self) {
         println!("\nShutting down Thymeline...");
+        restore::save(self);
         
-        *self.animation_stop_requested.lock().unwrap() = true;
+        if let Some(tracker) = self.time_tracker.as_mut() {
+            tracker.finish_current();
+        }
+        self.stats.store();
+        self.mirror = None;
+        osd::close();
+        hud::close();
+        grabstrip::close();
+        rowstrip::close();
+        switcher::close();
+        if let Some(pad) = self.scratchpad.take() {
+            pad.release();
+        }
         
-        let screen_width = unsafe { GetSystemMetrics(SM_CXSCREEN) };
-        let screen_height = unsafe { GetSystemMetrics(SM_CYSCREEN) };
+        // Stacked windows, and tiles parked in other viewports and on other displays, are
+        // restored along with the active ribbon
+        for (_, members) in self.stacks.drain() {
+            self.windows.extend(members.into_iter().map(|w| (w.hwnd.0, w)));
+        }
+        for parked in self.parked_ribbons.iter_mut().chain(self.monitor_ribbons.values_mut()) {
+            self.windows.extend(parked.windows.drain());
+            parked.rows.clear();
+        }
         
-        for window in self.windows.values_mut() {
-            let mut current_rect = RECT::default();
-            unsafe {
-                GetWindowRect(window.hwnd, &mut current_rect).ok();
-            }
-            
-            let width = window.original_rect.right - window.original_rect.left;
-            let height = window.original_rect.bottom - window.original_rect.top;
-            
-            let mut left = window.original_rect.left;
-            let mut top = window.original_rect.top;
-            
-            if left < -width + 100 || 
-               left > screen_width - 100 ||
-               top < -height + 100 || 
-               top > screen_height - 100 {
-                left = (screen_width - width) / 2;
-                top = (screen_height - height) / 2;
+        *self.animation_stop_requested.lock().unwrap() = true;
+        
+        if !self.native_release {
+            for window in self.windows.values_mut() {
+                let mut current_rect = RECT::default();
+                unsafe {
+                    GetWindowRect(window.hwnd, &mut current_rect).ok();
+                }
+                
+                let target_rect = Self::release_rect(window);
+                
+                window.animation = Some(AnimationState {
+                    start_rect: current_rect,
+                    target_rect,
+                    start_time: Instant::now(),
+                    curve: Curve { duration: Duration::from_millis(150), ..self.curves.exit },
+                    animation_type: AnimationType::Exit,
+                });
             }
             
-            left = left.clamp(-width + 100, screen_width - 100);
-            top = top.clamp(-height + 100, screen_height - 100);
-            
-            let target_rect = RECT {
-                left,
-                top,
-                right: left + width,
-                bottom: top + height,
-            };
+            self.start_animation_timer();
             
-            window.animation = Some(AnimationState {
-                start_rect: current_rect,
-                target_rect,
-                start_time: Instant::now(),
-                duration: Duration::from_millis(150),
-                animation_type: AnimationType::Exit,
-            });
+            thread::sleep(Duration::from_millis(200));
         }
         
-        self.start_animation_timer();
-        
-        thread::sleep(Duration::from_millis(200));
-        
         let windows: Vec<ManagedWindow> = self.windows.values().cloned().collect();
         for window in windows {
             self.restore_window(&window);
         }
         
         self.windows.clear();
+        self.rows.clear();
         self.ribbon_offset = 0;
         self.ribbon_offset_target = 0;
         self.vertical_offset = 0;
@@ -1188,16 +3190,12 @@ impl RibbonTiler {
         self.current_row = 0;
         self.scroll_animation = None;
         
-        for (_, hwnd) in &self.floating_windows {
+        for floating in self.floating_windows.values() {
             unsafe {
-                if IsWindow(*hwnd).as_bool() {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(*hwnd, GWL_EXSTYLE) as u32);
-                    if self.transparency < 255 {
-                        SetWindowLongW(*hwnd, GWL_EXSTYLE, 
-                            (ex_style.0 & !WS_EX_LAYERED.0) as i32);
-                        SetWindowPos(*hwnd, HWND_TOP, 0, 0, 0, 0,
-                            SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOZORDER).ok();
-                    }
+                if IsWindow(floating.hwnd).as_bool() && self.window_alpha(floating.hwnd) < 255 && !self.dry_run {
+                    layered::restore(floating.hwnd, floating.original_layering);
+                    SetWindowPos(floating.hwnd, HWND_TOP, 0, 0, 0, 0,
+                        SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOZORDER).ok();
                 }
             }
         }
@@ -1208,40 +3206,29 @@ impl RibbonTiler {
     }
 
     fn apply_window_position(&mut self, hwnd: HWND, animate: bool) {
+        if self.is_frozen(hwnd.0) {
+            return;
+        }
         if animate {
             self.apply_window_position_with_animation_type(hwnd, AnimationType::Move);
         } else {
-            let position = match self.windows.get(&hwnd.0) {
-                Some(window) => window.position,
+            let target_rect = match self.windows.get(&hwnd.0) {
+                Some(window) => self.tile_rect(window),
                 None => return,
             };
             
-            let target_rect = self.ribbon_to_screen(&position);
+            if self.dry_run {
+                Self::log_dry_run_rect(hwnd, &target_rect);
+                return;
+            }
+            
+            if let Some(window) = self.windows.get(&hwnd.0) {
+                Self::take_style(window);
+            }
+            
+            self.apply_transparency(hwnd);
             
             unsafe {
-                if let Some(window) = self.windows.get(&hwnd.0) {
-                    if IsZoomed(hwnd).as_bool() {
-                        ShowWindow(hwnd, SW_RESTORE);
-                    }
-                    
-                    let style = window.original_style;
-                    let style = WINDOW_STYLE(style.0 & !WS_MINIMIZEBOX.0 & !WS_MAXIMIZEBOX.0 & !WS_MAXIMIZE.0);
-                    SetWindowLongW(hwnd, GWL_STYLE, style.0 as i32);
-                    SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0, 
-                        SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOACTIVATE);
-                }
-
-                if self.transparency < 255 {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-                    SetWindowLongW(hwnd, GWL_EXSTYLE, 
-                        (ex_style.0 | WS_EX_LAYERED.0) as i32);
-                    SetLayeredWindowAttributes(hwnd, COLORREF(0), self.transparency, LWA_ALPHA).ok();
-                } else {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-                    SetWindowLongW(hwnd, GWL_EXSTYLE, 
-                        (ex_style.0 & !WS_EX_LAYERED.0) as i32);
-                }
-                
                 ShowWindow(hwnd, SW_RESTORE);
                 
                 SetWindowPos(
@@ -1258,15 +3245,21 @@ impl RibbonTiler {
     }
     
     fn apply_window_position_with_animation_type(&mut self, hwnd: HWND, animation_type: AnimationType) {
-        let position = match self.windows.get(&hwnd.0) {
-            Some(window) => window.position,
+        if self.is_frozen(hwnd.0) {
+            return;
+        }
+        let (position, target_rect) = match self.windows.get(&hwnd.0) {
+            Some(window) => (window.position, self.tile_rect(window)),
             None => return,
         };
         
-        let target_rect = self.ribbon_to_screen(&position);
+        if self.dry_run {
+            Self::log_dry_run_rect(hwnd, &target_rect);
+            return;
+        }
         
         unsafe {
-            if let Some(window) = self.windows.get(&hwnd.0) {
+            if let Some(window) = self.windows.get(&hwnd.0).filter(|w| !w.keep_style) {
                 if IsZoomed(hwnd).as_bool() {
                     ShowWindow(hwnd, SW_RESTORE);
                 }
@@ -1278,18 +3271,10 @@ impl RibbonTiler {
                     SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED | SWP_NOACTIVATE);
             }
 
-            if self.transparency < 255 {
-                let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-                SetWindowLongW(hwnd, GWL_EXSTYLE, 
-                    (ex_style.0 | WS_EX_LAYERED.0) as i32);
-                SetLayeredWindowAttributes(hwnd, COLORREF(0), self.transparency, LWA_ALPHA).ok();
-            } else {
-                let ex_style = WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32);
-                SetWindowLongW(hwnd, GWL_EXSTYLE, 
-                    (ex_style.0 & !WS_EX_LAYERED.0) as i32);
-            }
         }
         
+        self.apply_transparency(hwnd);
+        
         if let Some(window) = self.windows.get_mut(&hwnd.0) {
             unsafe {
                 let mut current_rect = RECT::default();
@@ -1302,17 +3287,17 @@ impl RibbonTiler {
                     ShowWindow(hwnd, SW_RESTORE);
                 }
                 
-                let duration = match animation_type {
-                    AnimationType::Entry => Duration::from_millis(200),
-                    AnimationType::Exit => Duration::from_millis(200),
-                    AnimationType::Move => Duration::from_millis(87),
+                let curve = match animation_type {
+                    AnimationType::Entry => self.curves.entry,
+                    AnimationType::Exit => self.curves.exit,
+                    AnimationType::Move => self.curves.moves,
                 };
                 
                 window.animation = Some(AnimationState {
                     start_rect: current_rect,
                     target_rect,
                     start_time: Instant::now(),
-                    duration,
+                    curve,
                     animation_type,
                 });
                 
@@ -1325,7 +3310,7 @@ impl RibbonTiler {
     fn find_next_free_position(&self) -> RibbonPosition {
         let max_x = self.windows.values()
             .filter(|w| w.position.row == self.current_row)
-            .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
             .max()
             .unwrap_or(0);
 
@@ -1339,20 +3324,29 @@ impl RibbonTiler {
             x,
             row: self.current_row,
             size: TileSize::Half,
+            width: None,
+            monitor: 0,
         }
     }
 
-    fn get_tile_width(&self, size: &TileSize) -> i32 {
-        match size {
-            TileSize::Full => self.monitor_width,
-            TileSize::Half => self.monitor_width / 2,
-        }
+    // Width in ribbon units
+    fn get_tile_width(&self, pos: &RibbonPosition) -> i32 {
+        pos.units()
+    }
+
+    fn to_pixels(&self, units: i32) -> i32 {
+        Self::units_to_pixels(units, self.viewport.width)
+    }
+
+    fn units_to_pixels(units: i32, viewport_width: i32) -> i32 {
+        (units as i64 * viewport_width as i64 / RIBBON_SPAN as i64) as i32
     }
 
     fn resize_window(&mut self, hwnd: HWND, direction: Direction) {
         self.check_monitor_dimensions();
         self.clean_closed_windows();
         self.clean_minimized_windows();
+        self.settle_scroll();
         
         if !self.windows.contains_key(&hwnd.0) {
             if !self.add_window(hwnd) {
@@ -1361,44 +3355,18 @@ impl RibbonTiler {
         }
 
         if let Some(window) = self.windows.get(&hwnd.0).cloned() {
-            let old_size = window.position.size;
-            let old_width = self.get_tile_width(&old_size);
-            
-            let new_size = match (old_size, direction) {
-                (TileSize::Full, Direction::Left | Direction::Right) => TileSize::Half,
-                (TileSize::Half, Direction::Left | Direction::Right) => TileSize::Full,
-                _ => old_size,
+            let new_size = match direction {
+                Direction::Left => TileSize::step(window.position.units(), false),
+                Direction::Right => TileSize::step(window.position.units(), true),
+                _ => window.position.size,
             };
             
-            let new_width = self.get_tile_width(&new_size);
-            let width_diff = new_width - old_width;
-            
             if let Some(w) = self.windows.get_mut(&hwnd.0) {
-                w.position.size = new_size;
+                w.position.set_size(new_size);
             }
             
-            // If expanding, push windows to the right
-            if width_diff > 0 {
-                let current_pos = window.position;
-                let current_end = current_pos.x + old_width;
-                
-                // Find all windows to the right on the same row that need to be pushed
-                let windows_to_push: Vec<isize> = self.windows.iter()
-                    .filter(|(h, w)| {
-                        **h != hwnd.0 && 
-                        w.position.row == current_pos.row && 
-                        w.position.x >= current_end
-                    })
-                    .map(|(h, _)| *h)
-                    .collect();
-                
-                // Push them right by the width difference
-                for hwnd_to_push in windows_to_push {
-                    if let Some(w) = self.windows.get_mut(&hwnd_to_push) {
-                        w.position.x += width_diff;
-                    }
-                }
-            }
+            // Tiles to the right follow the new width
+            self.layout_rows();
             
             self.needs_ribbon_recalc = true;
         }
@@ -1406,6 +3374,28 @@ impl RibbonTiler {
         self.apply_all_windows(true);
     }
 
+    // Widen (+1) or narrow (-1) a tile by layout.resize_step_percent of the screen, between
+    // a tenth of the screen and all of it. Landing on a fixed width makes it that width.
+    fn resize_window_by(&mut self, hwnd: HWND, direction: i32) {
+        self.check_monitor_dimensions();
+        self.settle_scroll();
+        
+        let step = RIBBON_SPAN * self.config.layout.resize_step_percent as i32 / 100;
+        let Some(window) = self.windows.get_mut(&hwnd.0) else {
+            return;
+        };
+        let units = (window.position.units() + direction * step).clamp(RIBBON_SPAN / 10, RIBBON_SPAN);
+        match TileSize::of_units(units) {
+            Some(size) => window.position.set_size(size),
+            None => window.position.width = Some(units),
+        }
+        
+        // Tiles to the right follow the new width
+        self.layout_rows();
+        self.needs_ribbon_recalc = true;
+        self.apply_all_windows(true);
+    }
+
     fn pull_adjacent_windows(&mut self, _changed_hwnd: isize) {
         self.needs_ribbon_recalc = true;
     }
@@ -1420,16 +3410,19 @@ impl RibbonTiler {
             return;
         }
 
-        // Clear any existing animation on the focused window
-        if let Some(window) = self.windows.get_mut(&hwnd.0) {
-            window.animation = None;
-        }
-
+        // Work from the logical layout, never from a half-finished scroll
+        self.settle_scroll();
+        self.layout_rows();
+        
         let current_pos = match self.windows.get(&hwnd.0) {
             Some(w) => w.position,
             None => return,
         };
         
+        // Store old positions for animation
+        let old_ribbon_offset = self.ribbon_offset;
+        let old_vertical_offset = self.vertical_offset;
+        
         match direction {
             Direction::Up | Direction::Down => {
                 let old_row = current_pos.row;
@@ -1445,178 +3438,59 @@ impl RibbonTiler {
                     _ => unreachable!(),
                 };
                 
-                let current_width = self.get_tile_width(&current_pos.size);
-                let current_x = current_pos.x;
-                let current_end = current_x + current_width;
-                
-                // Check if the target position is empty
-                let mut is_empty = true;
-                let mut blocking_windows = Vec::new();
-                
-                for (other_hwnd, window) in self.windows.iter() {
-                    if window.position.row == new_row {
-                        let other_x = window.position.x;
-                        let other_width = self.get_tile_width(&window.position.size);
-                        let other_end = other_x + other_width;
-                        
-                        // Check if this window overlaps with our target position
-                        if !(current_end <= other_x || current_x >= other_end) {
-                            is_empty = false;
-                            blocking_windows.push((*other_hwnd, other_x, other_width));
-                        }
-                    }
-                }
-                
-                // Store old positions for animation
-                let old_ribbon_offset = self.ribbon_offset;
-                let old_vertical_offset = self.vertical_offset;
-                
-                if is_empty {
-                    // Just move to the empty space
-                    if let Some(window) = self.windows.get_mut(&hwnd.0) {
-                        window.position.row = new_row;
-                    }
-                } else {
-                    // Need to swap or shift windows
-                    blocking_windows.sort_by_key(|(_, x, _)| *x);
-                    
-                    // Shift blocking windows to make room
-                    for (other_hwnd, _, _) in &blocking_windows {
-                        if let Some(w) = self.windows.get_mut(other_hwnd) {
-                            w.position.x += current_width;
-                        }
-                    }
-                    
-                    // Move the current window to the new row
-                    if let Some(window) = self.windows.get_mut(&hwnd.0) {
-                        window.position.row = new_row;
-                    }
-                }
+                // Land in the new row ahead of whatever starts at or after our x
+                self.detach_from_row(hwnd.0);
+                self.insert_into_row(hwnd.0, new_row, current_pos.x);
+                self.layout_rows();
                 
                 // Update viewport to keep focused window stationary
                 let row_diff = new_row - old_row;
                 self.current_row = new_row;
                 self.vertical_offset = old_vertical_offset + row_diff * self.row_height;
                 self.vertical_offset_target = self.vertical_offset;
-                
-                // Start smooth universe movement
-                self.animate_universe_movement(hwnd, old_ribbon_offset, old_vertical_offset);
-                
-                self.needs_ribbon_recalc = true;
-                
-                unsafe {
-                    SetForegroundWindow(hwnd);
-                }
             },
             Direction::Left | Direction::Right => {
-                // Define the step size - half monitor width for consistent increments
-                let step_size = self.monitor_width / 2;
-                
-                let current_width = self.get_tile_width(&current_pos.size);
-                let old_x = current_pos.x;
-                let mut new_x = old_x;
-                
-                // Get windows on the same row for collision checking
-                let windows_on_row: Vec<(isize, i32, i32)> = self.windows.iter()
-                    .filter(|(_, w)| w.position.row == current_pos.row)
-                    .map(|(h, w)| (*h, w.position.x, self.get_tile_width(&w.position.size)))
-                    .collect();
-                
-                match direction {
-                    Direction::Left => {
-                        // Move left by step_size, but check for collisions
-                        let proposed_x = (old_x - step_size).max(0);
-                        
-                        // Check if this position would overlap with any window
-                        let mut blocked = false;
-                        for (other_hwnd, other_x, other_width) in &windows_on_row {
-                            if *other_hwnd != hwnd.0 {
-                                let other_end = other_x + other_width;
-                                // Check if we would overlap
-                                if proposed_x < other_end && proposed_x + current_width > *other_x {
-                                    // We would overlap, so swap positions instead
-                                    new_x = *other_x;
-                                    if let Some(w) = self.windows.get_mut(other_hwnd) {
-                                        w.position.x = old_x;
-                                    }
-                                    blocked = true;
-                                    break;
-                                }
-                            }
-                        }
-                        
-                        if !blocked {
-                            new_x = proposed_x;
-                        }
-                    },
-                    Direction::Right => {
-                        // Move right by step_size
-                        let proposed_x = old_x + step_size;
-                        
-                        // Check if this position would overlap with any window
-                        let mut blocked = false;
-                        for (other_hwnd, other_x, other_width) in &windows_on_row {
-                            if *other_hwnd != hwnd.0 {
-                                let other_end = other_x + other_width;
-                                // Check if we would overlap
-                                if proposed_x < other_end && proposed_x + current_width > *other_x {
-                                    // We would overlap, so swap positions instead
-                                    new_x = *other_x;
-                                    if let Some(w) = self.windows.get_mut(other_hwnd) {
-                                        w.position.x = old_x;
-                                    }
-                                    blocked = true;
-                                    break;
-                                }
-                            }
-                        }
-                        
-                        if !blocked {
-                            new_x = proposed_x;
-                        }
-                    },
-                    _ => unreachable!(),
-                }
-                
-                // Calculate actual movement distance
-                let movement_distance = new_x - old_x;
-                
-                if movement_distance == 0 {
-                    return; // No movement needed
-                }
-                
-                // Store old offset for animation
-                let old_ribbon_offset = self.ribbon_offset;
-                let old_vertical_offset = self.vertical_offset;
-                
-                // Update position
-                if let Some(w) = self.windows.get_mut(&hwnd.0) {
-                    w.position.x = new_x;
-                }
-                
-                // Update ribbon offset to keep focused window stationary
-                self.ribbon_offset = old_ribbon_offset + movement_distance;
-                self.ribbon_offset_target = self.ribbon_offset;
-                
-                // Clamp to valid bounds
-                let max_x = self.windows.values()
-                    .map(|w| w.position.x + self.get_tile_width(&w.position.size))
-                    .max()
-                    .unwrap_or(0);
-                let max_offset = (max_x - self.monitor_width).max(0);
-                self.ribbon_offset = self.ribbon_offset.clamp(0, max_offset);
-                self.ribbon_offset_target = self.ribbon_offset;
-                
-                // Start smooth universe movement
-                self.animate_universe_movement(hwnd, old_ribbon_offset, old_vertical_offset);
-                
-                self.needs_ribbon_recalc = true;
+                let Some(order) = self.rows.get_mut(&current_pos.row) else {
+                    return;
+                };
+                let Some(index) = order.iter().position(|h| *h == hwnd.0) else {
+                    return;
+                };
                 
-                unsafe {
-                    SetForegroundWindow(hwnd);
-                }
-            }
+                // Swap with the neighbour on that side
+                let neighbour = match direction {
+                    Direction::Left if index > 0 => index - 1,
+                    Direction::Right if index + 1 < order.len() => index + 1,
+                    _ => return,
+                };
+                order.swap(index, neighbour);
+                self.layout_rows();
+            },
         }
+        
+        // Update ribbon offset to keep focused window stationary
+        let new_x = self.windows.get(&hwnd.0).map_or(current_pos.x, |w| w.position.x);
+        self.ribbon_offset = old_ribbon_offset + new_x - current_pos.x;
+        
+        // Clamp to valid bounds
+        let max_x = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
+            .max()
+            .unwrap_or(0);
+        let max_offset = (max_x - RIBBON_SPAN).max(0);
+        self.ribbon_offset = self.ribbon_offset.clamp(0, max_offset);
+        self.ribbon_offset_target = self.ribbon_offset;
+        
+        // Start smooth universe movement
+        self.animate_universe_movement(hwnd, old_ribbon_offset, old_vertical_offset);
+        
+        // The focused tile glides from wherever it is on screen to its logical spot
+        // (normally where it already is, unless the offset had to be clamped)
+        self.apply_window_position(hwnd, true);
+        
+        self.needs_ribbon_recalc = true;
+        
+        focus::focus_window(hwnd);
     }
     
     // Smoothly animate universe movement from old viewport to new viewport
@@ -1627,6 +3501,7 @@ impl RibbonTiler {
         let margin_h = self.margin_horizontal;
         let margin_v = self.margin_vertical;
         let row_height = self.row_height;
+        let viewport = self.viewport;
         let monitor_width = self.monitor_width;
         
         // For each window, calculate where it would be with the OLD viewport
@@ -1638,25 +3513,22 @@ impl RibbonTiler {
             }
             
             // Get tile width before we need it
-            let tile_width = match window.position.size {
-                TileSize::Full => monitor_width,
-                TileSize::Half => monitor_width / 2,
-            };
+            let to_pixels = |units: i32| Self::units_to_pixels(units, viewport.width);
+            let tile_width = to_pixels(window.position.units());
             
             // Calculate old screen position (with old viewport)
-            let old_screen_x = window.position.x - old_ribbon_offset;
+            let old_screen_x = viewport.screen_x(to_pixels(window.position.x - old_ribbon_offset), tile_width, monitor_width);
             let old_screen_y = window.position.row * row_height - old_vertical_offset;
             
             // Calculate new screen position (with new viewport)
-            let new_screen_x = window.position.x - new_ribbon_offset;
+            let new_screen_x = viewport.screen_x(to_pixels(window.position.x - new_ribbon_offset), tile_width, monitor_width);
             let new_screen_y = window.position.row * row_height - new_vertical_offset;
             
             // If there's already an animation in progress, we need to handle it carefully
             let start_rect = if let Some(existing_anim) = &window.animation {
                 // Use the current interpolated position as the start
                 let elapsed = Instant::now().duration_since(existing_anim.start_time);
-                let t = (elapsed.as_secs_f32() / existing_anim.duration.as_secs_f32()).min(1.0);
-                let eased_t = Self::ease_out_cubic(t);
+                let eased_t = existing_anim.curve.progress(elapsed);
                 
                 RECT {
                     left: Self::lerp(existing_anim.start_rect.left, existing_anim.target_rect.left, eased_t),
@@ -1682,12 +3554,12 @@ impl RibbonTiler {
                 bottom: new_screen_y + row_height - margin_v / 2,
             };
             
-            // Create smooth animation
+            // Slides along with the view, so it keeps time with a scroll
             window.animation = Some(AnimationState {
                 start_rect,
                 target_rect,
                 start_time: Instant::now(),
-                duration: Duration::from_millis(200),
+                curve: self.curves.scroll,
                 animation_type: AnimationType::Move,
             });
         }
@@ -1695,42 +3567,9 @@ impl RibbonTiler {
         self.start_animation_timer();
     }
 
-    fn recalculate_positions_for_new_resolution(&mut self, old_width: i32) {
-        if self.windows.is_empty() {
-            return;
-        }
-        
-        let scale_factor = self.monitor_width as f32 / old_width as f32;
-        
-        // Recalculate row height
-        self.row_height = self.monitor_height;
-        
-        // Group windows by row
-        let mut rows: HashMap<i32, Vec<(isize, RibbonPosition)>> = HashMap::new();
-        for (hwnd, w) in self.windows.iter() {
-            rows.entry(w.position.row)
-                .or_insert_with(Vec::new)
-                .push((*hwnd, w.position));
-        }
-        
-        // Process each row
-        for (row, mut windows) in rows {
-            windows.sort_by_key(|(_, pos)| pos.x);
-            
-            let mut current_x = 0;
-            for (hwnd, old_pos) in windows {
-                if let Some(window) = self.windows.get_mut(&hwnd) {
-                    window.position.x = current_x;
-                    window.position.row = row;
-                    window.position.size = old_pos.size;
-                    
-                    current_x += self.get_tile_width(&old_pos.size);
-                }
-            }
-        }
-        
-        self.ribbon_offset = (self.ribbon_offset as f32 * scale_factor) as i32;
-        self.ribbon_offset_target = self.ribbon_offset;
+    // Ribbon positions are resolution independent, so only the row geometry follows the screen
+    fn recalculate_positions_for_new_resolution(&mut self) {
+        self.row_height = Self::row_height_for(self.monitor_width, self.monitor_height, self.visible_rows);
         self.vertical_offset = self.current_row * self.row_height;
         self.vertical_offset_target = self.vertical_offset;
         
@@ -1755,16 +3594,35 @@ impl RibbonTiler {
         
         self.last_resolution_check = now;
         
+        // A secondary display was added, removed or rearranged; tiles on a display that
+        // went away fall back to the primary
+        let displays = monitor::secondary_work_areas();
+        if displays != self.displays {
+            self.refresh_rates = Self::refresh_rates(&displays);
+            self.displays = displays;
+            self.follow_refresh_rate();
+            self.rehome_lost_displays();
+            self.apply_display_preferences();
+            self.apply_all_windows(false);
+        }
+        
         let (new_width, new_height) = Self::get_monitor_dimensions();
         
         if new_width != self.monitor_width || new_height != self.monitor_height {
-            let old_width = self.monitor_width;
             self.monitor_width = new_width;
             self.monitor_height = new_height;
+            self.viewport = Self::viewport_bounds(&self.viewport_splits, self.viewport_index, new_width);
             
-            self.recalculate_positions_for_new_resolution(old_width);
+            self.recalculate_positions_for_new_resolution();
             
             self.apply_all_windows(false);
+            if self.desktop_profile.is_some() && self.config.tablet.grab_strip {
+                grabstrip::show();
+            }
+
+            // A mode change usually comes with a new refresh rate too
+            self.refresh_rates = Self::refresh_rates(&self.displays);
+            self.follow_refresh_rate();
         }
     }
 
@@ -1777,19 +3635,19 @@ impl RibbonTiler {
         }
         
         let max_x = self.windows.values()
-            .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
             .max()
             .unwrap_or(0);
-        let max_offset = (max_x - self.monitor_width).max(0);
+        let max_offset = (max_x - RIBBON_SPAN).max(0);
         
         // Check if we're already at the edge
         match direction {
-            Direction::Left if self.ribbon_offset_target <= 0 => return,
-            Direction::Right if self.ribbon_offset_target >= max_offset => return,
+            Direction::Left if self.ribbon_offset_target <= 0 => return self.show_position_hud(),
+            Direction::Right if self.ribbon_offset_target >= max_offset => return self.show_position_hud(),
             _ => {},
         }
         
-        let snap_distance = self.monitor_width / 2;
+        let snap_distance = RIBBON_SPAN / 2;
         
         match direction {
             Direction::Left => {
@@ -1802,132 +3660,717 @@ impl RibbonTiler {
         }
         
         self.start_scroll_animation();
+        self.show_position_hud();
     }
     
-    // Pan between rows
-    fn pan_row(&mut self, direction: Direction) {
+    // Jump to the start or the end of the ribbon in one scroll
+    fn pan_ribbon_to_edge(&mut self, direction: Direction) {
         self.check_monitor_dimensions();
         self.clean_closed_windows();
         
-        // Get the maximum row that has windows
-        let max_row_with_windows = self.windows.values()
-            .map(|w| w.position.row)
+        let max_x = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
             .max()
             .unwrap_or(0);
-        
-        // Allow panning one row beyond the last window row (for empty space)
-        // but no further
-        let max_allowed_row = max_row_with_windows + 1;
-        
-        match direction {
-            Direction::Up => {
-                if self.current_row > 0 {
-                    self.current_row -= 1;
-                    self.vertical_offset_target = self.current_row * self.row_height;
-                    println!("Targeting row {}", self.current_row);
-                    self.start_scroll_animation();
-                }
-            },
-            Direction::Down => {
-                if self.current_row < max_allowed_row {
-                    self.current_row += 1;
-                    self.vertical_offset_target = self.current_row * self.row_height;
-                    println!("Targeting row {}", self.current_row);
-                    self.start_scroll_animation();
-                }
-            },
-            _ => return,
+        let target = match direction {
+            Direction::Right => (max_x - RIBBON_SPAN).max(0),
+            _ => 0,
         };
+        if target != self.ribbon_offset_target {
+            self.ribbon_offset_target = target;
+            self.start_scroll_animation();
+        }
+        self.show_position_hud();
     }
     
-    // Start or update scroll animation to current targets
-    fn start_scroll_animation(&mut self) {
-        // If we're already animating, just update the targets
-        // The animation will smoothly interpolate to the new destination
-        
-        // Clamp targets to valid bounds
-        let max_row = self.windows.values()
-            .map(|w| w.position.row)
-            .max()
-            .unwrap_or(0);
-        let max_vertical = max_row * self.row_height;
-        self.vertical_offset_target = self.vertical_offset_target.clamp(0, max_vertical);
+    // A finger or pen on the grab strip takes hold of the ribbon. A scroll under way stops
+    // where it is, so a glide can be caught; a row change lands at once.
+    fn begin_drag(&mut self) {
+        self.check_monitor_dimensions();
+        self.clean_closed_windows();
         
+        if let Some(scroll_anim) = self.scroll_animation.take() {
+            self.ribbon_offset_target = self.ribbon_offset;
+            self.vertical_offset = scroll_anim.target_y;
+            self.vertical_offset_target = scroll_anim.target_y;
+            self.apply_all_windows(false);
+        }
+        self.drag_origin = Some(self.ribbon_offset_target);
+    }
+    
+    // Dragging right pulls the ribbon right, showing what lies to the left
+    fn drag_offset(&self, origin: i32, pixels: i32) -> i32 {
         let max_x = self.windows.values()
-            .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
             .max()
             .unwrap_or(0);
-        let max_horizontal = (max_x - self.monitor_width).max(0);
-        self.ribbon_offset_target = self.ribbon_offset_target.clamp(0, max_horizontal);
-        
-        // Start new animation from current position to target
-        self.scroll_animation = Some(ScrollAnimation {
-            start_x: self.ribbon_offset,
+        let max_offset = (max_x - RIBBON_SPAN).max(0);
+        let units = (pixels as i64 * RIBBON_SPAN as i64 / self.viewport.width.max(1) as i64) as i32;
+        (origin - units).clamp(0, max_offset)
+    }
+    
+    // The ribbon follows the finger with no animation
+    fn drag_ribbon(&mut self, pixels: i32) {
+        let Some(origin) = self.drag_origin else {
+            return;
+        };
+        self.ribbon_offset = self.drag_offset(origin, pixels);
+        self.ribbon_offset_target = self.ribbon_offset;
+        
+        let updates: Vec<(HWND, RECT)> = self.windows.values()
+            .filter(|w| w.animation.is_none())
+            .map(|w| (w.hwnd, self.tile_rect(w)))
+            .collect();
+        self.batch_set_window_positions(&updates);
+    }
+    
+    // Let go: the ribbon glides on by however far the release carried it (`pixels`
+    // includes that), then lands with the nearest tile's left edge at the start of the
+    // screen, or at the end of the ribbon
+    fn end_drag(&mut self, pixels: i32) {
+        let Some(origin) = self.drag_origin.take() else {
+            return;
+        };
+        let target = self.drag_offset(origin, pixels);
+        let max_x = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
+            .max()
+            .unwrap_or(0);
+        let end = (max_x - RIBBON_SPAN).max(0);
+        let edges = self.rows.get(&self.current_row).into_iter().flatten()
+            .filter_map(|hwnd| self.windows.get(hwnd))
+            .map(|w| w.position.x.min(end))
+            .chain(std::iter::once(end));
+        self.ribbon_offset_target = edges.min_by_key(|x| (x - target).abs()).unwrap_or(target);
+        
+        self.start_scroll_animation();
+        self.show_position_hud();
+    }
+    
+    // Pan between rows
+    fn pan_row(&mut self, direction: Direction) {
+        self.check_monitor_dimensions();
+        self.clean_closed_windows();
+        
+        let new_row = match direction {
+            Direction::Up if self.current_row > 0 => self.current_row - 1,
+            Direction::Down if self.current_row < self.last_pannable_row() => self.current_row + 1,
+            _ => return self.show_position_hud(),
+        };
+        self.scroll_to_row(new_row);
+        self.show_position_hud();
+    }
+    
+    // "Row 2 · 3/7": the row the view is heading for, and which of its tiles will be
+    // under the middle of the screen
+    fn show_position_hud(&self) {
+        if !self.config.appearance.hud || self.sharing.is_some() {
+            return;
+        }
+        let tiles: Vec<&RibbonPosition> = self.rows.get(&self.current_row).into_iter().flatten()
+            .filter_map(|hwnd| self.windows.get(hwnd))
+            .map(|w| &w.position)
+            .collect();
+        let row = self.current_row + 1;
+        if tiles.is_empty() {
+            hud::show(&format!("Row {} · empty", row));
+            return;
+        }
+        let middle = self.ribbon_offset_target + RIBBON_SPAN / 2;
+        let index = tiles.iter()
+            .position(|pos| pos.x + self.get_tile_width(pos) > middle)
+            .unwrap_or(tiles.len() - 1);
+        hud::show(&format!("Row {} · {}/{}", row, index + 1, tiles.len()));
+    }
+    
+    // Allow panning one row beyond the last window row (for empty space) but no further
+    fn last_pannable_row(&self) -> i32 {
+        let max_row_with_windows = self.windows.values()
+            .map(|w| w.position.row)
+            .max()
+            .unwrap_or(0);
+        max_row_with_windows + 1
+    }
+    
+    fn scroll_to_row(&mut self, row: i32) {
+        self.current_row = row;
+        self.vertical_offset_target = self.current_row * self.row_height;
+        println!("Targeting row {}", self.current_row);
+        self.recall_row_focus();
+        self.start_scroll_animation();
+    }
+    
+    // Key repeat while the row-switch key is held: move the pick and show where it lands
+    fn step_row_pick(&mut self, direction: Direction) {
+        let from = self.row_pick.unwrap_or(self.current_row);
+        let target = match direction {
+            Direction::Up => from - 1,
+            _ => from + 1,
+        }.clamp(0, self.last_pannable_row());
+        self.row_pick = Some(target);
+        if self.sharing.is_none() {
+            rowstrip::show(self.row_previews(), self.current_row as usize, target as usize, self.config.appearance.row_dim);
+        }
+    }
+    
+    // Every row for the row strip, with where focus time has gone along it
+    fn row_previews(&self) -> Vec<rowstrip::RowPreview> {
+        let heat = self.stats.heat();
+        let span = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
+            .max()
+            .unwrap_or(0)
+            .max(RIBBON_SPAN) as f32;
+        let column = (RIBBON_SPAN / 2) as f32 / span;
+        (0..=self.last_pannable_row())
+            .map(|row| rowstrip::RowPreview {
+                tiles: self.rows.get(&row).into_iter().flatten()
+                    .filter_map(|hwnd| self.windows.get(hwnd))
+                    .map(|w| (w.position.x as f32 / span, self.get_tile_width(&w.position) as f32 / span))
+                    .collect(),
+                icons: self.rows.get(&row).into_iter().flatten()
+                    .filter(|hwnd| self.windows.contains_key(hwnd))
+                    .map(|hwnd| self.labels.get(hwnd).and_then(|label| label.icon.clone()))
+                    .collect(),
+                heat: heat.get(&row).into_iter().flatten().enumerate()
+                    .map(|(index, &share)| (index as f32 * column, column, share))
+                    .collect(),
+            })
+            .collect()
+    }
+    
+    // Half-screen columns of the ribbon a tile covers, even partly, for the focus heatmap
+    fn ribbon_columns(pos: &RibbonPosition) -> std::ops::Range<usize> {
+        let column = RIBBON_SPAN / 2;
+        let start = pos.x.max(0);
+        let end = start + pos.units();
+        (start / column) as usize..((end - 1) / column + 1) as usize
+    }
+    
+    // The row strip on its own for a few seconds, shaded by where focus time has gone
+    fn show_heatmap(&mut self) {
+        self.stats.settle();
+        rowstrip::flash(self.row_previews(), self.current_row as usize, self.config.appearance.row_dim, HEATMAP_SHOW_MS);
+    }
+    
+    // Row-switch key released: scroll to the picked row, if the key repeated at all
+    fn commit_row_pick(&mut self) {
+        rowstrip::hide();
+        if let Some(row) = self.row_pick.take().filter(|&row| row != self.current_row) {
+            self.scroll_to_row(row);
+        }
+    }
+    
+    // Tiles of the active ribbon in the order they last had focus, as many as the switcher lists
+    fn recent_tiles(&self) -> Vec<isize> {
+        self.focus_history.iter()
+            .copied()
+            .filter(|hwnd| self.windows.contains_key(hwnd))
+            .take(switcher::MAX_ENTRIES)
+            .collect()
+    }
+    
+    // Each press while the modifiers stay down picks the next tile, starting with the
+    // one used before the focused tile
+    fn step_switcher(&mut self) {
+        let (tiles, selected) = match self.switch_pick.take() {
+            Some((tiles, selected)) => {
+                let next = (selected + 1) % tiles.len();
+                (tiles, next)
+            }
+            None => {
+                let tiles = self.recent_tiles();
+                if tiles.is_empty() {
+                    return;
+                }
+                let first = usize::from(tiles.len() > 1);
+                (tiles, first)
+            }
+        };
+        let entries = tiles.iter()
+            .map(|hwnd| match self.labels.get(hwnd) {
+                Some(label) => switcher::Entry { title: label.title.clone(), icon: label.icon.clone() },
+                None => switcher::Entry { title: window_info::window_title(HWND(*hwnd)), icon: None },
+            })
+            .collect();
+        switcher::show(entries, selected);
+        self.switch_pick = Some((tiles, selected));
+    }
+    
+    // Released: scroll to the picked tile and focus it. Escape closes it without moving.
+    fn end_switcher(&mut self, go: bool) {
+        switcher::hide();
+        let Some((tiles, selected)) = self.switch_pick.take() else {
+            return;
+        };
+        if go {
+            self.go_to_tile(HWND(tiles[selected]));
+        }
+    }
+    
+    fn go_to_tile(&mut self, hwnd: HWND) {
+        if !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        self.scroll_to_window(hwnd);
+        // Focus now, and again when the scroll lands rather than whatever scroll_focus picks
+        self.row_focus_pending = Some(hwnd.0);
+        focus::focus_window(hwnd);
+    }
+    
+    // With row focus memory on, bring the row's last focused tile into view;
+    // it takes focus once the scroll lands
+    fn recall_row_focus(&mut self) {
+        let remembered = self.row_focus.as_ref()
+            .and_then(|row_focus| row_focus.get(&self.current_row))
+            .and_then(|hwnd| self.windows.get(hwnd))
+            .filter(|w| w.position.row == self.current_row)
+            .map(|w| (w.hwnd.0, w.position));
+        
+        self.row_focus_pending = None;
+        let Some((hwnd, position)) = remembered else {
+            return;
+        };
+        
+        // Only scroll sideways when the tile would otherwise be off screen
+        let width = self.get_tile_width(&position);
+        if position.x < self.ribbon_offset_target || position.x + width > self.ribbon_offset_target + RIBBON_SPAN {
+            self.ribbon_offset_target = position.x + width / 2 - RIBBON_SPAN / 2;
+        }
+        self.row_focus_pending = Some(hwnd);
+    }
+    
+    // Layout commands operate on the logical (target) offsets. A scroll still in flight
+    // is settled first: offsets jump to their targets and each tile finishes the
+    // remaining travel as its own Move animation, so nothing jumps on screen.
+    fn settle_scroll(&mut self) {
+        let Some(scroll_anim) = self.scroll_animation.take() else {
+            return;
+        };
+        let remaining = Curve {
+            duration: scroll_anim.curve.duration.saturating_sub(Instant::now().duration_since(scroll_anim.start_time)),
+            ..scroll_anim.curve
+        };
+        
+        let start_rects: Vec<(isize, RECT)> = self.windows.iter()
+            .filter(|(_, w)| w.animation.is_none())
+            .map(|(hwnd, w)| (*hwnd, self.tile_rect(w)))
+            .collect();
+        
+        self.ribbon_offset = self.ribbon_offset_target;
+        self.vertical_offset = self.vertical_offset_target;
+        
+        let now = Instant::now();
+        for (hwnd, start_rect) in start_rects {
+            let Some(target_rect) = self.windows.get(&hwnd).map(|w| self.tile_rect(w)) else {
+                continue;
+            };
+            if let Some(window) = self.windows.get_mut(&hwnd) {
+                window.animation = Some(AnimationState {
+                    start_rect,
+                    target_rect,
+                    start_time: now,
+                    curve: remaining,
+                    animation_type: AnimationType::Move,
+                });
+            }
+        }
+        
+        self.start_animation_timer();
+    }
+    
+    // Start or update scroll animation to current targets
+    fn start_scroll_animation(&mut self) {
+        // If we're already animating, just update the targets
+        // The animation will smoothly interpolate to the new destination
+        
+        // Clamp targets to valid bounds
+        let max_row = self.windows.values()
+            .map(|w| w.position.row)
+            .max()
+            .unwrap_or(0);
+        let max_vertical = max_row * self.row_height;
+        self.vertical_offset_target = self.vertical_offset_target.clamp(0, max_vertical);
+        
+        let max_x = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
+            .max()
+            .unwrap_or(0);
+        let max_horizontal = (max_x - RIBBON_SPAN).max(0);
+        self.ribbon_offset_target = self.ribbon_offset_target.clamp(0, max_horizontal);
+        
+        // Start new animation from current position to target
+        self.scroll_animation = Some(ScrollAnimation {
+            start_x: self.ribbon_offset,
             start_y: self.vertical_offset,
             target_x: self.ribbon_offset_target,
             target_y: self.vertical_offset_target,
             start_time: Instant::now(),
-            duration: Duration::from_millis(200), // Smooth animation duration
+            curve: self.curves.scroll,
         });
         
         self.start_animation_timer();
     }
     
-    fn focus_visible_window(&self) {
-        unsafe {
-            let mut best_window: Option<HWND> = None;
-            let mut best_distance = f32::MAX;
-            
-            let screen_center_x = self.monitor_width as f32 / 2.0;
-            let screen_center_y = self.monitor_height as f32 / 2.0;
-            
-            for window in self.windows.values() {
-                if !self.is_window_visible(&window.position) {
-                    continue;
+    // Tile whose centre lies inside the viewport on screen
+    fn is_on_screen(&self, pos: &RibbonPosition) -> bool {
+        let rect = self.ribbon_rect(pos);
+        let center_x = (rect.left + rect.right) / 2;
+        let center_y = (rect.top + rect.bottom) / 2;
+        center_x >= self.viewport.left && center_x < self.viewport.left + self.viewport.width
+            && center_y >= 0 && center_y < self.monitor_height
+    }
+
+    // Called when a scroll lands; what happens to focus depends on the configured policy
+    fn apply_scroll_focus(&mut self) {
+        if let Some(hwnd) = self.row_focus_pending.take() {
+            if self.windows.contains_key(&hwnd) {
+                focus::focus_window(HWND(hwnd));
+                return;
+            }
+        }
+        
+        match self.scroll_focus {
+            ScrollFocus::None => {},
+            ScrollFocus::Center => self.focus_visible_window(),
+            ScrollFocus::Keep => {
+                // Only move focus when the focused tile has scrolled out of view
+                let foreground = unsafe { GetForegroundWindow() };
+                let still_visible = self.windows.get(&foreground.0)
+                    .is_none_or(|w| self.is_on_screen(&w.position));
+                if !still_visible {
+                    self.focus_visible_window();
                 }
-                
-                let rect = self.ribbon_to_screen(&window.position);
-                let window_center_x = ((rect.left + rect.right) / 2) as f32;
-                let window_center_y = ((rect.top + rect.bottom) / 2) as f32;
-                
-                // Calculate distance from screen center
-                let dx = window_center_x - screen_center_x;
-                let dy = window_center_y - screen_center_y;
-                let distance = (dx * dx + dy * dy).sqrt();
-                
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_window = Some(window.hwnd);
+            },
+            ScrollFocus::Leftmost => {
+                let leftmost = self.windows.values()
+                    .filter(|w| w.animation.as_ref().is_none_or(|a| a.animation_type != AnimationType::Exit))
+                    .filter(|w| self.is_on_screen(&w.position))
+                    .min_by_key(|w| (w.position.row, w.position.x));
+                if let Some(window) = leftmost {
+                    focus::focus_window(window.hwnd);
                 }
+            },
+        }
+    }
+
+    fn focus_visible_window(&self) {
+        let mut best_window: Option<HWND> = None;
+        let mut best_distance = f32::MAX;
+        
+        let screen_center_x = (self.viewport.left + self.viewport.width / 2) as f32;
+        let screen_center_y = self.monitor_height as f32 / 2.0;
+        
+        for window in self.windows.values() {
+            if !self.is_window_visible(&window.position) {
+                continue;
             }
             
-            if let Some(hwnd) = best_window {
-                let current_foreground = GetForegroundWindow();
-                if current_foreground != hwnd {
-                    SetActiveWindow(hwnd);
-                }
+            // Tiles on their way out of the ribbon never take focus
+            if window.animation.as_ref().is_some_and(|a| a.animation_type == AnimationType::Exit) {
+                continue;
+            }
+            
+            let rect = self.ribbon_rect(&window.position);
+            let window_center_x = ((rect.left + rect.right) / 2) as f32;
+            let window_center_y = ((rect.top + rect.bottom) / 2) as f32;
+            
+            // Calculate distance from screen center
+            let dx = window_center_x - screen_center_x;
+            let dy = window_center_y - screen_center_y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            
+            if distance < best_distance {
+                best_distance = distance;
+                best_window = Some(window.hwnd);
             }
         }
+        
+        if let Some(hwnd) = best_window {
+            focus::focus_window(hwnd);
+        }
     }
 
-    fn adjust_transparency(&mut self, delta: i8) {
-        self.transparency = (self.transparency as i16 + delta as i16)
-            .clamp(50, 255) as u8;
+    // Direction is +1 (more opaque) or -1; holding the key ramps the step up
+    fn adjust_transparency(&mut self, direction: i16) {
+        if self.instant {
+            println!("Transparency is off in instant mode");
+            return;
+        }
         
+        let now = Instant::now();
+        let presses = match self.transparency_ramp {
+            Some((last, presses)) if now.duration_since(last) < TRANSPARENCY_RAMP_WINDOW => presses + 1,
+            _ => 0,
+        };
+        self.transparency_ramp = Some((now, presses));
+        
+        // Doubles after every 5 repeats, up to 4x
+        let step = self.transparency_step as i16 * (1 << (presses / 5).min(2));
+        self.transparency = (self.transparency as i16 + direction * step)
+            .clamp(self.transparency_floor as i16, 255) as u8;
+        self.show_transparency_osd();
+        self.reapply_transparency();
+    }
+    
+    fn reapply_transparency(&mut self) {
         self.apply_all_windows(false);
         
-        for (_, hwnd) in &self.floating_windows {
-            unsafe {
-                if IsWindow(*hwnd).as_bool() {
-                    let ex_style = WINDOW_EX_STYLE(GetWindowLongW(*hwnd, GWL_EXSTYLE) as u32);
-                    SetWindowLongW(*hwnd, GWL_EXSTYLE, 
-                        (ex_style.0 | WS_EX_LAYERED.0) as i32);
-                    SetLayeredWindowAttributes(*hwnd, COLORREF(0), self.transparency, LWA_ALPHA).ok();
+        let floating: Vec<HWND> = self.floating_windows.values().map(|f| f.hwnd).collect();
+        for hwnd in floating {
+            if unsafe { IsWindow(hwnd).as_bool() } {
+                self.apply_transparency(hwnd);
+            }
+        }
+    }
+    
+    // One window's alpha after something it depends on changed, if the tiler handles it
+    fn refresh_alpha(&mut self, hwnd: HWND) {
+        let handled = self.windows.contains_key(&hwnd.0) || self.floating_windows.contains_key(&hwnd.0);
+        if handled && !self.is_frozen(hwnd.0) && unsafe { IsWindow(hwnd).as_bool() } {
+            self.apply_transparency(hwnd);
+        }
+    }
+    
+    // Alpha the windows actually get; instant mode keeps them opaque
+    fn alpha(&self) -> u8 {
+        if self.instant { 255 } else { self.transparency }
+    }
+    
+    // A rule's transparency replaces the global one for its windows, and failing that a
+    // scheduled one for the tile's row
+    fn window_alpha(&self, hwnd: HWND) -> u8 {
+        if self.presenting.is_some() {
+            return 255;
+        }
+        if self.config.appearance.dim_unfocused && hwnd.0 == self.focused {
+            return 255;
+        }
+        let row_alpha = self.windows.get(&hwnd.0).and_then(|w| self.row_alphas.get(&w.position.row));
+        match self.alpha_overrides.get(&hwnd.0).or(row_alpha) {
+            Some(&alpha) if !self.instant => alpha,
+            _ => self.alpha(),
+        }
+    }
+    
+    fn show_transparency_osd(&self) {
+        let opacity = self.transparency as f32 / 255.0;
+        let mut text = format!("Opacity {}%", (opacity * 100.0).round() as i32);
+        if self.transparency == self.transparency_floor {
+            text.push_str(" (minimum)");
+        }
+        osd::show(&text, opacity);
+    }
+
+    fn original_layering(&self, hwnd: HWND) -> Layering {
+        self.windows.get(&hwnd.0).map(|w| w.original_layering)
+            .or_else(|| self.floating_windows.get(&hwnd.0).map(|f| f.original_layering))
+            .unwrap_or(Layering::None)
+    }
+    
+    // Layered alpha at the current transparency; windows that render black under it stay opaque
+    fn apply_transparency(&mut self, hwnd: HWND) {
+        let original = self.original_layering(hwnd);
+        if original == Layering::PerPixel || self.dry_run {
+            return;
+        }
+        
+        // Presentation mode hides the other rows' tiles by making them fully
+        // transparent, which also lets clicks through
+        if self.presenting.is_some() && self.windows.get(&hwnd.0).is_some_and(|w| w.position.row != self.presented_row) {
+            layered::set_alpha(hwnd, 0);
+            return;
+        }
+        
+        let alpha = self.window_alpha(hwnd);
+        if alpha == 255 || self.opaque_windows.contains(&hwnd.0) {
+            layered::restore(hwnd, original);
+            return;
+        }
+        
+        if self.transparency_checked.contains(&hwnd.0) {
+            layered::set_alpha(hwnd, alpha);
+            return;
+        }
+        
+        if self.quirk_of(hwnd) == Some(Quirk::Opaque) {
+            self.opaque_windows.insert(hwnd.0);
+            layered::restore(hwnd, original);
+            return;
+        }
+        
+        match layered::set_alpha_checked(hwnd, alpha) {
+            layered::AlphaCheck::Applied => {
+                self.transparency_checked.insert(hwnd.0);
+            }
+            layered::AlphaCheck::Unverified => {}
+            layered::AlphaCheck::Refused => {
+                self.opaque_windows.insert(hwnd.0);
+                layered::restore(hwnd, original);
+                // Remember it for the app's other windows too, unless a rule already covers the app
+                if let Some(exe) = window_info::process_name(hwnd) {
+                    if self.quirk_of(hwnd).is_none() {
+                        println!("Transparency disabled for {} (renders black when layered)", exe);
+                        let mut prefs = Prefs::load();
+                        if !prefs.opaque_apps.iter().any(|app| app.eq_ignore_ascii_case(&exe)) {
+                            prefs.opaque_apps.push(exe.clone());
+                            prefs.save();
+                        }
+                        self.opaque_apps.push(exe);
+                    }
                 }
             }
         }
     }
 
+    // Forget that the window's app went black when layered, and try transparency on its
+    // windows again. An app that still does is caught and remembered again.
+    fn retry_transparency(&mut self, hwnd: HWND) {
+        let Some(exe) = window_info::process_name(hwnd) else {
+            return;
+        };
+        let same_app = |app: &String| app.eq_ignore_ascii_case(&exe);
+        if !self.opaque_apps.iter().any(same_app) {
+            osd::show(&format!("{} is not kept opaque by detection", exe), 0.0);
+            return;
+        }
+        self.opaque_apps.retain(|app| !same_app(app));
+        let mut prefs = Prefs::load();
+        prefs.opaque_apps.retain(|app| !same_app(app));
+        prefs.save();
+
+        self.opaque_windows.retain(|&other| window_info::process_name(HWND(other)).is_none_or(|other| !same_app(&other)));
+        println!("Retrying transparency for {}", exe);
+        osd::show(&format!("Retrying transparency for {}", exe), 0.0);
+        self.reapply_transparency();
+    }
+
+    // Called from the config timer. Only settings the edit changed are applied, so
+    // margins or transparency adjusted from the keyboard since stay as they are.
+    fn check_config(&mut self) {
+        let modified = config::modified();
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+        
+        let Some(config) = Config::reload(&self.config_overrides) else {
+            return;
+        };
+        if config == self.config {
+            return;
+        }
+        let old = std::mem::replace(&mut self.config, config.clone());
+        
+        if config.keybindings != old.keybindings || config.hold_keybindings != old.hold_keybindings
+            || config.input.keymap != old.input.keymap || config.input.hold_ms != old.input.hold_ms
+            || config.repeat != old.repeat {
+            config.apply_keybindings();
+        }
+        if config.input.passthrough != old.input.passthrough {
+            self.update_passthrough(unsafe { GetForegroundWindow() });
+        }
+        self.curves = config.animations.curves();
+        self.command_throttle_ms = config.input.command_throttle_ms as u128;
+        if config.animations.fps != old.animations.fps {
+            let fps = config.animations.fps
+                .or_else(|| self.refresh_rates.get(&self.monitor).copied())
+                .unwrap_or(90);
+            self.animation_fps.store(fps, Ordering::Relaxed);
+            self.fps_chosen = config.animations.fps.is_some();
+        }
+        if config.animations.enabled != old.animations.enabled {
+            self.animate = config.animations.enabled.unwrap_or_else(Self::system_animations);
+            if !self.animate {
+                self.cancel_animations();
+            }
+        }
+        if config.tablet != old.tablet {
+            // Left and entered again, so new tablet values apply at once
+            self.leave_tablet_profile();
+            self.check_posture();
+        }
+        if config.appearance.transparency != old.appearance.transparency {
+            self.transparency = config.appearance.transparency;
+            self.reapply_transparency();
+        } else if config.appearance.dim_unfocused != old.appearance.dim_unfocused {
+            self.reapply_transparency();
+        }
+        if config.rules != old.rules {
+            self.rules = rules::compile(&config.rules);
+            self.reapply_rule_transparency();
+        }
+        // The tablet profile has margins of its own, the new ones apply on leaving it
+        if self.desktop_profile.is_none() && (config.layout.margin_horizontal != old.layout.margin_horizontal
+            || config.layout.margin_vertical != old.layout.margin_vertical) {
+            self.margin_horizontal = config.layout.margin_horizontal;
+            self.margin_vertical = config.layout.margin_vertical;
+            self.apply_all_windows(false);
+        }
+        // Caps only decide where the next window goes; rows already past one stay as they are
+        self.row_limit = config.layout.row_limit;
+        self.max_row_width = Self::row_width_units(config.layout.max_row_width);
+        self.limit_policy = config.layout.limit_policy;
+        if config.layout.visible_rows != old.layout.visible_rows {
+            self.visible_rows = config.layout.visible_rows;
+            self.recalculate_positions_for_new_resolution();
+            self.apply_all_windows(false);
+        }
+        println!("Applied the edited config file");
+    }
+    
+    // Called from the config timer too; rows only change appearance when an entry of
+    // the schedule starts or ends
+    fn check_schedule(&mut self) {
+        let alphas = schedule::row_alphas(&self.config.schedule);
+        if alphas != self.row_alphas {
+            self.row_alphas = alphas;
+            self.reapply_transparency();
+        }
+    }
+    
+    // Called from the config timer too: show the next of rotation.rows once the current
+    // one has had its time. Any input holds rotation back until the user leaves again.
+    fn check_rotation(&mut self) {
+        let rotation = &self.config.rotation;
+        if rotation.rows.is_empty() {
+            return;
+        }
+        if idle::user_idle_time() < Duration::from_secs(rotation.resume_after_secs) {
+            // A full interval on the row the user left it at once rotation resumes
+            self.rotated_at = Instant::now();
+            return;
+        }
+        if self.rotated_at.elapsed() < Duration::from_secs(rotation.interval_secs) {
+            return;
+        }
+        self.rotated_at = Instant::now();
+        let next = rotation.rows.iter()
+            .position(|&row| row == self.current_row)
+            .map_or(0, |index| (index + 1) % rotation.rows.len());
+        let row = rotation.rows[next];
+        if row != self.current_row {
+            self.scroll_to_row(row);
+        }
+    }
+    
+    // After the rules changed; placement stays as it is until a window is added again
+    fn reapply_rule_transparency(&mut self) {
+        let hwnds: Vec<HWND> = self.windows.values().map(|w| w.hwnd)
+            .chain(self.floating_windows.values().map(|f| f.hwnd))
+            .collect();
+        self.alpha_overrides = hwnds.iter()
+            .filter_map(|&hwnd| Some((hwnd.0, rules::lookup(&self.rules, hwnd)?.transparency?)))
+            .collect();
+        self.reapply_transparency();
+    }
+    
+    // Checked here rather than in the keyboard hook, which must not wait on the process lookup
+    fn update_passthrough(&self, hwnd: HWND) {
+        let passes = self.config.passes_through(hwnd);
+        PASSTHROUGH_WINDOW.store(if passes { hwnd.0 } else { 0 }, Ordering::Relaxed);
+    }
+    
     fn adjust_margins(&mut self, delta: i32) {
         self.margin_horizontal = (self.margin_horizontal as i32 + delta).clamp(0, 200) as i32;
         self.margin_vertical = (self.margin_vertical as i32 + delta * 2).clamp(0, 200) as i32;
@@ -1936,12 +4379,487 @@ impl RibbonTiler {
     }
     
     fn cycle_fps(&mut self) {
-        self.animation_fps = match self.animation_fps {
+        let fps = match self.animation_fps.load(Ordering::Relaxed) {
             60 => 90,
             90 => 120,
             120 => 144,
             _ => 60,
         };
+        self.animation_fps.store(fps, Ordering::Relaxed);
+        self.fps_chosen = true;
+        osd::show(&format!("Animation {} FPS", fps), fps as f32 / 144.0);
+        
+        let mut prefs = Prefs::load();
+        prefs.animation_fps = Some(fps);
+        prefs.save();
+    }
+    
+    // Put a window at a given slot of a row, adopting it first if needed
+    fn place_window(&mut self, hwnd: HWND, row: i32, slot: usize, size: TileSize) {
+        // The launcher already waited for the window, so no adoption delay
+        if !self.windows.contains_key(&hwnd.0) {
+            self.adopt_window(hwnd);
+        }
+        let Some(window) = self.windows.get_mut(&hwnd.0) else {
+            return;
+        };
+        // Already there, as most tiles are when a layout is applied over itself
+        let in_place = self.rows.get(&row).is_some_and(|order| {
+            order.get(slot.min(order.len().saturating_sub(1))) == Some(&hwnd.0)
+        });
+        if in_place && window.position.size == size && window.position.width.is_none() {
+            return;
+        }
+        window.position.set_size(size);
+        
+        self.settle_scroll();
+        self.detach_from_row(hwnd.0);
+        let order = self.rows.entry(row).or_default();
+        order.insert(slot.min(order.len()), hwnd.0);
+        self.layout_rows();
+        self.apply_all_windows(true);
+        self.needs_ribbon_recalc = true;
+    }
+    
+    // Take a tile out of the ribbon where it stands, with its own window styles back,
+    // and float it. Pressed again, it returns to the slot it left.
+    fn toggle_float(&mut self, hwnd: HWND) {
+        if let Some(floated) = self.floated.remove(&hwnd.0) {
+            if let Some(floating) = self.floating_windows.remove(&hwnd.0) {
+                layered::restore(hwnd, floating.original_layering);
+            }
+            self.place_window(hwnd, floated.row, floated.slot, floated.size);
+            if let Some(window) = self.windows.get_mut(&hwnd.0) {
+                window.position.width = floated.width;
+            }
+            return;
+        }
+        
+        if self.is_frozen(hwnd.0) {
+            return;
+        }
+        let Some(window) = self.windows.get(&hwnd.0).cloned() else {
+            // Not a tile; floating windows get tiled like with Win+Shift+T
+            self.floating_windows.remove(&hwnd.0);
+            self.declined.remove(&hwnd.0);
+            self.adopt_window(hwnd);
+            return;
+        };
+        
+        self.settle_scroll();
+        let row = window.position.row;
+        let slot = self.rows.get(&row).and_then(|order| order.iter().position(|&h| h == hwnd.0)).unwrap_or(usize::MAX);
+        self.floated.insert(hwnd.0, FloatedTile {
+            row,
+            slot,
+            size: window.position.size,
+            width: window.position.width,
+        });
+        
+        self.lift_tile(&window);
+        self.track_floating_window(hwnd);
+    }
+    
+    // Take a tile out of the ribbon without moving it, giving it its own window styles back
+    fn lift_tile(&mut self, window: &ManagedWindow) {
+        let hwnd = window.hwnd;
+        if let Some(next) = self.promote_stack(hwnd.0) {
+            self.apply_window_position(HWND(next), false);
+        }
+        self.detach_from_row(hwnd.0);
+        self.windows.remove(&hwnd.0);
+        if self.dry_run {
+            Self::log_dry_run(hwnd, "would leave the ribbon where it is");
+        } else {
+            // Styles only; the window stays where it is on screen
+            let style = WINDOW_STYLE(window.original_style.0 & !(WS_MAXIMIZE.0 | WS_MINIMIZE.0));
+            unsafe {
+                SetWindowLongW(hwnd, GWL_STYLE, style.0 as i32);
+                SetWindowLongW(hwnd, GWL_EXSTYLE, window.original_ex_style.0 as i32);
+                layered::restore(hwnd, window.original_layering);
+                SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_FRAMECHANGED).ok();
+            }
+        }
+        
+        self.needs_ribbon_recalc = true;
+        if !*self.animation_running.lock().unwrap() {
+            self.recalculate_ribbon();
+        }
+    }
+    
+    // Put the layout on the clipboard as startup layout JSON
+    fn copy_layout(&mut self) {
+        let apps = launch::capture(self);
+        if apps.is_empty() {
+            osd::show("No tiles to copy", 0.0);
+            return;
+        }
+        if clipboard::set_text(self.main_hwnd, &launch::to_text(&apps)) {
+            println!("Copied the layout of {} tiles to the clipboard", apps.len());
+            osd::show(&format!("Layout of {} tiles copied", apps.len()), 0.0);
+        }
+    }
+    
+    // Apply a layout copied with copy-layout, here or on another machine
+    fn import_layout(&mut self) {
+        if self.dry_run {
+            println!("[dry run] Layout on the clipboard not applied");
+            return;
+        }
+        let apps = clipboard::get_text(self.main_hwnd).and_then(|text| launch::parse(&text).ok());
+        match apps {
+            Some(apps) if !apps.is_empty() => {
+                osd::show(&format!("Applying a layout of {} tiles", apps.len()), 0.0);
+                launch::apply(apps, false);
+            },
+            _ => osd::show("No layout on the clipboard", 0.0),
+        }
+    }
+    
+    // Make the focused window the scratchpad, in place of any other; pressed on the
+    // scratchpad itself, it becomes an ordinary window again
+    fn set_scratchpad(&mut self, hwnd: HWND) {
+        if hwnd.0 == 0 || self.is_frozen(hwnd.0) {
+            return;
+        }
+        if self.dry_run {
+            Self::log_dry_run(hwnd, "would become the scratchpad");
+            return;
+        }
+        if let Some(pad) = self.scratchpad.take() {
+            let released = pad.hwnd == hwnd;
+            pad.release();
+            if released {
+                // Left alone by auto-tile, like a window untiled by hand
+                self.declined.insert(hwnd.0);
+                osd::show("Scratchpad released", 0.0);
+                return;
+            }
+        }
+        
+        if let Some(window) = self.windows.get(&hwnd.0).cloned() {
+            self.settle_scroll();
+            self.lift_tile(&window);
+        }
+        if let Some(floating) = self.floating_windows.remove(&hwnd.0) {
+            layered::restore(hwnd, floating.original_layering);
+        }
+        self.floated.remove(&hwnd.0);
+        self.rule_floated.remove(&hwnd.0);
+        self.delayed_adoptions.remove(&hwnd.0);
+        
+        let mut pad = scratchpad::Scratchpad::new(hwnd);
+        pad.show(self.scratchpad_rect(), self.animates());
+        self.scratchpad = Some(pad);
+        self.start_animation_timer();
+        osd::show(&format!("{} is the scratchpad", window_info::window_title(hwnd)), 0.0);
+    }
+    
+    // Drop the scratchpad down, or focus it if it is down but behind something; up again
+    // when it already has focus
+    fn toggle_scratchpad(&mut self) {
+        let rect = self.scratchpad_rect();
+        let animate = self.animates();
+        let Some(pad) = self.scratchpad.as_mut() else {
+            osd::show("No scratchpad; set one with Win+Ctrl+Shift+`", 0.0);
+            return;
+        };
+        if self.dry_run {
+            Self::log_dry_run(pad.hwnd, if pad.shown { "would hide the scratchpad" } else { "would show the scratchpad" });
+            return;
+        }
+        if !pad.shown {
+            pad.show(rect, animate);
+        } else if unsafe { GetForegroundWindow() } != pad.hwnd {
+            focus::focus_window(pad.hwnd);
+            return;
+        } else {
+            pad.hide(rect, animate);
+        }
+        self.start_animation_timer();
+    }
+    
+    // Across the top of the active viewport, layout.scratchpad_height_percent of the screen tall
+    fn scratchpad_rect(&self) -> RECT {
+        let height = self.monitor_height * self.config.layout.scratchpad_height_percent as i32 / 100;
+        RECT {
+            left: self.viewport.left + self.margin_horizontal / 2,
+            top: 0,
+            right: self.viewport.left + self.viewport.width - self.margin_horizontal / 2,
+            bottom: height,
+        }
+    }
+    
+    // The tile a window is stacked behind
+    fn stack_of(&self, hwnd: isize) -> Option<isize> {
+        self.stacks.iter()
+            .find(|(_, members)| members.iter().any(|w| w.hwnd.0 == hwnd))
+            .map(|(&front, _)| front)
+    }
+    
+    // Stacked windows wait past the right edge of the desktop, where no display shows
+    // them but they keep their taskbar button
+    fn park_stacked(&self, hwnd: HWND) {
+        if self.dry_run {
+            Self::log_dry_run(hwnd, "would be parked off-screen");
+            return;
+        }
+        unsafe {
+            let right = GetSystemMetrics(SM_XVIRTUALSCREEN) + GetSystemMetrics(SM_CXVIRTUALSCREEN);
+            SetWindowPos(hwnd, HWND_BOTTOM, right, GetSystemMetrics(SM_YVIRTUALSCREEN), 0, 0,
+                SWP_NOSIZE | SWP_NOACTIVATE).ok();
+        }
+    }
+    
+    // Stack a tile behind its left neighbour, along with anything stacked behind it.
+    // Only the front window of a stack holds a slot.
+    fn stack_window(&mut self, hwnd: HWND) {
+        let Some(row) = self.windows.get(&hwnd.0).map(|w| w.position.row) else {
+            return;
+        };
+        let front = self.rows.get(&row)
+            .and_then(|order| order.iter().position(|&h| h == hwnd.0).filter(|&index| index > 0).map(|index| order[index - 1]));
+        let Some(front) = front else {
+            return;
+        };
+        
+        self.settle_scroll();
+        self.detach_from_row(hwnd.0);
+        let Some(mut window) = self.windows.remove(&hwnd.0) else {
+            return;
+        };
+        window.animation = None;
+        self.hung.remove(&hwnd.0);
+        self.fullscreen.remove(&hwnd.0);
+        let behind = self.stacks.remove(&hwnd.0).unwrap_or_default();
+        let members = self.stacks.entry(front).or_default();
+        members.push(window);
+        members.extend(behind);
+        self.park_stacked(hwnd);
+        
+        self.layout_rows();
+        self.apply_all_windows(true);
+        self.needs_ribbon_recalc = true;
+        focus::focus_window(HWND(front));
+    }
+    
+    // Take the front window out of its stack into a slot of its own to the right; the
+    // next window of the stack takes its place
+    fn unstack_window(&mut self, hwnd: HWND) {
+        if !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        self.settle_scroll();
+        let Some(next) = self.promote_stack(hwnd.0) else {
+            return;
+        };
+        if let Some(order) = self.rows.values_mut().find(|order| order.contains(&next)) {
+            let index = order.iter().position(|&h| h == next).unwrap_or(order.len());
+            order.insert(index + 1, hwnd.0);
+        }
+        
+        self.layout_rows();
+        self.apply_all_windows(true);
+        self.needs_ribbon_recalc = true;
+    }
+    
+    // Show the next window of a stack in its slot
+    fn cycle_stack(&mut self, hwnd: HWND) {
+        let Some(next) = self.stacks.get(&hwnd.0).and_then(|members| members.first()).map(|w| w.hwnd) else {
+            return;
+        };
+        self.raise_stacked(next);
+        focus::focus_window(next);
+    }
+    
+    // Swap a stacked window with the front of its stack. The ones after it in the
+    // stack come up next, and the old front goes to the back.
+    fn raise_stacked(&mut self, hwnd: HWND) {
+        let Some(front) = self.stack_of(hwnd.0) else {
+            return;
+        };
+        let Some(mut old) = self.windows.remove(&front) else {
+            return;
+        };
+        let mut members = self.stacks.remove(&front).unwrap_or_default();
+        let index = members.iter().position(|w| w.hwnd == hwnd).unwrap_or(0);
+        let mut window = members.remove(index);
+        window.position = old.position;
+        let before: Vec<ManagedWindow> = members.drain(..index).collect();
+        old.animation = None;
+        members.push(old);
+        members.extend(before);
+        
+        for order in self.rows.values_mut() {
+            order.iter_mut().filter(|h| **h == front).for_each(|h| *h = hwnd.0);
+        }
+        self.windows.insert(hwnd.0, window);
+        self.stacks.insert(hwnd.0, members);
+        self.hung.remove(&front);
+        self.fullscreen.remove(&front);
+        
+        self.apply_window_position(hwnd, false);
+        self.park_stacked(HWND(front));
+    }
+    
+    // The next live window of a tile's stack takes over its slot and width, leaving the
+    // tile itself out of the rows. Returns the new front.
+    fn promote_stack(&mut self, front: isize) -> Option<isize> {
+        let mut members = self.stacks.remove(&front)?;
+        members.retain(|w| unsafe { IsWindow(w.hwnd).as_bool() });
+        if members.is_empty() {
+            return None;
+        }
+        let mut next = members.remove(0);
+        if let Some(window) = self.windows.get(&front) {
+            next.position = window.position;
+        }
+        let hwnd = next.hwnd.0;
+        for order in self.rows.values_mut() {
+            order.iter_mut().filter(|h| **h == front).for_each(|h| *h = hwnd);
+        }
+        self.windows.insert(hwnd, next);
+        if !members.is_empty() {
+            self.stacks.insert(hwnd, members);
+        }
+        Some(hwnd)
+    }
+    
+    // Bring a tile to the middle of the view instead of scrolling to it. It trades
+    // places with the tile there, or joins the current row if nothing is there.
+    fn pull_window(&mut self, hwnd: HWND) {
+        self.check_monitor_dimensions();
+        self.clean_closed_windows();
+        
+        if !self.windows.contains_key(&hwnd.0) {
+            return;
+        }
+        
+        self.settle_scroll();
+        self.layout_rows();
+        
+        let center = self.ribbon_offset_target + RIBBON_SPAN / 2;
+        let here = self.rows.get(&self.current_row).into_iter().flatten()
+            .copied()
+            .find(|h| self.windows.get(h).is_some_and(|w| {
+                w.position.x <= center && center < w.position.x + self.get_tile_width(&w.position)
+            }));
+        
+        match here {
+            Some(other) if other == hwnd.0 => {}
+            Some(other) => {
+                for order in self.rows.values_mut() {
+                    for slot in order.iter_mut() {
+                        if *slot == hwnd.0 {
+                            *slot = other;
+                        } else if *slot == other {
+                            *slot = hwnd.0;
+                        }
+                    }
+                }
+            }
+            None => {
+                self.detach_from_row(hwnd.0);
+                self.insert_into_row(hwnd.0, self.current_row, center);
+            }
+        }
+        self.layout_rows();
+        
+        // A wider tile may not fit where the old one was
+        let on_screen = self.windows.get(&hwnd.0).is_some_and(|w| self.is_on_screen(&w.position));
+        if on_screen {
+            self.apply_all_windows(true);
+        } else {
+            self.scroll_to_window(hwnd);
+        }
+        self.needs_ribbon_recalc = true;
+        
+        focus::focus_window(hwnd);
+    }
+    
+    // Focus the tile next to `hwnd` on that side, scrolling only as far as it takes to
+    // bring it fully into view. Up and down pick the tile of the nearest row with tiles
+    // whose centre is closest. From a window that isn't a tile, focus goes to the tile
+    // nearest the middle of the screen.
+    fn focus_neighbour(&mut self, hwnd: HWND, direction: Direction) {
+        self.check_monitor_dimensions();
+        self.clean_closed_windows();
+        self.settle_scroll();
+        self.layout_rows();
+        
+        let Some(pos) = self.windows.get(&hwnd.0).map(|w| w.position) else {
+            self.focus_visible_window();
+            return;
+        };
+        let target = match direction {
+            Direction::Left | Direction::Right => {
+                let order = self.rows.get(&pos.row).map(Vec::as_slice).unwrap_or_default();
+                let index = order.iter().position(|&h| h == hwnd.0);
+                match (direction, index) {
+                    (Direction::Left, Some(index)) if index > 0 => order.get(index - 1).copied(),
+                    (Direction::Right, Some(index)) => order.get(index + 1).copied(),
+                    _ => None,
+                }
+            }
+            Direction::Up | Direction::Down => {
+                let rows = self.get_active_rows();
+                let row = match direction {
+                    Direction::Up => rows.iter().rev().find(|&&row| row < pos.row),
+                    _ => rows.iter().find(|&&row| row > pos.row),
+                };
+                let center = pos.x + self.get_tile_width(&pos) / 2;
+                row.and_then(|row| self.rows.get(row))
+                    .and_then(|order| order.iter()
+                        .filter_map(|h| self.windows.get(h))
+                        .min_by_key(|w| (w.position.x + self.get_tile_width(&w.position) / 2 - center).abs())
+                        .map(|w| w.hwnd.0))
+            }
+        };
+        let Some(target) = target else {
+            return;
+        };
+        
+        self.reveal_tile(target);
+        focus::focus_window(HWND(target));
+    }
+    
+    // Scroll the least distance that shows the whole tile; no scroll when it already is
+    fn reveal_tile(&mut self, hwnd: isize) {
+        let Some(pos) = self.windows.get(&hwnd).map(|w| w.position) else {
+            return;
+        };
+        let rows_on_screen = (self.monitor_height / self.row_height.max(1)).max(1);
+        let row = if pos.row < self.current_row {
+            pos.row
+        } else if pos.row >= self.current_row + rows_on_screen {
+            pos.row - rows_on_screen + 1
+        } else {
+            self.current_row
+        };
+        
+        let width = self.get_tile_width(&pos);
+        let max_x = self.windows.values()
+            .map(|w| w.position.x + self.get_tile_width(&w.position))
+            .max()
+            .unwrap_or(0);
+        let offset = if pos.x < self.ribbon_offset_target {
+            pos.x
+        } else if pos.x + width > self.ribbon_offset_target + RIBBON_SPAN {
+            pos.x + width - RIBBON_SPAN
+        } else {
+            self.ribbon_offset_target
+        }.clamp(0, (max_x - RIBBON_SPAN).max(0));
+        
+        if row == self.current_row && offset == self.ribbon_offset_target {
+            return;
+        }
+        self.current_row = row;
+        self.vertical_offset_target = row * self.row_height;
+        self.ribbon_offset_target = offset;
+        // The scroll landing focuses this tile rather than the one scroll_focus would pick
+        self.row_focus_pending = Some(hwnd);
+        self.start_scroll_animation();
     }
     
     fn scroll_to_window(&mut self, hwnd: HWND) {
@@ -1951,21 +4869,20 @@ impl RibbonTiler {
             // Extract values before mutable operations
             let window_row = window.position.row;
             let window_x = window.position.x;
-            let window_size = window.position.size;
+            let window_width = self.get_tile_width(&window.position);
             
             // Set both vertical and horizontal targets
             self.current_row = window_row;
             self.vertical_offset_target = window_row * self.row_height;
             
             // Center the window horizontally
-            let window_width = self.get_tile_width(&window_size);
-            let center_offset = window_x + window_width / 2 - self.monitor_width / 2;
+            let center_offset = window_x + window_width / 2 - RIBBON_SPAN / 2;
             
             let max_x = self.windows.values()
-                .map(|w| w.position.x + self.get_tile_width(&w.position.size))
+                .map(|w| w.position.x + self.get_tile_width(&w.position))
                 .max()
                 .unwrap_or(0);
-            let max_offset = (max_x - self.monitor_width).max(0);
+            let max_offset = (max_x - RIBBON_SPAN).max(0);
             
             self.ribbon_offset_target = center_offset.clamp(0, max_offset);
             
@@ -1983,11 +4900,167 @@ enum Direction {
     Right,
 }
 
-// Global state
-static TILER: Mutex<Option<Arc<Mutex<RibbonTiler>>>> = Mutex::new(None);
-static MAIN_HWND: AtomicUsize = AtomicUsize::new(0);
-static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
-
+// Global state
+static TILER: Mutex<Option<Arc<Mutex<RibbonTiler>>>> = Mutex::new(None);
+static MAIN_HWND: AtomicUsize = AtomicUsize::new(0);
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Set once every tile has been handed back, so the restore runs only once
+static RELEASED: AtomicBool = AtomicBool::new(false);
+// Set when a new instance has taken the windows over; exit without restoring them
+static HANDED_OFF: AtomicBool = AtomicBool::new(false);
+// Foreground window whose app passes every shortcut through, or 0
+static PASSTHROUGH_WINDOW: AtomicIsize = AtomicIsize::new(0);
+// The message window's own STATIC window procedure, replaced to catch session end
+static STATIC_WNDPROC: AtomicUsize = AtomicUsize::new(0);
+static IDLE_SUSPENDED: AtomicBool = AtomicBool::new(false);
+// Set while a convertible is in tablet posture with tablet.auto on
+static TABLET_POSTURE: AtomicBool = AtomicBool::new(false);
+// Virtual key of the row-switch arrow while it is held down, else 0
+static ROW_KEY_HELD: AtomicU32 = AtomicU32::new(0);
+// Modifier bits of the switch-window combo while the switcher is open, else 0
+static SWITCH_MODIFIERS: AtomicU32 = AtomicU32::new(0);
+// A combo with a hold binding, from its press until it is released
+static HELD_COMBO: Mutex<Option<HeldCombo>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+struct HeldCombo {
+    vk: u32,
+    tap: Option<TilerCommand>,   // The combo's own binding, run on a short press
+    hold: TilerCommand,
+    since: u32,                  // Hook timestamp of the press, in ms
+    hwnd: HWND,                  // Foreground window at the press
+    fired: bool,                 // The hold command already ran
+}
+
+// Repeats of the key last pressed for a command with repeat acceleration
+static KEY_REPEAT: Mutex<Option<KeyRepeat>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+struct KeyRepeat {
+    vk: u32,
+    last: u32,       // Hook timestamp the command last ran, in ms
+    wait: u32,       // Time until it may run again
+    repeated: bool,  // Past the first delay
+}
+
+// Hand a command to the message loop; safe to call from any thread
+fn post_tiler_command(command: TilerCommand, hwnd: HWND) -> bool {
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value == 0 {
+        return false;
+    }
+    
+    unsafe {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            WM_TILER_COMMAND,
+            WPARAM(command as usize),
+            LPARAM(hwnd.0)
+        ).is_ok()
+    }
+}
+
+// Play the macro saved as "1" to "9", or with 0 start stepping through one already queued
+fn post_macro(slot: usize) -> bool {
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value == 0 {
+        return false;
+    }
+    
+    unsafe {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            WM_TILER_MACRO,
+            WPARAM(slot),
+            LPARAM(0)
+        ).is_ok()
+    }
+}
+
+// Ask the tiler to place a window at a row slot; row, slot and size share the wparam
+fn post_tiler_place(hwnd: HWND, row: i32, slot: usize, size: TileSize) -> bool {
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value == 0 {
+        return false;
+    }
+    
+    let packed = ((row as i16 as u16 as usize) << 16)
+        | ((slot.min(0x1FFF)) << 3)
+        | size.index();
+    unsafe {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            WM_TILER_PLACE,
+            WPARAM(packed),
+            LPARAM(hwnd.0)
+        ).is_ok()
+    }
+}
+
+fn post_switch(action: usize) {
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value != 0 {
+        unsafe {
+            PostMessageW(
+                HWND(main_hwnd_value as isize),
+                WM_TILER_SWITCH,
+                WPARAM(action),
+                LPARAM(0)
+            ).ok();
+        }
+    }
+}
+
+// Modifier bit a key stands for, or 0 for any other key
+fn modifier_bit(vk: u32) -> u32 {
+    let vk = VIRTUAL_KEY(vk as u16);
+    let bit = if [VK_LWIN, VK_RWIN].contains(&vk) {
+        config::WIN
+    } else if [VK_CONTROL, VK_LCONTROL, VK_RCONTROL].contains(&vk) {
+        config::CTRL
+    } else if [VK_MENU, VK_LMENU, VK_RMENU].contains(&vk) {
+        config::ALT
+    } else if [VK_SHIFT, VK_LSHIFT, VK_RSHIFT].contains(&vk) {
+        config::SHIFT
+    } else {
+        0
+    };
+    bit as u32
+}
+
+fn post_row_pick(action: usize) {
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value != 0 {
+        unsafe {
+            PostMessageW(
+                HWND(main_hwnd_value as isize),
+                WM_TILER_ROW_PICK,
+                WPARAM(action),
+                LPARAM(0)
+            ).ok();
+        }
+    }
+}
+
+// Wake the tiler from idle suspension; called from hooks on user input
+fn post_resume_if_idle() {
+    if !IDLE_SUSPENDED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value != 0 {
+        unsafe {
+            PostMessageW(
+                HWND(main_hwnd_value as isize),
+                WM_TILER_RESUME,
+                WPARAM(0),
+                LPARAM(0)
+            ).ok();
+        }
+    }
+}
+
 // Keyboard hook procedure
 unsafe extern "system" fn keyboard_hook_proc(
     code: i32,
@@ -1998,7 +5071,38 @@ unsafe extern "system" fn keyboard_hook_proc(
         return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
     }
 
+    if wparam.0 as u32 == WM_KEYUP || wparam.0 as u32 == WM_SYSKEYUP {
+        let kb_struct = *(lparam.0 as *const KBDLLHOOKSTRUCT);
+        if ROW_KEY_HELD.compare_exchange(kb_struct.vkCode, 0, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            post_row_pick(ROW_PICK_COMMIT);
+        }
+        // Letting go of any of the switcher's modifiers picks the highlighted tile
+        let modifiers = SWITCH_MODIFIERS.load(Ordering::Relaxed);
+        if modifiers & modifier_bit(kb_struct.vkCode) != 0 {
+            SWITCH_MODIFIERS.store(0, Ordering::Relaxed);
+            post_switch(SWITCH_COMMIT);
+        }
+        
+        KEY_REPEAT.lock().unwrap().take_if(|repeat| repeat.vk == kb_struct.vkCode);
+        
+        let mut held = HELD_COMBO.lock().unwrap();
+        if let Some(pressed) = held.take_if(|pressed| pressed.vk == kb_struct.vkCode) {
+            let command = if pressed.fired {
+                None
+            } else if kb_struct.time.wrapping_sub(pressed.since) >= config::hold_ms() {
+                Some(pressed.hold)
+            } else {
+                pressed.tap
+            };
+            if let Some(command) = command {
+                post_tiler_command(command, pressed.hwnd);
+            }
+        }
+    }
+
     if wparam.0 as u32 == WM_KEYDOWN || wparam.0 as u32 == WM_SYSKEYDOWN {
+        post_resume_if_idle();
+        
         let kb_struct = *(lparam.0 as *const KBDLLHOOKSTRUCT);
         let vk_code = VIRTUAL_KEY(kb_struct.vkCode as u16);
         
@@ -2006,84 +5110,116 @@ unsafe extern "system" fn keyboard_hook_proc(
             return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
         }
         
+        if vk_code == VK_ESCAPE && SWITCH_MODIFIERS.swap(0, Ordering::Relaxed) != 0 {
+            post_switch(SWITCH_CANCEL);
+            return LRESULT(1);
+        }
+        
         let ctrl = GetAsyncKeyState(VK_CONTROL.0 as i32) & 0x8000u16 as i16 != 0;
         let alt = GetAsyncKeyState(VK_MENU.0 as i32) & 0x8000u16 as i16 != 0;
         let win = GetAsyncKeyState(VK_LWIN.0 as i32) & 0x8000u16 as i16 != 0 
             || GetAsyncKeyState(VK_RWIN.0 as i32) & 0x8000u16 as i16 != 0;
         let shift = GetAsyncKeyState(VK_SHIFT.0 as i32) & 0x8000u16 as i16 != 0;
 
-        if !win {
+        let modifiers = [(ctrl, config::CTRL), (alt, config::ALT), (shift, config::SHIFT), (win, config::WIN)]
+            .iter()
+            .filter(|(held, _)| *held)
+            .fold(0u8, |bits, (_, bit)| bits | bit);
+        if modifiers == 0 {
             return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
         }
 
-        let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
-        if main_hwnd_value == 0 {
+        if MAIN_HWND.load(Ordering::Relaxed) == 0 {
             return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
         }
-        let main_hwnd = HWND(main_hwnd_value as isize);
         
         let hwnd = GetForegroundWindow();
         
-        let mut command: Option<TilerCommand> = None;
-        
-        if win && !ctrl && !shift && !alt {
-            match vk_code {
-                VK_UP => command = Some(TilerCommand::PanUp),
-                VK_DOWN => command = Some(TilerCommand::PanDown),
-                VK_LEFT => command = Some(TilerCommand::PanLeft),
-                VK_RIGHT => command = Some(TilerCommand::PanRight),
-                VIRTUAL_KEY(0x43) => command = Some(TilerCommand::ForceRecalc), // C for Clean
-                _ => {},
-            }
-        }
-
-        if win && ctrl && !shift && !alt {
-            match vk_code {
-                VK_LEFT => command = Some(TilerCommand::ResizeLeft),
-                VK_RIGHT => command = Some(TilerCommand::ResizeRight),
-                _ => {},
-            }
+        // Apps listed in input.passthrough get every combo as if Thymeline weren't running
+        if hwnd.0 != 0 && PASSTHROUGH_WINDOW.load(Ordering::Relaxed) == hwnd.0 {
+            return CallNextHookEx(HHOOK::default(), code, wparam, lparam);
         }
-
-        if win && ctrl && shift && !alt {
-            match vk_code {
-                VK_UP => command = Some(TilerCommand::MoveUp),
-                VK_DOWN => command = Some(TilerCommand::MoveDown),
-                VK_LEFT => command = Some(TilerCommand::MoveLeft),
-                VK_RIGHT => command = Some(TilerCommand::MoveRight),
-                _ => {},
+        
+        let combo = config::KeyCombo { modifiers, vk: vk_code.0 };
+        
+        // A combo that also has a hold binding waits to see how long the key stays down.
+        // Repeats of the press don't run anything until the hold time is up; the first
+        // one after that runs the hold command, and a release before then the tap one.
+        if let Some(hold) = config::hold_binding(combo) {
+            let mut held = HELD_COMBO.lock().unwrap();
+            match held.as_mut() {
+                Some(pressed) if pressed.vk == kb_struct.vkCode => {
+                    if !pressed.fired && kb_struct.time.wrapping_sub(pressed.since) >= config::hold_ms() {
+                        pressed.fired = true;
+                        post_tiler_command(pressed.hold, pressed.hwnd);
+                    }
+                }
+                _ => {
+                    *held = Some(HeldCombo {
+                        vk: kb_struct.vkCode,
+                        tap: config::binding(combo),
+                        hold,
+                        since: kb_struct.time,
+                        hwnd,
+                        fired: false,
+                    });
+                }
             }
+            return LRESULT(1);
         }
         
-        if win && !ctrl && !shift && !alt {
-            match vk_code {
-                VK_OEM_PLUS | VK_ADD => command = Some(TilerCommand::IncreaseTransparency),
-                VK_OEM_MINUS | VK_SUBTRACT => command = Some(TilerCommand::DecreaseTransparency),
-                VIRTUAL_KEY(0x53) => command = Some(TilerCommand::ScrollToWindow), // S
-                VIRTUAL_KEY(0x4D) => command = Some(TilerCommand::IncreaseMargins), // M
-                VIRTUAL_KEY(0x4E) => command = Some(TilerCommand::DecreaseMargins), // N
-                VIRTUAL_KEY(0x46) => command = Some(TilerCommand::CycleFPS), // F for FPS
-                _ => {},
+        if let Some(command) = config::binding(combo) {
+            // Held keys of accelerating commands skip repeats that come too soon
+            if let Some(acceleration) = config::repeat(command) {
+                let mut repeat = KEY_REPEAT.lock().unwrap();
+                match repeat.as_mut() {
+                    Some(repeat) if repeat.vk == kb_struct.vkCode => {
+                        if kb_struct.time.wrapping_sub(repeat.last) < repeat.wait {
+                            return LRESULT(1);
+                        }
+                        let next = if repeat.repeated {
+                            (repeat.wait as f32 * acceleration.speedup) as u32
+                        } else {
+                            acceleration.interval_ms
+                        };
+                        repeat.last = kb_struct.time;
+                        repeat.wait = next.max(acceleration.min_interval_ms);
+                        repeat.repeated = true;
+                    }
+                    _ => {
+                        *repeat = Some(KeyRepeat {
+                            vk: kb_struct.vkCode,
+                            last: kb_struct.time,
+                            wait: acceleration.delay_ms,
+                            repeated: false,
+                        });
+                    }
+                }
             }
-        }
-
-        if win && shift && !ctrl && !alt {
-            match vk_code {
-                VK_OEM_PLUS | VK_ADD => command = Some(TilerCommand::IncreaseTransparency),
-                VK_OEM_MINUS | VK_SUBTRACT => command = Some(TilerCommand::DecreaseTransparency),
-                VIRTUAL_KEY(0x54) => command = Some(TilerCommand::AddWindow), // T
-                VIRTUAL_KEY(0x52) => command = Some(TilerCommand::RemoveWindow), // R
-                _ => {},
+            
+            // The first press pans straight away; key repeats while it is held pick a row
+            // further on instead, and releasing the key goes there
+            if matches!(command, TilerCommand::PanUp | TilerCommand::PanDown)
+                && ROW_KEY_HELD.swap(kb_struct.vkCode, Ordering::Relaxed) == kb_struct.vkCode
+            {
+                post_row_pick(if matches!(command, TilerCommand::PanUp) { ROW_PICK_UP } else { ROW_PICK_DOWN });
+                return LRESULT(1);
+            }
+            
+            // Opens the switcher, or moves it on while it is open; see SWITCH_MODIFIERS
+            if matches!(command, TilerCommand::SwitchWindow) {
+                SWITCH_MODIFIERS.store(modifiers as u32, Ordering::Relaxed);
+                post_switch(SWITCH_NEXT);
+                return LRESULT(1);
             }
+            
+            post_tiler_command(command, hwnd);
+            return LRESULT(1);
         }
         
-        if let Some(cmd) = command {
-            PostMessageW(
-                main_hwnd,
-                WM_TILER_COMMAND,
-                WPARAM(cmd as usize),
-                LPARAM(hwnd.0)
-            ).ok();
+        // Win+Alt+1..9 plays the macro saved under that digit
+        if modifiers == config::WIN | config::ALT && (0x31..=0x39).contains(&vk_code.0) {
+            post_macro((vk_code.0 - 0x30) as usize);
             return LRESULT(1);
         }
     }
@@ -2091,31 +5227,202 @@ unsafe extern "system" fn keyboard_hook_proc(
     CallNextHookEx(HHOOK::default(), code, wparam, lparam)
 }
 
-// Handler for Ctrl+C signal
+// Foreground change hook - forwards to the message loop so state is only touched on the main thread
+unsafe extern "system" fn foreground_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if event != EVENT_SYSTEM_FOREGROUND || id_object != OBJID_WINDOW.0 {
+        return;
+    }
+    
+    post_resume_if_idle();
+    
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value != 0 {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            WM_TILER_FOREGROUND,
+            WPARAM(0),
+            LPARAM(hwnd.0)
+        ).ok();
+    }
+}
+
+// Windows appearing (for --auto-tile), going away (destroyed, hidden or minimized) and
+// coming back from minimized, forwarded like foreground changes so tiles are cleaned up
+// without polling
+unsafe extern "system" fn window_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    let message = match event {
+        EVENT_OBJECT_SHOW => WM_TILER_SHOWN,
+        EVENT_SYSTEM_MINIMIZEEND => WM_TILER_UNMINIMIZED,
+        EVENT_OBJECT_NAMECHANGE => WM_TILER_RENAMED,
+        EVENT_OBJECT_DESTROY | EVENT_OBJECT_HIDE | EVENT_SYSTEM_MINIMIZESTART => WM_TILER_GONE,
+        _ => return,
+    };
+    
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value != 0 {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            message,
+            WPARAM(event as usize),
+            LPARAM(hwnd.0)
+        ).ok();
+    }
+}
+
+// Ask the message loop to restore everything and exit; safe to call from any thread
+fn request_shutdown() -> bool {
+    SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
+    
+    let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
+    if main_hwnd_value == 0 {
+        return false;
+    }
+    unsafe {
+        PostMessageW(
+            HWND(main_hwnd_value as isize),
+            WM_TILER_SHUTDOWN,
+            WPARAM(0),
+            LPARAM(0)
+        ).is_ok()
+    }
+}
+
+// Handler for Ctrl+C and for the console window closing
 extern "system" fn console_handler(ctrl_type: u32) -> BOOL {
     const CTRL_C_EVENT: u32 = 0;
-    if ctrl_type == CTRL_C_EVENT {
-        SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
-        
-        let main_hwnd_value = MAIN_HWND.load(Ordering::Relaxed);
-        if main_hwnd_value != 0 {
-            unsafe {
-                PostMessageW(
-                    HWND(main_hwnd_value as isize),
-                    WM_TILER_SHUTDOWN,
-                    WPARAM(0),
-                    LPARAM(0)
-                ).ok();
+    const CTRL_CLOSE_EVENT: u32 = 2;
+    match ctrl_type {
+        CTRL_C_EVENT => {
+            request_shutdown();
+            BOOL::from(true)
+        }
+        // Closing the --console window ends the process once this returns, so give the
+        // main loop a moment to restore the windows first
+        CTRL_CLOSE_EVENT => {
+            request_shutdown();
+            let deadline = Instant::now() + Duration::from_secs(4);
+            while !RELEASED.load(Ordering::Relaxed) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
             }
+            BOOL::from(true)
+        }
+        _ => BOOL::from(false),
+    }
+}
+
+// Restore every window, at most once. Uses try_lock because a session-end message
+// can arrive while the main thread is inside the tiler, waiting on a SendMessage
+// to another app; the caller falls back to a later chance in that case.
+fn release_all() -> bool {
+    if RELEASED.load(Ordering::Relaxed) {
+        return true;
+    }
+    let Ok(tiler_slot) = TILER.try_lock() else {
+        return false;
+    };
+    let Some(tiler_arc) = tiler_slot.as_ref() else {
+        return false;
+    };
+    let Ok(mut tiler) = tiler_arc.try_lock() else {
+        return false;
+    };
+    tiler.shutdown();
+    RELEASED.store(true, Ordering::Relaxed);
+    true
+}
+
+// Windows sends WM_QUERYENDSESSION/WM_ENDSESSION straight to the window procedure, not
+// through the message loop. Apps save their placement as they close on logoff, so the
+// tiles have to be restored here, before any of them are told the session is ending.
+unsafe extern "system" fn message_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_QUERYENDSESSION => {
+            if release_all() {
+                SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
+                PostMessageW(hwnd, WM_TILER_SHUTDOWN, WPARAM(0), LPARAM(0)).ok();
+            }
+            LRESULT(1)
+        }
+        WM_ENDSESSION if wparam.0 != 0 => {
+            // Last chance: the process is ended once this returns
+            release_all();
+            LRESULT(0)
+        }
+        WM_TILER_TRAY => {
+            tray::on_message(hwnd, lparam);
+            LRESULT(0)
+        }
+        // Sent for every settings change, tablet posture (ConvertibleSlateMode) among them;
+        // the tiler compares the posture with what it last saw
+        WM_SETTINGCHANGE => {
+            PostMessageW(hwnd, WM_TILER_POSTURE, WPARAM(0), LPARAM(0)).ok();
+            let original: WNDPROC = std::mem::transmute(STATIC_WNDPROC.load(Ordering::Relaxed));
+            CallWindowProcW(original, hwnd, msg, wparam, lparam)
+        }
+        // Handled in the message loop, where the tiler can be locked
+        _ if msg == shellhook::message() => {
+            PostMessageW(hwnd, WM_TILER_SHELL, wparam, lparam).ok();
+            LRESULT(0)
+        }
+        _ if msg == tray::taskbar_created() => {
+            tray::add(hwnd);
+            LRESULT(0)
+        }
+        _ => {
+            let original: WNDPROC = std::mem::transmute(STATIC_WNDPROC.load(Ordering::Relaxed));
+            CallWindowProcW(original, hwnd, msg, wparam, lparam)
         }
-        
-        BOOL::from(true)
-    } else {
-        BOOL::from(false)
     }
 }
 
 fn main() -> Result<()> {
+    let options = Options::from_args();
+    
+    if options.subcommand.is_some() {
+        console::attach_parent();
+    } else {
+        console::setup(options.console, options.handoff);
+    }
+    
+    match &options.subcommand {
+        Some(Subcommand::PwshModule(path)) => {
+            if let Err(e) = pwsh::write_module(path.as_deref()) {
+                println!("Failed to write PowerShell module: {}", e);
+            }
+            return Ok(());
+        }
+        Some(Subcommand::RegisterUri(register)) => {
+            let result = if *register { uri::register() } else { uri::unregister() };
+            match result {
+                Ok(()) if *register => println!("thymeline: links now run commands, e.g. thymeline://pan-right"),
+                Ok(()) => println!("thymeline: links are no longer handled"),
+                Err(e) => println!("Failed to update the thymeline: link handler: {}", e),
+            }
+            return Ok(());
+        }
+        Some(subcommand) => std::process::exit(client::run(subcommand)),
+        None => {}
+    }
+    
     println!("╔═══════════════════════════════════════════════╗");
     println!("║     THYMELINE TILER v3.1 - Smooth Scrolling    ║");
     println!("╚═══════════════════════════════════════════════╝");
@@ -2127,31 +5434,108 @@ fn main() -> Result<()> {
     println!("  Win+Ctrl+Left/Right  Toggle between full/half width");
     println!("\n🔀 WINDOW MOVEMENT:");
     println!("  Win+Ctrl+Shift+Arrow Move windows (up/down changes rows)");
+    println!("  Win+Shift+Left/Right Send window to the adjacent monitor");
     println!("\n📍 RIBBON NAVIGATION:");
     println!("  Win+Left/Right       Pan horizontally through ribbon");
     println!("  Win+Up/Down          Switch between rows");
     println!("  Win+S                Scroll to current window");
+    if !options.viewport_splits.is_empty() {
+        println!("  Win+Shift+V          Switch to the next viewport");
+    }
     println!("\n🎨 APPEARANCE:");
     println!("  Win+Plus             Increase transparency");
     println!("  Win+Minus            Decrease transparency");
     println!("  Win+M                Increase margins (+5H/+10V)");
     println!("  Win+N                Decrease margins (-5H/-10V)");
-    println!("  Win+F                Cycle FPS (60→90→120→144)");
-    println!("\nPress Ctrl+C to exit gracefully");
+    println!("  Win+F                Cycle FPS (60→90→120→144, starts at display rate)");
+    println!("  Win+Shift+P          Mirror viewport to second monitor");
+    if options.ipc {
+        println!("\n🔌 SCRIPTING:");
+        println!("  JSON commands on {}", ipc::pipe_name());
+    }
+    if !options.hot_corners.is_empty() {
+        println!("\n🖱️ HOT CORNERS:");
+        for (corner, command) in &options.hot_corners {
+            println!("  {:?} → {}", corner, command.name());
+        }
+    }
+    println!("\n🖥️ DISPLAYS (for display preferences):");
+    for display in monitor::all() {
+        let bounds = display.rcMonitor;
+        println!("  {:<20} {}x{}{}", monitor::device_name(monitor::handle_of(&display)).unwrap_or_default(),
+            bounds.right - bounds.left, bounds.bottom - bounds.top,
+            if monitor::is_primary(&display) { ", primary" } else { "" });
+    }
+    if let Some(tracking) = &options.time_tracking {
+        println!("\n⏱  TIME TRACKING:");
+        println!("  Logging focused windows to {}", tracking.path.display());
+    }
+    if options.console {
+        println!("\nPress Ctrl+C to exit gracefully");
+    } else {
+        println!("\nQuit from the notification area icon, or with `windowpots quit`");
+    }
 
     unsafe {
+        // Take the windows over from the running instance once it has let go of them.
+        // It holds the instance guard until it has exited, so that is waited for here.
+        let handoff = if options.handoff {
+            match client::take_over() {
+                Ok(state) => Some(state),
+                Err(e) => {
+                    println!("Warning: Handoff failed, starting fresh: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let instance_guard = if options.handoff {
+            session::InstanceGuard::acquire_after(HANDOFF_GUARD_TIMEOUT_MS)
+        } else {
+            session::InstanceGuard::acquire()
+        };
+        let Some(_instance_guard) = instance_guard else {
+            println!("\nThymeline is already running in this session");
+            return Ok(());
+        };
+        
+        CoInitializeEx(None, COINIT_APARTMENTTHREADED).ok()?;
+        
         if SetConsoleCtrlHandler(Some(console_handler), true).is_err() {
             println!("Warning: Failed to set console handler");
         }
         
-        let tiler = Arc::new(Mutex::new(RibbonTiler::new()));
+        let config = Config::load(&options.config_overrides);
+        config.apply_keybindings();
+        let tiler = Arc::new(Mutex::new(RibbonTiler::new(&options, &config)));
+        if let Some(state) = handoff {
+            handoff::resume(&mut tiler.lock().unwrap(), state);
+        }
         
         {
-            let tiler_lock = tiler.lock().unwrap();
+            let mut tiler_lock = tiler.lock().unwrap();
+            tiler_lock.update_passthrough(GetForegroundWindow());
+            tray::add(tiler_lock.main_hwnd);
+            tiler_lock.check_posture();
+            tiler_lock.update_tray();
             MAIN_HWND.store(tiler_lock.main_hwnd.0 as usize, Ordering::Relaxed);
+            let original = SetWindowLongPtrW(tiler_lock.main_hwnd, GWLP_WNDPROC, message_window_proc as *const () as isize);
+            STATIC_WNDPROC.store(original as usize, Ordering::Relaxed);
+            shellhook::register(tiler_lock.main_hwnd);
         }
         
         *TILER.lock().unwrap() = Some(tiler.clone());
+        
+        if options.ipc {
+            ipc::start_server();
+        }
+        if config.remote.enabled {
+            remote::start_server(config.remote.clone());
+        }
+
+        // The hook runs on this thread
+        priority::apply(options.input_priority);
 
         let hook = SetWindowsHookExW(
             WH_KEYBOARD_LL,
@@ -2159,6 +5543,72 @@ fn main() -> Result<()> {
             GetModuleHandleW(None)?,
             0,
         )?;
+        
+        let foreground_hook = SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            None,
+            Some(foreground_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        // Destroy, show and hide are adjacent event ids; minimize start and end and title
+        // changes are far off
+        let object_hook = SetWinEventHook(
+            EVENT_OBJECT_DESTROY,
+            EVENT_OBJECT_HIDE,
+            None,
+            Some(window_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        let minimize_hook = SetWinEventHook(
+            EVENT_SYSTEM_MINIMIZESTART,
+            EVENT_SYSTEM_MINIMIZEEND,
+            None,
+            Some(window_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        let name_hook = SetWinEventHook(
+            EVENT_OBJECT_NAMECHANGE,
+            EVENT_OBJECT_NAMECHANGE,
+            None,
+            Some(window_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+
+        // A handoff carries the layout over itself
+        if options.restore_layout && !options.handoff {
+            if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                restore::restore(&mut tiler_arc.lock().unwrap());
+            }
+        }
+
+        // After a handoff the layout's apps are already running and placed
+        if let Some(path) = options.startup_layout.as_ref().filter(|_| !options.handoff) {
+            match launch::load(path) {
+                Ok(apps) => launch::start(apps),
+                Err(e) => println!("Warning: Failed to read startup layout {}: {}", path.display(), e),
+            }
+        }
+
+        if options.idle_minutes > 0 {
+            SetTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), idle::IDLE_TIMER_ID, idle::IDLE_CHECK_INTERVAL_MS, None);
+        }
+
+        SetTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), HUNG_CHECK_TIMER_ID, HUNG_CHECK_INTERVAL_MS, None);
+        SetTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), config::CONFIG_TIMER_ID, config::CONFIG_POLL_MS, None);
+
+        let mut hot_corners = HotCorners::new(options.hot_corners.clone(), Duration::from_millis(options.hot_corner_dwell_ms));
+        if !options.hot_corners.is_empty() {
+            SetTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), hotcorner::HOT_CORNER_TIMER_ID, hotcorner::HOT_CORNER_POLL_MS, None);
+        }
 
         let mut msg = MSG::default();
         loop {
@@ -2173,47 +5623,17 @@ fn main() -> Result<()> {
                         tiler.update_animations();
                     }
                 }
-            } else if msg.message == WM_TILER_RECALC {
-                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
-                    if let Ok(mut tiler) = tiler_arc.lock() {
-                        tiler.clean_closed_windows();
-                        
-                        if tiler.needs_ribbon_recalc {
-                            let now = Instant::now();
-                            if now.duration_since(tiler.last_ribbon_recalc).as_millis() > 500 {
-                                tiler.recalculate_ribbon();
-                            }
-                        }
-                    }
-                }
             } else if msg.message == WM_TILER_COMMAND {
                 if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
                     if let Ok(mut tiler) = tiler_arc.lock() {
                         let command_value = msg.wParam.0 as u32;
                         let hwnd = HWND(msg.lParam.0);
                         
-                        let command = match command_value {
-                            0 => TilerCommand::PanLeft,
-                            1 => TilerCommand::PanRight,
-                            2 => TilerCommand::PanUp,
-                            3 => TilerCommand::PanDown,
-                            4 => TilerCommand::ResizeLeft,
-                            5 => TilerCommand::ResizeRight,
-                            6 => TilerCommand::MoveUp,
-                            7 => TilerCommand::MoveDown,
-                            8 => TilerCommand::MoveLeft,
-                            9 => TilerCommand::MoveRight,
-                            10 => TilerCommand::AddWindow,
-                            14 => TilerCommand::IncreaseTransparency,
-                            15 => TilerCommand::DecreaseTransparency,
-                            17 => TilerCommand::ScrollToWindow,
-                            18 => TilerCommand::IncreaseMargins,
-                            19 => TilerCommand::DecreaseMargins,
-                            20 => TilerCommand::RemoveWindow,
-                            21 => TilerCommand::CycleFPS,
-                            22 => TilerCommand::ForceRecalc,
-                            _ => continue,
+                        let Some(command) = TilerCommand::from_u32(command_value) else {
+                            continue;
                         };
+                        tiler.macros.record(command);
+                        tiler.stats.record_command(command);
                         
                         match command {
                             TilerCommand::PanLeft | TilerCommand::PanRight | 
@@ -2234,6 +5654,175 @@ fn main() -> Result<()> {
                         }
                     }
                 }
+            } else if msg.message == WM_TIMER && msg.wParam.0 == idle::IDLE_TIMER_ID {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.check_idle();
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TIMER && msg.wParam.0 == HUNG_CHECK_TIMER_ID {
+                if IDLE_SUSPENDED.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.check_hung();
+                        tiler.check_sharing();
+                        tiler.update_tray();
+                        restore::save(&mut tiler);
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TIMER && msg.wParam.0 == hotcorner::HOT_CORNER_TIMER_ID {
+                // A tap near a screen corner, on the Start button say, moves the cursor there
+                if TABLET_POSTURE.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if let Some(command) = hot_corners.poll() {
+                    post_tiler_command(command, GetForegroundWindow());
+                }
+                continue;
+            } else if msg.message == WM_TILER_MACRO {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        if msg.wParam.0 != 0 {
+                            tiler.play_macro(Some(&msg.wParam.0.to_string()));
+                        } else if tiler.macros.is_playing() {
+                            SetTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), macros::MACRO_TIMER_ID, macros::STEP_INTERVAL_MS, None);
+                        }
+                    }
+                }
+            } else if msg.message == WM_TIMER && msg.wParam.0 == macros::MACRO_TIMER_ID {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        // Each step runs against whatever has focus by then, as a keypress would
+                        match tiler.macros.next_step() {
+                            Some(command) => {
+                                post_tiler_command(command, GetForegroundWindow());
+                            }
+                            None => {
+                                KillTimer(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize), macros::MACRO_TIMER_ID).ok();
+                            }
+                        }
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TIMER && msg.wParam.0 == demo::DEMO_TIMER_ID {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.advance_demo();
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TIMER && msg.wParam.0 == config::CONFIG_TIMER_ID {
+                if IDLE_SUSPENDED.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.check_config();
+                        tiler.check_schedule();
+                        tiler.check_rotation();
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TILER_RESUME {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.resume_from_idle();
+                    }
+                }
+            } else if msg.message == WM_TILER_FOREGROUND {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.on_foreground_changed(HWND(msg.lParam.0));
+                    }
+                }
+            } else if msg.message == WM_TILER_SHOWN {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.auto_tile_window(HWND(msg.lParam.0));
+                    }
+                }
+            } else if msg.message == WM_TILER_UNMINIMIZED {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.end_show_desktop(HWND(msg.lParam.0));
+                    }
+                }
+            } else if msg.message == WM_TIMER && msg.wParam.0 == SHOW_DESKTOP_TIMER_ID {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.settle_minimized();
+                    }
+                }
+                continue;
+            } else if msg.message == WM_TILER_RENAMED {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.on_title_changed(HWND(msg.lParam.0));
+                    }
+                }
+            } else if msg.message == WM_TILER_SHELL {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.on_shell_event(msg.wParam.0 as u32, HWND(msg.lParam.0));
+                    }
+                }
+            } else if msg.message == WM_TILER_GONE {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.on_window_gone(HWND(msg.lParam.0), msg.wParam.0 as u32);
+                    }
+                }
+            } else if msg.message == WM_TILER_PLACE {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        let packed = msg.wParam.0;
+                        let row = ((packed >> 16) & 0xFFFF) as u16 as i16 as i32;
+                        let slot = (packed >> 3) & 0x1FFF;
+                        let size = TileSize::WIDTHS.get(packed & 0x7).copied().unwrap_or(TileSize::Half);
+                        tiler.place_window(HWND(msg.lParam.0), row, slot, size);
+                    }
+                }
+            } else if msg.message == WM_TILER_ROW_PICK {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        match msg.wParam.0 {
+                            ROW_PICK_UP => tiler.step_row_pick(Direction::Up),
+                            ROW_PICK_DOWN => tiler.step_row_pick(Direction::Down),
+                            _ => tiler.commit_row_pick(),
+                        }
+                    }
+                }
+            } else if msg.message == WM_TILER_SWITCH {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        match msg.wParam.0 {
+                            SWITCH_NEXT => tiler.step_switcher(),
+                            SWITCH_COMMIT => tiler.end_switcher(true),
+                            _ => tiler.end_switcher(false),
+                        }
+                    }
+                }
+            } else if msg.message == WM_TILER_POSTURE {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        tiler.check_posture();
+                    }
+                }
+            } else if msg.message == WM_TILER_DRAG {
+                if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                    if let Ok(mut tiler) = tiler_arc.lock() {
+                        let pixels = msg.lParam.0 as i32;
+                        match msg.wParam.0 {
+                            grabstrip::DRAG_BEGIN => tiler.begin_drag(),
+                            grabstrip::DRAG_MOVE => tiler.drag_ribbon(pixels),
+                            _ => tiler.end_drag(pixels),
+                        }
+                    }
+                }
             } else if msg.message == WM_TILER_SHUTDOWN {
                 break;
             }
@@ -2242,12 +5831,28 @@ fn main() -> Result<()> {
             DispatchMessageW(&msg);
         }
 
-        if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
-            if let Ok(mut tiler) = tiler_arc.lock() {
-                tiler.shutdown();
+        if !RELEASED.swap(true, Ordering::Relaxed) {
+            if let Some(tiler_arc) = TILER.lock().unwrap().as_ref() {
+                if let Ok(mut tiler) = tiler_arc.lock() {
+                    if HANDED_OFF.load(Ordering::Relaxed) {
+                        handoff::release(&mut tiler);
+                    } else {
+                        tiler.shutdown();
+                    }
+                }
             }
         }
         
+        tray::remove();
+        shellhook::deregister(HWND(MAIN_HWND.load(Ordering::Relaxed) as isize));
+        if !foreground_hook.is_invalid() {
+            UnhookWinEvent(foreground_hook);
+        }
+        for hook in [object_hook, minimize_hook, name_hook] {
+            if !hook.is_invalid() {
+                UnhookWinEvent(hook);
+            }
+        }
         UnhookWindowsHookEx(hook)?;
         println!("\nThymeline shut down gracefully");
     }
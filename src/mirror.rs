@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Once;
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        Graphics::Dwm::*,
+        Graphics::Gdi::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::monitor;
+
+const MIRROR_CLASS: PCWSTR = w!("ThymelineMirror");
+static REGISTER_CLASS: Once = Once::new();
+
+// Presentation mirror: live DWM thumbnails of the tiles on the primary display,
+// scaled onto another monitor so an audience follows the ribbon
+pub struct Mirror {
+    hwnd: HWND,
+    target: RECT,
+    thumbnails: HashMap<isize, isize>, // Source hwnd -> DWM thumbnail handle
+}
+
+unsafe extern "system" fn mirror_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+impl Mirror {
+    // None when there is no second monitor to present on
+    pub fn open() -> Option<Self> {
+        let target = monitor::all().into_iter()
+            .find(|m| !monitor::is_primary(m))
+            .map(|m| m.rcMonitor)?;
+
+        unsafe {
+            let instance = GetModuleHandleW(None).unwrap_or_default();
+
+            REGISTER_CLASS.call_once(|| {
+                let class = WNDCLASSW {
+                    lpfnWndProc: Some(mirror_wndproc),
+                    hInstance: instance.into(),
+                    hbrBackground: HBRUSH(GetStockObject(BLACK_BRUSH).0),
+                    lpszClassName: MIRROR_CLASS,
+                    ..Default::default()
+                };
+                RegisterClassW(&class);
+            });
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                MIRROR_CLASS,
+                w!("Thymeline Mirror"),
+                WS_POPUP | WS_VISIBLE,
+                target.left,
+                target.top,
+                target.right - target.left,
+                target.bottom - target.top,
+                HWND::default(),
+                HMENU::default(),
+                instance,
+                None,
+            );
+
+            if hwnd.0 == 0 {
+                return None;
+            }
+
+            Some(Self {
+                hwnd,
+                target,
+                thumbnails: HashMap::new(),
+            })
+        }
+    }
+
+    // Re-sync thumbnails with where the tiles currently sit on the source display
+    pub fn update(&mut self, tiles: &[HWND], source_width: i32, source_height: i32) {
+        if source_width <= 0 || source_height <= 0 {
+            return;
+        }
+
+        // Uniform scale, letterboxed when the aspect ratios differ
+        let target_width = self.target.right - self.target.left;
+        let target_height = self.target.bottom - self.target.top;
+        let scale = (target_width as f32 / source_width as f32)
+            .min(target_height as f32 / source_height as f32);
+        let offset_x = (target_width - (source_width as f32 * scale) as i32) / 2;
+        let offset_y = (target_height - (source_height as f32 * scale) as i32) / 2;
+
+        let mut shown = HashSet::new();
+
+        for &hwnd in tiles {
+            let mut rect = RECT::default();
+            unsafe {
+                if GetWindowRect(hwnd, &mut rect).is_err() {
+                    continue;
+                }
+            }
+
+            if rect.right <= 0 || rect.left >= source_width || rect.bottom <= 0 || rect.top >= source_height {
+                continue;
+            }
+
+            let thumbnail = match self.thumbnails.get(&hwnd.0) {
+                Some(&thumbnail) => thumbnail,
+                None => match unsafe { DwmRegisterThumbnail(self.hwnd, hwnd) } {
+                    Ok(thumbnail) => {
+                        self.thumbnails.insert(hwnd.0, thumbnail);
+                        thumbnail
+                    }
+                    Err(_) => continue,
+                },
+            };
+
+            let properties = DWM_THUMBNAIL_PROPERTIES {
+                dwFlags: DWM_TNP_RECTDESTINATION | DWM_TNP_VISIBLE | DWM_TNP_OPACITY,
+                rcDestination: RECT {
+                    left: offset_x + (rect.left as f32 * scale) as i32,
+                    top: offset_y + (rect.top as f32 * scale) as i32,
+                    right: offset_x + (rect.right as f32 * scale) as i32,
+                    bottom: offset_y + (rect.bottom as f32 * scale) as i32,
+                },
+                opacity: 255,
+                fVisible: BOOL::from(true),
+                ..Default::default()
+            };
+            unsafe {
+                DwmUpdateThumbnailProperties(thumbnail, &properties).ok();
+            }
+            shown.insert(hwnd.0);
+        }
+
+        // Drop thumbnails for tiles that scrolled off the source display
+        self.thumbnails.retain(|hwnd, thumbnail| {
+            if shown.contains(hwnd) {
+                true
+            } else {
+                unsafe {
+                    DwmUnregisterThumbnail(*thumbnail).ok();
+                }
+                false
+            }
+        });
+    }
+}
+
+impl Drop for Mirror {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, thumbnail) in self.thumbnails.drain() {
+                DwmUnregisterThumbnail(thumbnail).ok();
+            }
+            DestroyWindow(self.hwnd).ok();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use windows::core::PCWSTR;
+use windows::Win32::{
+    Foundation::*,
+    Graphics::Gdi::*,
+    UI::WindowsAndMessaging::MONITORINFOF_PRIMARY,
+};
+
+use crate::Direction;
+
+unsafe extern "system" fn collect_monitor(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<(HMONITOR, MONITORINFO)>);
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if GetMonitorInfoW(monitor, &mut info).as_bool() {
+        monitors.push((monitor, info));
+    }
+    BOOL::from(true)
+}
+
+// Every attached display with its handle, in enumeration order
+fn enumerate() -> Vec<(HMONITOR, MONITORINFO)> {
+    let mut monitors: Vec<(HMONITOR, MONITORINFO)> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(collect_monitor),
+            LPARAM(&mut monitors as *mut _ as isize),
+        );
+    }
+    monitors
+}
+
+// Every attached display, in enumeration order
+pub fn all() -> Vec<MONITORINFO> {
+    enumerate().into_iter().map(|(_, info)| info).collect()
+}
+
+// Work area of every display but the primary, by monitor handle
+pub fn secondary_work_areas() -> HashMap<isize, RECT> {
+    enumerate().into_iter()
+        .filter(|(_, info)| !is_primary(info))
+        .map(|(handle, info)| (handle.0, info.rcWork))
+        .collect()
+}
+
+pub fn primary_handle() -> isize {
+    unsafe { MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY).0 }
+}
+
+// Display showing most of a window, the primary if none does
+pub fn of_window(hwnd: HWND) -> isize {
+    unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTOPRIMARY).0 }
+}
+
+// Bounds of a display, which unlike its handle are the same from one run to the next
+pub fn bounds(handle: isize) -> Option<RECT> {
+    enumerate().into_iter().find(|(h, _)| h.0 == handle).map(|(_, info)| info.rcMonitor)
+}
+
+// Display with exactly these bounds, if one is still attached
+pub fn with_bounds(bounds: &RECT) -> Option<isize> {
+    enumerate().into_iter().find(|(_, info)| info.rcMonitor == *bounds).map(|(h, _)| h.0)
+}
+
+// Handle of a display found through all(), for keying its ribbon
+pub fn handle_of(monitor: &MONITORINFO) -> isize {
+    unsafe { MonitorFromRect(&monitor.rcMonitor, MONITOR_DEFAULTTONEAREST).0 }
+}
+
+// Info of a display along with its device name
+fn info_ex(handle: isize) -> Option<MONITORINFOEXW> {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    unsafe { GetMonitorInfoW(HMONITOR(handle), &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) }
+        .as_bool()
+        .then_some(info)
+}
+
+// Device name of a display without the \\.\ prefix, e.g. DISPLAY2
+pub fn device_name(handle: isize) -> Option<String> {
+    let info = info_ex(handle)?;
+    let len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
+    let name = String::from_utf16_lossy(&info.szDevice[..len]);
+    Some(name.trim_start_matches(r"\\.\").to_string())
+}
+
+// Attached display a config names: "primary", or a device name with or without its
+// \\.\ prefix, in any case
+pub fn named(name: &str) -> Option<isize> {
+    if name.eq_ignore_ascii_case("primary") {
+        return Some(primary_handle());
+    }
+    let name = name.trim_start_matches(r"\\.\");
+    enumerate().into_iter()
+        .map(|(handle, _)| handle.0)
+        .find(|&handle| device_name(handle).is_some_and(|device| device.eq_ignore_ascii_case(name)))
+}
+
+// Refresh rate of a display in Hz, None when the driver only reports its default
+pub fn refresh_rate(handle: isize) -> Option<u32> {
+    let info = info_ex(handle)?;
+    unsafe {
+        let mut mode = DEVMODEW {
+            dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+            ..Default::default()
+        };
+        if !EnumDisplaySettingsW(PCWSTR(info.szDevice.as_ptr()), ENUM_CURRENT_SETTINGS, &mut mode).as_bool() {
+            return None;
+        }
+        // 0 and 1 both mean "hardware default"
+        match mode.dmDisplayFrequency {
+            0 | 1 => None,
+            hz => Some(hz),
+        }
+    }
+}
+
+pub fn is_primary(monitor: &MONITORINFO) -> bool {
+    monitor.dwFlags & MONITORINFOF_PRIMARY != 0
+}
+
+// Display containing the centre of a rect, falling back to the primary
+pub fn containing(rect: &RECT) -> Option<MONITORINFO> {
+    let x = (rect.left + rect.right) / 2;
+    let y = (rect.top + rect.bottom) / 2;
+    let monitors = all();
+    monitors.iter()
+        .find(|m| {
+            let bounds = &m.rcMonitor;
+            x >= bounds.left && x < bounds.right && y >= bounds.top && y < bounds.bottom
+        })
+        .or_else(|| monitors.iter().find(|m| is_primary(m)))
+        .copied()
+}
+
+// Nearest display beside `from` in a horizontal direction
+pub fn adjacent(from: &MONITORINFO, direction: Direction) -> Option<MONITORINFO> {
+    let monitors = all();
+    match direction {
+        Direction::Left => monitors.into_iter()
+            .filter(|m| m.rcMonitor.right <= from.rcMonitor.left)
+            .max_by_key(|m| m.rcMonitor.right),
+        Direction::Right => monitors.into_iter()
+            .filter(|m| m.rcMonitor.left >= from.rcMonitor.right)
+            .min_by_key(|m| m.rcMonitor.left),
+        _ => None,
+    }
+}
+
+// Map a rect from one area onto another proportionally. Unlike scale_rect nothing is
+// clamped, so a tile scrolled off one display stays off the other.
+pub fn map_rect(rect: &RECT, from: &RECT, to: &RECT) -> RECT {
+    let scale_x = (to.right - to.left) as f32 / (from.right - from.left).max(1) as f32;
+    let scale_y = (to.bottom - to.top) as f32 / (from.bottom - from.top).max(1) as f32;
+    let x = |x: i32| to.left + ((x - from.left) as f32 * scale_x) as i32;
+    let y = |y: i32| to.top + ((y - from.top) as f32 * scale_y) as i32;
+
+    RECT {
+        left: x(rect.left),
+        top: y(rect.top),
+        right: x(rect.right),
+        bottom: y(rect.bottom),
+    }
+}
+
+// Map a rect from one work area onto another, keeping its relative position and size
+pub fn scale_rect(rect: &RECT, from: &RECT, to: &RECT) -> RECT {
+    let scale_x = (to.right - to.left) as f32 / (from.right - from.left).max(1) as f32;
+    let scale_y = (to.bottom - to.top) as f32 / (from.bottom - from.top).max(1) as f32;
+
+    let left = to.left + ((rect.left - from.left) as f32 * scale_x) as i32;
+    let top = to.top + ((rect.top - from.top) as f32 * scale_y) as i32;
+    let width = ((rect.right - rect.left) as f32 * scale_x) as i32;
+    let height = ((rect.bottom - rect.top) as f32 * scale_y) as i32;
+
+    // Keep the whole window on the target display
+    let width = width.min(to.right - to.left);
+    let height = height.min(to.bottom - to.top);
+    let left = left.clamp(to.left, to.right - width);
+    let top = top.clamp(to.top, to.bottom - height);
+
+    RECT {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    }
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use windows::Win32::{
+    Foundation::RECT,
+    UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN},
+};
+
+use crate::compositor::rgba;
+use crate::overlay::{Layer, Overlay};
+
+const HIDE_AFTER_MS: u32 = 1200;
+
+// Set while nothing should pop up on screen, e.g. in presentation mode
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+// Size and placement in 96-DPI units
+const WIDTH: i32 = 320;
+const HEIGHT: i32 = 64;
+const BOTTOM_GAP: i32 = 120;
+
+// Short-lived on-screen readout with a level bar, e.g. while adjusting transparency.
+// Its composition surface is tied to the main thread, so it lives there rather than
+// on the tiler.
+struct Osd {
+    overlay: Overlay,
+    content: Rc<RefCell<(String, f32)>>,
+}
+
+impl Osd {
+    fn new() -> Option<Self> {
+        let content = Rc::new(RefCell::new((String::new(), 0.0_f32)));
+        let painted = content.clone();
+        let overlay = Overlay::new(Layer::Osd, true, Box::new(move |canvas| {
+            let (text, level) = &*painted.borrow();
+            let (w, h) = (canvas.width, canvas.height);
+
+            canvas.fill_rounded_rect(0.0, 0.0, w, h, 8.0, rgba(0x20, 0x20, 0x20, 0.9));
+            canvas.text(text, 0.0, 0.0, w, h - 20.0, 16.0, rgba(0xFF, 0xFF, 0xFF, 1.0));
+
+            let (left, right) = (16.0, w - 16.0);
+            canvas.fill_rect(left, h - 18.0, right, h - 12.0, rgba(0x50, 0x50, 0x50, 1.0));
+            let filled = left + (right - left) * level.clamp(0.0, 1.0);
+            canvas.fill_rect(left, h - 18.0, filled, h - 12.0, rgba(0x40, 0xA0, 0xE0, 1.0));
+        }))?;
+
+        Some(Self { overlay, content })
+    }
+
+    fn show(&mut self, text: &str, level: f32) {
+        *self.content.borrow_mut() = (text.to_string(), level);
+
+        let scale = self.overlay.scale();
+        let (screen_width, screen_height) = unsafe {
+            (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN))
+        };
+        let width = (WIDTH as f32 * scale) as i32;
+        let height = (HEIGHT as f32 * scale) as i32;
+        let left = (screen_width - width) / 2;
+        let top = screen_height - height - (BOTTOM_GAP as f32 * scale) as i32;
+
+        self.overlay.show_at(RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        });
+        self.overlay.hide_after(HIDE_AFTER_MS);
+    }
+}
+
+thread_local! {
+    static OSD: RefCell<Option<Osd>> = const { RefCell::new(None) };
+}
+
+// Show `text` above a bar filled to `level` (0..=1), hiding again shortly after the last call
+pub fn show(text: &str, level: f32) {
+    if MUTED.load(Ordering::Relaxed) {
+        return;
+    }
+    OSD.with(|osd| {
+        let mut osd = osd.borrow_mut();
+        if osd.is_none() {
+            *osd = Osd::new();
+        }
+        if let Some(osd) = osd.as_mut() {
+            osd.show(text, level);
+        }
+    });
+}
+
+pub fn close() {
+    OSD.with(|osd| *osd.borrow_mut() = None);
+}
+
+pub fn muted() -> bool {
+    MUTED.load(Ordering::Relaxed)
+}
+
+// Muting also takes down a readout already showing
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+    if muted {
+        close();
+    }
+}
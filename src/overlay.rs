@@ -0,0 +1,202 @@
+use std::sync::{Mutex, Once};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        System::LibraryLoader::GetModuleHandleW,
+        UI::HiDpi::GetDpiForWindow,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::compositor::{Canvas, Surface};
+
+// Shared plumbing for the tiler's own on-screen overlays (OSD today; borders, hints
+// and the like later). Every overlay is created, painted and hidden on the tiler's
+// main thread, so painting never races the layout and there is one render loop.
+// Content is drawn with Direct2D into a DirectComposition visual (see compositor).
+
+const OVERLAY_CLASS: PCWSTR = w!("ThymelineOverlay");
+static REGISTER_CLASS: Once = Once::new();
+
+// The main loop claims its own timer ids (1, 2 and 4) without checking the window, so stay clear of them
+const HIDE_TIMER_ID: usize = 3;
+
+// Stacking bands; overlays in a higher band are kept above lower ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    GrabStrip,
+    RowStrip,
+    Hud,
+    Switcher,
+    Osd,
+}
+
+// Live overlays and their bands, for restacking
+static STACK: Mutex<Vec<(isize, Layer)>> = Mutex::new(Vec::new());
+
+// Draws the overlay's content; called whenever it is shown or redrawn
+pub type Painter = Box<dyn Fn(&Canvas)>;
+
+// Gets the touch and pen messages (WM_POINTER*) of an overlay that isn't click-through
+pub type PointerHandler = fn(u32, WPARAM, LPARAM);
+
+pub struct Overlay {
+    hwnd: HWND,
+    layer: Layer,
+    surface: Surface,
+    painter: Painter,
+}
+
+unsafe extern "system" fn overlay_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_TIMER if wparam.0 == HIDE_TIMER_ID => {
+            KillTimer(hwnd, HIDE_TIMER_ID).ok();
+            ShowWindow(hwnd, SW_HIDE);
+            LRESULT(0)
+        }
+        WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP | WM_POINTERCAPTURECHANGED
+            if GetWindowLongPtrW(hwnd, GWLP_USERDATA) != 0 =>
+        {
+            let handler: PointerHandler = std::mem::transmute(GetWindowLongPtrW(hwnd, GWLP_USERDATA));
+            handler(msg, wparam, lparam);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+fn dpi(hwnd: HWND) -> f32 {
+    match unsafe { GetDpiForWindow(hwnd) } {
+        0 => 96.0,
+        dpi => dpi as f32,
+    }
+}
+
+impl Overlay {
+    pub fn new(layer: Layer, click_through: bool, painter: Painter) -> Option<Self> {
+        unsafe {
+            let instance = GetModuleHandleW(None).unwrap_or_default();
+
+            REGISTER_CLASS.call_once(|| {
+                let class = WNDCLASSW {
+                    lpfnWndProc: Some(overlay_wndproc),
+                    hInstance: instance.into(),
+                    lpszClassName: OVERLAY_CLASS,
+                    ..Default::default()
+                };
+                RegisterClassW(&class);
+            });
+
+            // Never activated, so an overlay can't take focus from the tiles. No redirection
+            // bitmap: the composition visual is the only thing drawn.
+            let mut ex_style = WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_NOREDIRECTIONBITMAP;
+            if click_through {
+                // Mouse input only passes through layered windows
+                ex_style |= WS_EX_LAYERED | WS_EX_TRANSPARENT;
+            }
+
+            let hwnd = CreateWindowExW(
+                ex_style,
+                OVERLAY_CLASS,
+                w!("Thymeline Overlay"),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                HWND::default(),
+                HMENU::default(),
+                instance,
+                None,
+            );
+            if hwnd.0 == 0 {
+                return None;
+            }
+
+            if click_through {
+                SetLayeredWindowAttributes(hwnd, COLORREF(0), 255, LWA_ALPHA).ok();
+            }
+
+            let Some(surface) = Surface::new(hwnd) else {
+                DestroyWindow(hwnd).ok();
+                return None;
+            };
+            STACK.lock().unwrap().push((hwnd.0, layer));
+
+            Some(Self { hwnd, layer, surface, painter })
+        }
+    }
+
+    // Screen rect in pixels; size it with scale() for the overlay's DPI
+    pub fn show_at(&mut self, rect: RECT) {
+        let width = (rect.right - rect.left).max(1) as u32;
+        let height = (rect.bottom - rect.top).max(1) as u32;
+        let painter = &self.painter;
+        if let Err(e) = self.surface.draw(width, height, dpi(self.hwnd), |canvas| painter(canvas)) {
+            println!("Warning: Failed to draw overlay: {}", e);
+        }
+        
+        unsafe {
+            SetWindowPos(
+                self.hwnd,
+                HWND_TOPMOST,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOACTIVATE | SWP_SHOWWINDOW,
+            ).ok();
+        }
+        restack(self.layer);
+    }
+
+    pub fn hide(&self) {
+        unsafe {
+            KillTimer(self.hwnd, HIDE_TIMER_ID).ok();
+            ShowWindow(self.hwnd, SW_HIDE);
+        }
+    }
+
+    pub fn hide_after(&self, ms: u32) {
+        unsafe {
+            SetTimer(self.hwnd, HIDE_TIMER_ID, ms, None);
+        }
+    }
+
+    // Touch and pen input on the overlay go to `handler`, which runs on the main thread
+    pub fn on_pointer(&self, handler: PointerHandler) {
+        unsafe {
+            SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, handler as *const () as isize);
+        }
+    }
+
+    // DPI relative to 96
+    pub fn scale(&self) -> f32 {
+        dpi(self.hwnd) / 96.0
+    }
+}
+
+// Put overlays in higher bands back above one that was just raised
+fn restack(raised: Layer) {
+    let mut above: Vec<(isize, Layer)> = STACK.lock().unwrap().iter()
+        .filter(|(_, layer)| *layer > raised)
+        .copied()
+        .collect();
+    above.sort_by_key(|(_, layer)| *layer);
+    for (hwnd, _) in above {
+        unsafe {
+            SetWindowPos(HWND(hwnd), HWND_TOPMOST, 0, 0, 0, 0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE).ok();
+        }
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        STACK.lock().unwrap().retain(|(hwnd, _)| *hwnd != self.hwnd.0);
+        unsafe {
+            DestroyWindow(self.hwnd).ok();
+        }
+    }
+}
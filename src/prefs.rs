@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::appdata;
+
+// %APPDATA%\Thymeline\prefs.json
+const FILE: &str = "prefs.json";
+
+// Settings changed from the keyboard at runtime that should survive a restart
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Prefs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_fps: Option<u64>,
+    // Apps found to render black when made transparent, kept opaque from then on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opaque_apps: Vec<String>,
+}
+
+impl Prefs {
+    // Missing or unreadable prefs fall back to defaults
+    pub fn load() -> Self {
+        appdata::load_json(FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        appdata::store_json(FILE, self);
+    }
+}
@@ -0,0 +1,74 @@
+use std::mem::size_of;
+use windows::Win32::System::{
+    Power::{SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED},
+    Threading::*,
+};
+
+// Scheduling priority of the main thread, which runs the keyboard hook
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputPriority {
+    Normal,
+    AboveNormal,
+    Highest,
+}
+
+impl InputPriority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(Self::Normal),
+            "above-normal" => Some(Self::AboveNormal),
+            "highest" => Some(Self::Highest),
+            _ => None,
+        }
+    }
+
+    fn thread_priority(self) -> THREAD_PRIORITY {
+        match self {
+            Self::Normal => THREAD_PRIORITY_NORMAL,
+            Self::AboveNormal => THREAD_PRIORITY_ABOVE_NORMAL,
+            Self::Highest => THREAD_PRIORITY_HIGHEST,
+        }
+    }
+}
+
+// Call from the main thread before installing the hooks. Windows puts background
+// processes without a focused window into efficiency mode (EcoQoS), which is exactly
+// what the tiler always looks like; opting out keeps hotkeys from waiting on a parked
+// core. Only the input thread gets a raised priority, and it sleeps in GetMessage,
+// so nothing spins.
+pub fn apply(input: InputPriority) {
+    unsafe {
+        let state = PROCESS_POWER_THROTTLING_STATE {
+            Version: PROCESS_POWER_THROTTLING_CURRENT_VERSION,
+            ControlMask: PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
+            StateMask: 0,
+        };
+        if SetProcessInformation(
+            GetCurrentProcess(),
+            ProcessPowerThrottling,
+            &state as *const _ as *const _,
+            size_of::<PROCESS_POWER_THROTTLING_STATE>() as u32,
+        ).is_err() {
+            println!("Warning: Failed to opt out of efficiency mode");
+        }
+
+        if SetThreadPriority(GetCurrentThread(), input.thread_priority()).is_err() {
+            println!("Warning: Failed to set input thread priority");
+        }
+    }
+}
+
+// Held by the animation thread while it ticks, so the system doesn't drop into a
+// low-power state partway through a slide. Per-thread, and released as soon as the
+// animation ends, so the tiler never keeps the machine awake.
+pub fn hold_awake() {
+    unsafe {
+        SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED);
+    }
+}
+
+pub fn release_awake() {
+    unsafe {
+        SetThreadExecutionState(ES_CONTINUOUS);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ipc;
+
+// PowerShell module talking to the IPC pipe; __COMMANDS__ and __PROTOCOL__ are filled in at generation time
+const MODULE_TEMPLATE: &str = r#"# Thymeline PowerShell module - generated by `windowpots pwsh-module`
+# Regenerate after upgrading Thymeline so the command list stays in sync.
+
+$script:ThymelineProtocolVersion = __PROTOCOL__
+$script:ThymelineCommands = @(__COMMANDS__)
+
+function Get-ThymelinePipeName {
+    $session = (Get-Process -Id $PID).SessionId
+    "thymeline-session$session"
+}
+
+function Invoke-ThymelineRequest {
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory)] [hashtable] $Request,
+        [int] $TimeoutMs = 2000
+    )
+
+    $pipe = New-Object System.IO.Pipes.NamedPipeClientStream('.', (Get-ThymelinePipeName), [System.IO.Pipes.PipeDirection]::InOut)
+    try {
+        $pipe.Connect($TimeoutMs)
+        $writer = New-Object System.IO.StreamWriter($pipe)
+        $writer.AutoFlush = $true
+        $reader = New-Object System.IO.StreamReader($pipe)
+
+        $writer.WriteLine(($Request | ConvertTo-Json -Compress -Depth 5))
+        $response = $reader.ReadLine() | ConvertFrom-Json
+        if ($response.type -eq 'error') {
+            throw "Thymeline: $($response.message) ($($response.code))"
+        }
+        $response
+    }
+    finally {
+        $pipe.Dispose()
+    }
+}
+
+function Get-ThymelineInfo {
+    [CmdletBinding()]
+    param()
+    Invoke-ThymelineRequest @{ type = 'hello'; protocol_version = $script:ThymelineProtocolVersion; client = 'powershell' }
+}
+
+function Get-ThymelineState {
+    [CmdletBinding()]
+    param()
+    Invoke-ThymelineRequest @{ type = 'state' }
+}
+
+function Get-ThymelineWindow {
+    [CmdletBinding()]
+    param([int] $Row, [string] $Title)
+    $windows = (Get-ThymelineState).windows
+    if ($PSBoundParameters.ContainsKey('Row')) {
+        $windows = $windows | Where-Object { $_.row -eq $Row }
+    }
+    if ($PSBoundParameters.ContainsKey('Title')) {
+        $windows = $windows | Where-Object { $_.title -like $Title }
+    }
+    $windows
+}
+
+function Get-ThymelineStaleWindow {
+    [CmdletBinding()]
+    param()
+    (Invoke-ThymelineRequest @{ type = 'stale' }).windows
+}
+
+function Get-ThymelineStats {
+    [CmdletBinding()]
+    param()
+    Invoke-ThymelineRequest @{ type = 'stats' }
+}
+
+function Stop-Thymeline {
+    [CmdletBinding()]
+    param()
+    Invoke-ThymelineRequest @{ type = 'quit' } | Out-Null
+}
+
+function Save-ThymelineMacro {
+    [CmdletBinding()]
+    param([Parameter(Mandatory, Position = 0)] [string] $Name)
+    Invoke-ThymelineRequest @{ type = 'save_macro'; name = $Name } | Out-Null
+}
+
+function Import-ThymelineLayout {
+    [CmdletBinding()]
+    param([Parameter(Mandatory, Position = 0)] [string] $Path)
+    $apps = @(Get-Content -Raw -LiteralPath $Path | ConvertFrom-Json)
+    Invoke-ThymelineRequest @{ type = 'apply_layout'; apps = $apps } | Out-Null
+}
+
+function Invoke-ThymelineMacro {
+    [CmdletBinding()]
+    param([Parameter(Position = 0)] [string] $Name)
+    $request = @{ type = 'play_macro' }
+    if ($PSBoundParameters.ContainsKey('Name')) { $request.name = $Name }
+    Invoke-ThymelineRequest $request | Out-Null
+}
+
+function Invoke-ThymelineCommand {
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory, Position = 0)]
+        [ValidateScript({ $script:ThymelineCommands -contains $_ })]
+        [string] $Name,
+        [long] $Hwnd
+    )
+    $request = @{ type = 'command'; command = $Name }
+    if ($PSBoundParameters.ContainsKey('Hwnd')) { $request.hwnd = $Hwnd }
+    Invoke-ThymelineRequest $request | Out-Null
+}
+
+function Move-ThymelineWindow {
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory, Position = 0)] [ValidateSet('Up', 'Down', 'Left', 'Right')] [string] $Direction,
+        [long] $Hwnd
+    )
+    $PSBoundParameters.Remove('Direction') | Out-Null
+    Invoke-ThymelineCommand "move-$($Direction.ToLower())" @PSBoundParameters
+}
+
+function Resize-ThymelineWindow {
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory, Position = 0)] [ValidateSet('Left', 'Right')] [string] $Direction,
+        [long] $Hwnd
+    )
+    $PSBoundParameters.Remove('Direction') | Out-Null
+    Invoke-ThymelineCommand "resize-$($Direction.ToLower())" @PSBoundParameters
+}
+
+function Move-ThymelineView {
+    [CmdletBinding()]
+    param([Parameter(Mandatory, Position = 0)] [ValidateSet('Up', 'Down', 'Left', 'Right')] [string] $Direction)
+    Invoke-ThymelineCommand "pan-$($Direction.ToLower())"
+}
+
+function Add-ThymelineWindow {
+    [CmdletBinding()]
+    param([long] $Hwnd)
+    Invoke-ThymelineCommand 'add-window' @PSBoundParameters
+}
+
+function Remove-ThymelineWindow {
+    [CmdletBinding()]
+    param([long] $Hwnd)
+    Invoke-ThymelineCommand 'remove-window' @PSBoundParameters
+}
+
+Export-ModuleMember -Function Get-ThymelineInfo, Get-ThymelineState, Get-ThymelineWindow, Get-ThymelineStaleWindow, Get-ThymelineStats, Stop-Thymeline, Save-ThymelineMacro, Import-ThymelineLayout, Invoke-ThymelineMacro, Invoke-ThymelineCommand, Move-ThymelineWindow, Resize-ThymelineWindow, Move-ThymelineView, Add-ThymelineWindow, Remove-ThymelineWindow, Invoke-ThymelineRequest
+"#;
+
+pub fn module_source() -> String {
+    let commands = ipc::command_names()
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    MODULE_TEMPLATE
+        .replace("__PROTOCOL__", &ipc::PROTOCOL_VERSION.to_string())
+        .replace("__COMMANDS__", &commands)
+}
+
+// Write the module to a file, or to stdout when no path is given
+pub fn write_module(path: Option<&Path>) -> io::Result<()> {
+    match path {
+        Some(path) => {
+            fs::write(path, module_source())?;
+            println!("Wrote PowerShell module to {}", path.display());
+            println!("Load it with: Import-Module {}", path.display());
+            Ok(())
+        }
+        None => {
+            print!("{}", module_source());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::Value;
+
+use crate::config::RemoteConfig;
+use crate::ipc::{self, ErrorCode, Request, Response};
+use crate::TilerCommand;
+
+// A client has this long to send its token before the connection is dropped
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+// Pause after a wrong token, so guessing one is slow
+const AUTH_FAILURE_DELAY: Duration = Duration::from_secs(1);
+// This many wrong tokens in a row, from any client, and every login is refused for a while
+const MAX_FAILURES: u32 = 5;
+const LOCKOUT: Duration = Duration::from_secs(300);
+// Connections served at once; any more are dropped as they arrive
+const MAX_CONNECTIONS: usize = 4;
+
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+// Wrong tokens in a row across every connection, and when the lockout they caused ends
+static FAILURES: Mutex<(u32, Option<Instant>)> = Mutex::new((0, None));
+
+fn locked_out() -> bool {
+    let failures = FAILURES.lock().unwrap();
+    failures.1.is_some_and(|until| Instant::now() < until)
+}
+
+fn record_failure() {
+    let mut failures = FAILURES.lock().unwrap();
+    failures.0 += 1;
+    if failures.0 >= MAX_FAILURES {
+        println!("Warning: {} wrong remote tokens in a row, refusing remote clients for {} minutes",
+            failures.0, LOCKOUT.as_secs() / 60);
+        *failures = (0, Some(Instant::now() + LOCKOUT));
+    }
+}
+
+// Remote clients may look and move around, but nothing that quits or records:
+// navigation commands, the layout state and the built-in templates. A template only
+// launches its missing apps once someone at this PC agrees to the commands.
+fn handle_request(request: Request) -> Response {
+    match request {
+        Request::Hello { .. } | Request::State => ipc::handle_request(request),
+        Request::ApplyTemplate { name, row } => ipc::apply_template(&name, row, false),
+        Request::Command { ref command, .. } if TilerCommand::from_name(command).is_some_and(TilerCommand::is_navigation) => {
+            ipc::handle_request(request)
+        }
+        Request::Command { command, .. } => {
+            ipc::error(ErrorCode::Unauthorized, format!("'{}' can't be run remotely", command))
+        }
+        _ => ipc::error(ErrorCode::Unauthorized, "Only navigation commands, state and templates are available remotely"),
+    }
+}
+
+// Equal without returning early, so the reply time says nothing about the token
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// The first line must be {"type": "auth", "token": "..."}; after it the connection
+// speaks the pipe protocol, JSON-RPC included, limited to what handle_request allows
+fn handle_client(stream: TcpStream, token: &str) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut writer = stream;
+
+    writer.set_read_timeout(Some(AUTH_TIMEOUT)).ok();
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    if locked_out() {
+        println!("Warning: Refused remote client {} during a lockout", peer);
+        let reply = ipc::error_line(ErrorCode::Unauthorized, "Too many wrong tokens; try again later");
+        writer.write_all(format!("{}\n", reply).as_bytes()).ok();
+        return;
+    }
+    let auth: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+    let given = auth["token"].as_str().filter(|_| auth["type"] == "auth");
+    if !given.is_some_and(|given| same_token(given, token)) {
+        println!("Warning: Remote client {} sent a wrong token", peer);
+        record_failure();
+        thread::sleep(AUTH_FAILURE_DELAY);
+        let reply = ipc::error_line(ErrorCode::Unauthorized, "Send {\"type\": \"auth\", \"token\": ...} with remote.token first");
+        writer.write_all(format!("{}\n", reply).as_bytes()).ok();
+        return;
+    }
+    FAILURES.lock().unwrap().0 = 0;
+    if writer.write_all(b"{\"type\":\"ok\"}\n").is_err() {
+        return;
+    }
+    writer.set_read_timeout(None).ok();
+    println!("Remote client connected from {}", peer);
+
+    ipc::serve(reader, writer, handle_request);
+}
+
+// Accept remote clients on remote.address, one thread per connection up to MAX_CONNECTIONS
+pub fn start_server(config: RemoteConfig) {
+    let listener = match TcpListener::bind(&config.address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("Warning: Failed to listen for remote control on {}: {}", config.address, e);
+            return;
+        }
+    };
+    println!("Listening for remote control on {}", config.address);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if CONNECTIONS.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+                continue;
+            }
+            let token = config.token.clone();
+            thread::spawn(move || {
+                handle_client(stream, &token);
+                CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    });
+}
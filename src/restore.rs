@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use windows::Win32::Foundation::{HWND, RECT};
+
+use crate::{appdata, launch::{self, SlotSize}, monitor, window_info, ManagedWindow, ParkedRibbon, RibbonTiler, TileSize};
+
+// Every ribbon as it was last seen, so the next start can rebuild them from the
+// windows still open. Written while running too, so a crash loses little.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedLayout {
+    current_row: i32,
+    ribbon_offset: i32,   // Ribbon units
+    tiles: Vec<SavedTile>,   // The primary display's first viewport
+    #[serde(default)]
+    ribbons: Vec<SavedRibbon>,   // Every other viewport and display with tiles
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SavedRibbon {
+    viewport: usize,                  // Of the primary display; 0 on another display
+    display: Option<[i32; 4]>,        // Bounds of another display, None on the primary
+    current_row: i32,
+    ribbon_offset: i32,
+    tiles: Vec<SavedTile>,
+}
+
+// What identifies a window across tiler runs; handles don't survive the app restarting,
+// but most apps keep their process, class and title
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SavedTile {
+    exe: String,    // Full image path
+    class: String,
+    title: String,
+    row: i32,
+    slot: usize,    // Position in the row, counted from the left
+    full: bool,     // Read when `size` is missing, from before the narrower widths
+    #[serde(default)]
+    size: Option<SlotSize>,
+    #[serde(default)]
+    width: Option<i32>,   // Custom width in ribbon units
+}
+
+// %APPDATA%\Thymeline\last-layout.json
+const FILE: &str = "last-layout.json";
+
+fn saved_tiles(windows: &HashMap<isize, ManagedWindow>, rows: &HashMap<i32, Vec<isize>>) -> Vec<SavedTile> {
+    let mut rows: Vec<(&i32, &Vec<isize>)> = rows.iter().collect();
+    rows.sort_by_key(|(row, _)| **row);
+    rows.into_iter()
+        .flat_map(|(&row, order)| order.iter().filter_map(|hwnd| windows.get(hwnd)).enumerate()
+            .map(move |(slot, window)| SavedTile {
+                exe: window_info::process_path(window.hwnd).unwrap_or_default(),
+                class: window_info::class_name(window.hwnd),
+                title: window_info::window_title(window.hwnd),
+                row,
+                slot,
+                full: window.position.size == TileSize::Full,
+                size: Some(SlotSize::of(window.position.size)),
+                width: window.position.width,
+            }))
+        .collect()
+}
+
+// A ribbon's tiles, row order, current row and pan position, active or parked
+type RibbonView<'a> = (&'a HashMap<isize, ManagedWindow>, &'a HashMap<i32, Vec<isize>>, i32, i32);
+
+fn parked(ribbon: &ParkedRibbon) -> RibbonView<'_> {
+    (&ribbon.windows, &ribbon.rows, ribbon.current_row, ribbon.ribbon_offset)
+}
+
+fn saved_ribbon(viewport: usize, display: Option<[i32; 4]>, (windows, rows, current_row, ribbon_offset): RibbonView) -> SavedRibbon {
+    SavedRibbon { viewport, display, current_row, ribbon_offset, tiles: saved_tiles(windows, rows) }
+}
+
+// Read-only, unlike the handoff capture, since it runs on every hung-check tick. The
+// active ribbon belongs to whichever viewport or display has the focus; while another
+// display's is active, the primary's active viewport is parked with the displays.
+pub fn capture(tiler: &RibbonTiler) -> SavedLayout {
+    let primary = monitor::primary_handle();
+    let active: RibbonView = (&tiler.windows, &tiler.rows, tiler.current_row, tiler.ribbon_offset_target);
+    let mut layout = SavedLayout::default();
+
+    for index in 0..tiler.parked_ribbons.len() {
+        let view = if index != tiler.viewport_index {
+            Some(parked(&tiler.parked_ribbons[index]))
+        } else if tiler.monitor == primary {
+            Some(active)
+        } else {
+            tiler.monitor_ribbons.get(&primary).map(parked)
+        };
+        let Some(view) = view else {
+            continue;
+        };
+        let ribbon = saved_ribbon(index, None, view);
+        if index == 0 {
+            layout.current_row = ribbon.current_row;
+            layout.ribbon_offset = ribbon.ribbon_offset;
+            layout.tiles = ribbon.tiles;
+        } else {
+            layout.ribbons.push(ribbon);
+        }
+    }
+
+    // Sorted, so an unchanged layout compares equal to the one last written
+    let mut displays: Vec<(isize, RibbonView)> = tiler.monitor_ribbons.iter()
+        .filter(|(&handle, _)| handle != primary)
+        .map(|(&handle, ribbon)| (handle, parked(ribbon)))
+        .chain((tiler.monitor != primary).then_some((tiler.monitor, active)))
+        .collect();
+    displays.sort_by_key(|(handle, _)| *handle);
+    for (handle, view) in displays {
+        if let Some(bounds) = monitor::bounds(handle) {
+            layout.ribbons.push(saved_ribbon(0, Some([bounds.left, bounds.top, bounds.right, bounds.bottom]), view));
+        }
+    }
+    layout.ribbons.retain(|ribbon| !ribbon.tiles.is_empty());
+    layout
+}
+
+// Only writes when the layout differs from the one last written
+pub fn save(tiler: &mut RibbonTiler) {
+    let layout = capture(tiler);
+    if tiler.saved_layout.as_ref() == Some(&layout) {
+        return;
+    }
+    appdata::store_json(FILE, &layout);
+    tiler.saved_layout = Some(layout);
+}
+
+// Put surviving windows back where they were, in each viewport and display. Each saved
+// tile takes the first open window with the same process, class and title; tiles whose
+// title has since changed then take any window left over from the same process and class.
+pub fn restore(tiler: &mut RibbonTiler) {
+    let Some(layout) = appdata::load_json::<SavedLayout>(FILE) else {
+        return;
+    };
+    let open: Vec<(HWND, String, String, String)> = launch::main_windows().into_iter()
+        .map(|hwnd| (
+            hwnd,
+            window_info::process_path(hwnd).unwrap_or_default(),
+            window_info::class_name(hwnd),
+            window_info::window_title(hwnd),
+        ))
+        .collect();
+    let mut claimed: HashSet<isize> = HashSet::new();
+    let primary = monitor::primary_handle();
+
+    let main = SavedRibbon {
+        viewport: 0,
+        display: None,
+        current_row: layout.current_row,
+        ribbon_offset: layout.ribbon_offset,
+        tiles: layout.tiles,
+    };
+    let mut restored = 0;
+    let mut saved = 0;
+    for ribbon in layout.ribbons.iter().chain(std::iter::once(&main)) {
+        // A display that is no longer attached gives its tiles to the primary's first viewport
+        let handle = ribbon.display
+            .and_then(|[left, top, right, bottom]| monitor::with_bounds(&RECT { left, top, right, bottom }))
+            .unwrap_or(primary);
+        tiler.activate_monitor(handle);
+        if handle == primary {
+            tiler.activate_viewport(if ribbon.display.is_some() { 0 } else { ribbon.viewport });
+        }
+        restored += restore_ribbon(tiler, ribbon, &open, &mut claimed);
+        saved += ribbon.tiles.len();
+    }
+    if restored > 0 {
+        println!("Restored {} of {} windows from the last layout", restored, saved);
+    }
+}
+
+// One ribbon's tiles, into whichever ribbon is active; returns how many were found
+fn restore_ribbon(tiler: &mut RibbonTiler, layout: &SavedRibbon, open: &[(HWND, String, String, String)], claimed: &mut HashSet<isize>) -> usize {
+    let mut matches: Vec<(&SavedTile, HWND)> = Vec::new();
+    for exact in [true, false] {
+        for tile in &layout.tiles {
+            if matches.iter().any(|(matched, _)| std::ptr::eq(*matched, tile)) {
+                continue;
+            }
+            let found = open.iter().find(|(hwnd, exe, class, title)| {
+                !claimed.contains(&hwnd.0)
+                    && !tile.exe.is_empty()
+                    && exe.eq_ignore_ascii_case(&tile.exe)
+                    && *class == tile.class
+                    && (!exact || *title == tile.title)
+            });
+            if let Some(&(hwnd, ..)) = found {
+                claimed.insert(hwnd.0);
+                matches.push((tile, hwnd));
+            }
+        }
+    }
+    if matches.is_empty() {
+        return 0;
+    }
+
+    matches.sort_by_key(|(tile, _)| (tile.row, tile.slot));
+    for (tile, hwnd) in &matches {
+        let size = match tile.size {
+            Some(size) => size.tile_size(),
+            None if tile.full => TileSize::Full,
+            None => TileSize::Half,
+        };
+        tiler.place_window(*hwnd, tile.row, tile.slot, size);
+        if let Some(window) = tiler.windows.get_mut(&hwnd.0) {
+            window.position.width = tile.width;
+        }
+    }
+
+    tiler.current_row = layout.current_row.clamp(0, tiler.last_pannable_row());
+    tiler.vertical_offset_target = tiler.current_row * tiler.row_height;
+    tiler.ribbon_offset_target = layout.ribbon_offset.max(0);
+    tiler.needs_ribbon_recalc = true;
+    tiler.start_scroll_animation();
+    matches.len()
+}
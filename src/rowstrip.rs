@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use windows::Win32::{
+    Foundation::RECT,
+    UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN},
+};
+
+use crate::compositor::rgba;
+use crate::labels::{Icon, ICON_SIZE};
+use crate::overlay::{Layer, Overlay};
+
+// Size and placement in 96-DPI units
+const ROW_WIDTH: f32 = 200.0;
+const ROW_HEIGHT: f32 = 36.0;
+const GAP: f32 = 6.0;
+const PADDING: f32 = 10.0;
+const EDGE_GAP: f32 = 24.0;
+const ICON: f32 = 14.0;
+
+// One row as schematic blocks: (start, width) of each tile as fractions of the ribbon's length
+#[derive(Debug, Clone, Default)]
+pub struct RowPreview {
+    pub tiles: Vec<(f32, f32)>,
+    pub icons: Vec<Option<Arc<Icon>>>,   // App icon of each tile, in the same order
+    pub heat: Vec<(f32, f32, f32)>,   // (start, width, share of focus time) of each ribbon column
+}
+
+#[derive(Default)]
+struct Content {
+    rows: Vec<RowPreview>,
+    current: usize,
+    target: usize,
+    dim: f32,   // Alpha of the shade over every other row
+}
+
+// Vertical strip of row previews shown while the row-switch key is held, marking the
+// row a release will land on. Lives on the main thread like the OSD.
+struct RowStrip {
+    overlay: Overlay,
+    content: Rc<RefCell<Content>>,
+}
+
+impl RowStrip {
+    fn new() -> Option<Self> {
+        let content = Rc::new(RefCell::new(Content::default()));
+        let painted = content.clone();
+        let overlay = Overlay::new(Layer::RowStrip, true, Box::new(move |canvas| {
+            let content = painted.borrow();
+            canvas.fill_rounded_rect(0.0, 0.0, canvas.width, canvas.height, 8.0, rgba(0x20, 0x20, 0x20, 0.9));
+
+            for (index, row) in content.rows.iter().enumerate() {
+                let top = PADDING + index as f32 * (ROW_HEIGHT + GAP);
+                let (left, right, bottom) = (PADDING, PADDING + ROW_WIDTH, top + ROW_HEIGHT);
+                let background = match index {
+                    i if i == content.target => rgba(0x40, 0xA0, 0xE0, 0.35),
+                    i if i == content.current => rgba(0x60, 0x60, 0x60, 0.6),
+                    _ => rgba(0x40, 0x40, 0x40, 0.6),
+                };
+                canvas.fill_rounded_rect(left, top, right, bottom, 4.0, background);
+
+                let tile_color = if index == content.target {
+                    rgba(0x40, 0xA0, 0xE0, 1.0)
+                } else {
+                    rgba(0xA0, 0xA0, 0xA0, 1.0)
+                };
+                for (index, &(start, width)) in row.tiles.iter().enumerate() {
+                    let tile_left = left + 4.0 + start * (ROW_WIDTH - 8.0);
+                    let tile_right = tile_left + (width * (ROW_WIDTH - 8.0) - 2.0).max(2.0);
+                    canvas.fill_rect(tile_left, top + 6.0, tile_right, bottom - 10.0, tile_color);
+
+                    // Only on tiles wide enough to frame it
+                    let icon = row.icons.get(index).and_then(Option::as_ref);
+                    if let Some(icon) = icon.filter(|_| tile_right - tile_left >= ICON + 4.0) {
+                        let icon_left = (tile_left + tile_right - ICON) / 2.0;
+                        let icon_top = top + 6.0 + (ROW_HEIGHT - 16.0 - ICON) / 2.0;
+                        canvas.bitmap(&icon.pixels, ICON_SIZE, icon_left, icon_top, icon_left + ICON, icon_top + ICON);
+                    }
+                }
+
+                // Shaded strip under the tiles: the more focus time a stretch of the row has had, the hotter
+                for &(start, width, share) in row.heat.iter().filter(|(_, _, share)| *share > 0.0) {
+                    let cell_left = left + 4.0 + start * (ROW_WIDTH - 8.0);
+                    let cell_right = (cell_left + width * (ROW_WIDTH - 8.0)).min(right - 4.0);
+                    if cell_right > cell_left {
+                        canvas.fill_rect(cell_left, bottom - 7.0, cell_right, bottom - 3.0, rgba(0xF0, 0x80, 0x30, 0.2 + share * 0.8));
+                    }
+                }
+
+                // Rows that are neither where the view is nor where it is going fade back
+                if index != content.current && index != content.target && content.dim > 0.0 {
+                    canvas.fill_rounded_rect(left, top, right, bottom, 4.0, rgba(0x00, 0x00, 0x00, content.dim));
+                }
+            }
+        }))?;
+
+        Some(Self { overlay, content })
+    }
+
+    fn show(&mut self, rows: Vec<RowPreview>, current: usize, target: usize, dim: f32) {
+        let count = rows.len().max(1) as f32;
+        *self.content.borrow_mut() = Content { rows, current, target, dim };
+
+        let scale = self.overlay.scale();
+        let (screen_width, screen_height) = unsafe {
+            (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN))
+        };
+        let width = ((ROW_WIDTH + PADDING * 2.0) * scale) as i32;
+        let height = ((PADDING * 2.0 + count * ROW_HEIGHT + (count - 1.0) * GAP) * scale) as i32;
+        let left = screen_width - width - (EDGE_GAP * scale) as i32;
+        let top = (screen_height - height) / 2;
+
+        self.overlay.show_at(RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        });
+    }
+}
+
+thread_local! {
+    static STRIP: RefCell<Option<RowStrip>> = const { RefCell::new(None) };
+}
+
+// `rows` runs from the top row down; `current` and `target` index into it. The rest are
+// shaded by `dim`, from 0 (not at all) to 1 (black).
+pub fn show(rows: Vec<RowPreview>, current: usize, target: usize, dim: f32) {
+    STRIP.with(|strip| {
+        let mut strip = strip.borrow_mut();
+        if strip.is_none() {
+            *strip = RowStrip::new();
+        }
+        if let Some(strip) = strip.as_mut() {
+            strip.show(rows, current, target, dim);
+        }
+    });
+}
+
+// Shown on its own rather than while the row-switch key is held, so it hides itself
+pub fn flash(rows: Vec<RowPreview>, current: usize, dim: f32, ms: u32) {
+    STRIP.with(|strip| {
+        let mut strip = strip.borrow_mut();
+        if strip.is_none() {
+            *strip = RowStrip::new();
+        }
+        if let Some(strip) = strip.as_mut() {
+            strip.show(rows, current, current, dim);
+            strip.overlay.hide_after(ms);
+        }
+    });
+}
+
+pub fn hide() {
+    STRIP.with(|strip| {
+        if let Some(strip) = strip.borrow().as_ref() {
+            strip.overlay.hide();
+        }
+    });
+}
+
+pub fn close() {
+    STRIP.with(|strip| *strip.borrow_mut() = None);
+}
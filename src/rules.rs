@@ -0,0 +1,104 @@
+use regex::Regex;
+use serde::{de::Error, Deserialize, Deserializer};
+use windows::Win32::Foundation::HWND;
+
+use crate::{compat::Quirk, launch::SlotSize, window_info, TileSize};
+
+// One [[rules]] entry of the config file. Every matcher given must match; the first
+// rule that matches a window decides for it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub exe: Option<String>,     // Image name, case-insensitive
+    pub class: Option<String>,   // Window class, exact
+    pub title: Option<String>,   // Regex searched in the title
+    #[serde(default, deserialize_with = "quirk")]
+    pub quirk: Option<Option<Quirk>>,   // Some(None) turns off a built-in entry, as with --quirk
+    pub size: Option<SlotSize>,
+    pub row: Option<i32>,
+    pub transparency: Option<u8>,   // Alpha replacing the global transparency
+    pub display: Option<String>,    // Preferred display, "primary" or a device name such as DISPLAY2
+}
+
+// The same names --quirk takes
+fn quirk<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<Quirk>>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Quirk::parse(&value)
+        .map(Some)
+        .ok_or_else(|| D::Error::custom(format!("unknown quirk `{}`", value)))
+}
+
+// A rule ready to match, with its title regex compiled
+#[derive(Debug, Clone)]
+pub struct Rule {
+    exe: Option<String>,
+    class: Option<String>,
+    title: Option<Regex>,
+    pub quirk: Option<Option<Quirk>>,
+    pub size: Option<TileSize>,
+    pub row: Option<i32>,
+    pub transparency: Option<u8>,
+    pub display: Option<String>,
+}
+
+// Every problem in the rules, each naming the rule by its position
+pub fn validate(rules: &[RuleConfig]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        if rule.exe.is_none() && rule.class.is_none() && rule.title.is_none() {
+            errors.push(format!("rules[{}]: needs at least one of exe, class or title", index));
+        }
+        if let Some(Err(e)) = rule.title.as_deref().map(Regex::new) {
+            errors.push(format!("rules[{}].title: {}", index, e));
+        }
+        if rule.row.is_some_and(|row| row < 0) {
+            errors.push(format!("rules[{}].row: must not be negative", index));
+        }
+        if rule.display.as_deref().is_some_and(|display| display.trim().is_empty()) {
+            errors.push(format!("rules[{}].display: must not be empty", index));
+        }
+    }
+    errors
+}
+
+// Rules that fail to compile are left out; the config is validated before it gets here
+pub fn compile(rules: &[RuleConfig]) -> Vec<Rule> {
+    rules.iter()
+        .filter_map(|rule| Some(Rule {
+            exe: rule.exe.clone(),
+            class: rule.class.clone(),
+            title: match rule.title.as_deref() {
+                Some(title) => Some(Regex::new(title).ok()?),
+                None => None,
+            },
+            quirk: rule.quirk,
+            size: rule.size.map(SlotSize::tile_size),
+            row: rule.row,
+            transparency: rule.transparency,
+            display: rule.display.clone(),
+        }))
+        .collect()
+}
+
+impl Rule {
+    // Only these can start or stop matching while a window is open
+    pub fn has_title(&self) -> bool {
+        self.title.is_some()
+    }
+}
+
+// The first rule for a window. The title is the one it has right now, so apps that
+// set their title late may only match on a later look.
+pub fn lookup(rules: &[Rule], hwnd: HWND) -> Option<&Rule> {
+    if rules.is_empty() {
+        return None;
+    }
+    let exe = window_info::process_name(hwnd).unwrap_or_default();
+    let class = window_info::class_name(hwnd);
+    let title = window_info::window_title(hwnd);
+    rules.iter().find(|rule| {
+        rule.exe.as_ref().is_none_or(|name| name.eq_ignore_ascii_case(&exe))
+            && rule.class.as_ref().is_none_or(|name| *name == class)
+            && rule.title.as_ref().is_none_or(|pattern| pattern.is_match(&title))
+    })
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+// One [[schedule]] entry of the config file: a row's appearance during part of the day
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub row: i32,
+    pub from: String,        // "HH:MM", local time
+    pub to: String,          // Earlier than `from` runs past midnight
+    #[serde(default)]
+    pub days: Vec<String>,   // "mon" … "sun" the stretch starts on; every day when empty
+    pub transparency: u8,    // Alpha the row's tiles get meanwhile
+}
+
+// In the order GetLocalTime numbers them
+const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// Minutes since midnight
+fn minutes(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+// Every problem in the schedule, each naming the entry by its position
+pub fn validate(schedule: &[ScheduleConfig]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (index, entry) in schedule.iter().enumerate() {
+        if entry.row < 0 {
+            errors.push(format!("schedule[{}].row: must not be negative", index));
+        }
+        for (key, time) in [("from", &entry.from), ("to", &entry.to)] {
+            if minutes(time).is_none() {
+                errors.push(format!("schedule[{}].{}: '{}' is not a time like 09:30", index, key, time));
+            }
+        }
+        for day in entry.days.iter().filter(|day| !DAYS.contains(&day.to_ascii_lowercase().as_str())) {
+            errors.push(format!("schedule[{}].days: '{}' is not one of {}", index, day, DAYS.join(", ")));
+        }
+    }
+    errors
+}
+
+// Alpha of each row with an entry in force right now; the first entry for a row wins
+pub fn row_alphas(schedule: &[ScheduleConfig]) -> HashMap<i32, u8> {
+    let mut alphas = HashMap::new();
+    if schedule.is_empty() {
+        return alphas;
+    }
+    let now = unsafe { GetLocalTime() };
+    let minute = now.wHour as u32 * 60 + now.wMinute as u32;
+    let today = now.wDayOfWeek as usize % 7;
+
+    for entry in schedule {
+        let (Some(from), Some(to)) = (minutes(&entry.from), minutes(&entry.to)) else {
+            continue;
+        };
+        // Past midnight, the stretch began the day before
+        let (active, started) = if from <= to {
+            (from <= minute && minute < to, today)
+        } else if minute >= from {
+            (true, today)
+        } else {
+            (minute < to, (today + 6) % 7)
+        };
+        let on_day = entry.days.is_empty() || entry.days.iter().any(|day| day.eq_ignore_ascii_case(DAYS[started]));
+        if active && on_day {
+            alphas.entry(entry.row).or_insert(entry.transparency);
+        }
+    }
+    alphas
+}
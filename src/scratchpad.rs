@@ -0,0 +1,133 @@
+use std::time::Instant;
+use windows::Win32::{
+    Foundation::*,
+    UI::WindowsAndMessaging::*,
+};
+
+use crate::easing::Curve;
+use crate::focus;
+
+// A window kept out of the ribbon that drops down over the top of the screen on a
+// hotkey and slides back up out of sight, like a game console
+pub struct Scratchpad {
+    pub hwnd: HWND,
+    original_placement: WINDOWPLACEMENT,   // Where it goes back to when released
+    was_topmost: bool,
+    pub shown: bool,
+    slide: Option<Slide>,
+}
+
+struct Slide {
+    from: i32,   // Top edge, in screen pixels
+    to: i32,
+    start: Instant,
+}
+
+impl Scratchpad {
+    pub fn new(hwnd: HWND) -> Self {
+        let mut original_placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        unsafe {
+            GetWindowPlacement(hwnd, &mut original_placement).ok();
+            // Shown maximized it would ignore the size it is given
+            if IsZoomed(hwnd).as_bool() {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+        }
+        let ex_style = unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) } as u32;
+        Self {
+            hwnd,
+            original_placement,
+            was_topmost: ex_style & WS_EX_TOPMOST.0 != 0,
+            shown: unsafe { IsWindowVisible(hwnd).as_bool() },
+            slide: None,
+        }
+    }
+
+    // Drop down into `rect`, above everything including the tiles, and take focus.
+    // Slides in from above the screen unless `animate` is off.
+    pub fn show(&mut self, rect: RECT, animate: bool) {
+        let height = rect.bottom - rect.top;
+        let from = match &self.slide {
+            Some(_) => self.top(),
+            None if self.shown => rect.top,
+            None => rect.top - height,
+        };
+        unsafe {
+            SetWindowPos(self.hwnd, HWND_TOPMOST, rect.left, from, rect.right - rect.left, height,
+                SWP_NOACTIVATE | SWP_SHOWWINDOW).ok();
+        }
+        self.shown = true;
+        focus::focus_window(self.hwnd);
+        self.slide_to(rect.top, animate);
+    }
+
+    // Slide up and out of sight, then hide, so it leaves the taskbar and Alt+Tab too
+    pub fn hide(&mut self, rect: RECT, animate: bool) {
+        self.shown = false;
+        self.slide_to(rect.top - (rect.bottom - rect.top), animate);
+    }
+
+    fn slide_to(&mut self, to: i32, animate: bool) {
+        let from = self.top();
+        self.slide = Some(Slide { from, to, start: Instant::now() });
+        if !animate {
+            self.step(Curve::INSTANT);
+        }
+    }
+
+    pub fn sliding(&self) -> bool {
+        self.slide.is_some()
+    }
+
+    // One animation frame. Lands the slide once the curve's duration has passed.
+    pub fn step(&mut self, curve: Curve) {
+        let Some(slide) = &self.slide else {
+            return;
+        };
+        let elapsed = slide.start.elapsed();
+        let eased = curve.progress(elapsed);
+        let top = slide.from + ((slide.to - slide.from) as f32 * eased) as i32;
+        let mut rect = RECT::default();
+        unsafe {
+            GetWindowRect(self.hwnd, &mut rect).ok();
+            SetWindowPos(self.hwnd, None, rect.left, top, 0, 0,
+                SWP_NOACTIVATE | SWP_NOZORDER | SWP_NOSIZE).ok();
+        }
+        if elapsed >= curve.duration {
+            self.slide = None;
+            if !self.shown {
+                unsafe {
+                    ShowWindow(self.hwnd, SW_HIDE);
+                }
+            }
+        }
+    }
+
+    fn top(&self) -> i32 {
+        let mut rect = RECT::default();
+        unsafe {
+            GetWindowRect(self.hwnd, &mut rect).ok();
+        }
+        rect.top
+    }
+
+    // Back to an ordinary window, where it was before it became the scratchpad
+    pub fn release(self) {
+        unsafe {
+            if !IsWindow(self.hwnd).as_bool() {
+                return;
+            }
+            if !self.was_topmost {
+                SetWindowPos(self.hwnd, HWND_NOTOPMOST, 0, 0, 0, 0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE).ok();
+            }
+            SetWindowPlacement(self.hwnd, &self.original_placement).ok();
+            if !IsWindowVisible(self.hwnd).as_bool() {
+                ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        System::Com::*,
+        System::RemoteDesktop::ProcessIdToSessionId,
+        System::Threading::*,
+        UI::Shell::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+// Terminal Services session this process runs in (0 on failure)
+pub fn current_session_id() -> u32 {
+    unsafe {
+        let mut session_id = 0u32;
+        ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id).ok();
+        session_id
+    }
+}
+
+// Session of the process that owns a window
+pub fn window_session_id(hwnd: HWND) -> Option<u32> {
+    unsafe {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+
+        let mut session_id = 0u32;
+        ProcessIdToSessionId(pid, &mut session_id).ok()?;
+        Some(session_id)
+    }
+}
+
+// Suffix a kernel object / window name with the session so parallel sessions never collide
+pub fn scoped_name(base: &str) -> String {
+    format!("{}-session{}", base, current_session_id())
+}
+
+// The shell's virtual desktop manager, created once on the main thread after COM is
+// initialised. None where the shell doesn't offer one.
+pub struct Desktops(Option<IVirtualDesktopManager>);
+
+// Only used on the main thread, which created it, though the tiler it lives in is shared
+unsafe impl Send for Desktops {}
+
+impl Desktops {
+    pub fn new() -> Self {
+        Self(unsafe { CoCreateInstance(&VirtualDesktopManager, None, CLSCTX_INPROC_SERVER).ok() })
+    }
+
+    // False only when the shell positively reports the window lives on another virtual desktop
+    pub fn is_current(&self, hwnd: HWND) -> bool {
+        let Some(manager) = self.0.as_ref() else {
+            return true;
+        };
+        unsafe {
+            manager.IsWindowOnCurrentVirtualDesktop(hwnd)
+                .map(|on_desktop| on_desktop.as_bool())
+                .unwrap_or(true)
+        }
+    }
+}
+
+// Held for the lifetime of the tiler so only one instance runs per session
+pub struct InstanceGuard(HANDLE);
+
+fn instance_mutex_name() -> HSTRING {
+    HSTRING::from(format!("Local\\{}", scoped_name("Thymeline")))
+}
+
+impl InstanceGuard {
+    // Returns None if another tiler already owns this session
+    pub fn acquire() -> Option<Self> {
+        unsafe {
+            let handle = CreateMutexW(None, true, &instance_mutex_name()).ok()?;
+            if GetLastError() == ERROR_ALREADY_EXISTS {
+                CloseHandle(handle).ok();
+                return None;
+            }
+            Some(Self(handle))
+        }
+    }
+
+    // For a --handoff instance, whose predecessor holds the session until it exits:
+    // waits up to `timeout_ms` for it to let go. An abandoned mutex, left by an instance
+    // that exited without releasing it, is ours all the same.
+    pub fn acquire_after(timeout_ms: u32) -> Option<Self> {
+        unsafe {
+            let handle = CreateMutexW(None, false, &instance_mutex_name()).ok()?;
+            let result = WaitForSingleObject(handle, timeout_ms);
+            if result != WAIT_OBJECT_0 && result != WAIT_ABANDONED {
+                CloseHandle(handle).ok();
+                return None;
+            }
+            Some(Self(handle))
+        }
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseMutex(self.0).ok();
+            CloseHandle(self.0).ok();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use windows::Win32::{
+    Foundation::CloseHandle,
+    System::Diagnostics::ToolHelp::*,
+};
+
+// Image names of apps that share or record the screen whenever they run: OBS, and
+// the process Zoom starts for the length of a share
+pub fn default_apps() -> Vec<String> {
+    ["obs64.exe", "obs32.exe", "CptHost.exe"].map(String::from).to_vec()
+}
+
+// The first of `apps` with a running process, matched case-insensitively
+pub fn running(apps: &[String]) -> Option<String> {
+    if apps.is_empty() {
+        return None;
+    }
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0).ok()?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut found = None;
+        let mut more = Process32FirstW(snapshot, &mut entry).is_ok();
+        while more {
+            let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+            let name = String::from_utf16_lossy(&entry.szExeFile[..len]);
+            if let Some(app) = apps.iter().find(|app| app.eq_ignore_ascii_case(&name)) {
+                found = Some(app.clone());
+                break;
+            }
+            more = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+        CloseHandle(snapshot).ok();
+        found
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+// HSHELL_* codes the windows crate leaves out. The fullscreen pair is undocumented but
+// has been sent by Explorer since Windows 8.
+pub const HSHELL_FLASH: u32 = HSHELL_REDRAW | HSHELL_HIGHBIT;
+pub const HSHELL_FULLSCREEN_ENTER: u32 = 0x35;
+pub const HSHELL_FULLSCREEN_EXIT: u32 = 0x36;
+
+static SHELL_HOOK: AtomicU32 = AtomicU32::new(0);
+
+// Message the shell notifications arrive as, with the HSHELL_* code in wparam and the
+// window in lparam
+pub fn message() -> u32 {
+    match SHELL_HOOK.load(Ordering::Relaxed) {
+        0 => {
+            let id = unsafe { RegisterWindowMessageW(w!("SHELLHOOK")) };
+            SHELL_HOOK.store(id, Ordering::Relaxed);
+            id
+        }
+        id => id,
+    }
+}
+
+// The taskbar's own view of windows: replacements, flashing and fullscreen, which the
+// WinEvent hooks don't report
+pub fn register(hwnd: HWND) {
+    message();
+    if !unsafe { RegisterShellHookWindow(hwnd) }.as_bool() {
+        println!("Warning: Failed to register for shell notifications");
+    }
+}
+
+pub fn deregister(hwnd: HWND) {
+    unsafe {
+        DeregisterShellHookWindow(hwnd);
+    }
+}
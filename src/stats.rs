@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::{appdata, TilerCommand};
+
+// Bumped by the animation thread, which can't reach the tiler without its lock
+static FRAMES: AtomicU64 = AtomicU64::new(0);
+static FRAME_DROPS: AtomicU64 = AtomicU64::new(0);
+
+// Usage counters kept on this machine only. They are never sent anywhere; the
+// running instance reports them over the pipe and writes them to stats.json on exit.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    since: u64,                        // Unix time counting started
+    commands: BTreeMap<String, u64>,   // Times each command ran, by name
+    windows_managed: u64,              // Windows adopted into a ribbon
+    #[serde(default)]
+    frames: u64,                       // Animation ticks
+    #[serde(default)]
+    frame_drops: u64,                  // Ticks skipped because the animation thread fell behind
+    #[serde(default)]
+    focus_ms: BTreeMap<i32, Vec<u64>>, // Focus time by row, per half-screen column of the ribbon
+    #[serde(skip)]
+    focused: Option<Focus>,
+}
+
+// The tile holding focus right now, and the ribbon columns it covers
+#[derive(Debug, Clone)]
+struct Focus {
+    row: i32,
+    columns: Range<usize>,
+    since: Instant,
+}
+
+// %APPDATA%\Thymeline\stats.json
+const FILE: &str = "stats.json";
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// One animation tick, `elapsed` after the one before. A tick that came two or more
+// frames late stands for the frames it skipped.
+pub fn record_frame(elapsed: Duration, frame: Duration) {
+    FRAMES.fetch_add(1, Ordering::Relaxed);
+    let late = (elapsed.as_secs_f64() / frame.as_secs_f64()) as u64;
+    if late >= 2 {
+        FRAME_DROPS.fetch_add(late - 1, Ordering::Relaxed);
+    }
+}
+
+impl Stats {
+    // Carries on from the totals of earlier runs
+    pub fn load() -> Self {
+        let stats = appdata::load_json::<Self>(FILE).unwrap_or_else(|| Self { since: now(), ..Self::default() });
+        FRAMES.store(stats.frames, Ordering::Relaxed);
+        FRAME_DROPS.store(stats.frame_drops, Ordering::Relaxed);
+        stats
+    }
+
+    // Closes the running focus interval first, so it isn't lost
+    pub fn store(&mut self) {
+        self.focus(None);
+        appdata::store_json(FILE, &self.snapshot());
+    }
+
+    pub fn record_command(&mut self, command: TilerCommand) {
+        *self.commands.entry(command.name().to_string()).or_default() += 1;
+    }
+
+    pub fn record_window(&mut self) {
+        self.windows_managed += 1;
+    }
+
+    // Focus moved to the tile covering these columns of a row, or away from the ribbon.
+    // A full-width tile credits both of its columns with the whole interval.
+    pub fn focus(&mut self, tile: Option<(i32, Range<usize>)>) {
+        if let Some(focus) = self.focused.take() {
+            let ms = focus.since.elapsed().as_millis() as u64;
+            let row = self.focus_ms.entry(focus.row).or_default();
+            if row.len() < focus.columns.end {
+                row.resize(focus.columns.end, 0);
+            }
+            row[focus.columns].iter_mut().for_each(|total| *total += ms);
+        }
+        self.focused = tile.map(|(row, columns)| Focus { row, columns, since: Instant::now() });
+    }
+
+    // Bank the running interval without ending it, so a report includes it
+    pub fn settle(&mut self) {
+        let tile = self.focused.as_ref().map(|focus| (focus.row, focus.columns.clone()));
+        self.focus(tile);
+    }
+
+    // Focus time of each column of a row, from 0 to 1 against the busiest column anywhere
+    pub fn heat(&self) -> BTreeMap<i32, Vec<f32>> {
+        let busiest = self.focus_ms.values().flatten().copied().max().unwrap_or(0).max(1) as f32;
+        self.focus_ms.iter()
+            .map(|(&row, columns)| (row, columns.iter().map(|&ms| ms as f32 / busiest).collect()))
+            .collect()
+    }
+
+    // The counters as they stand, frame counts included
+    pub fn snapshot(&self) -> Self {
+        Self {
+            frames: FRAMES.load(Ordering::Relaxed),
+            frame_drops: FRAME_DROPS.load(Ordering::Relaxed),
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use windows::Win32::{
+    Foundation::RECT,
+    UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN},
+};
+
+use crate::compositor::rgba;
+use crate::labels::{Icon, ICON_SIZE};
+use crate::overlay::{Layer, Overlay};
+
+// Size in 96-DPI units
+const WIDTH: f32 = 420.0;
+const ENTRY_HEIGHT: f32 = 40.0;
+const PADDING: f32 = 8.0;
+const ICON: f32 = 24.0;
+
+// Most entries listed; presses past the last one wrap around to the first
+pub const MAX_ENTRIES: usize = 9;
+
+// A tile as the switcher lists it
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub title: String,
+    pub icon: Option<Arc<Icon>>,
+}
+
+#[derive(Default)]
+struct Content {
+    entries: Vec<Entry>,
+    selected: usize,
+}
+
+// Recently used tiles, most recent first, shown in the middle of the screen while
+// switch-window's modifiers are held. Lives on the main thread like the OSD.
+struct Switcher {
+    overlay: Overlay,
+    content: Rc<RefCell<Content>>,
+}
+
+impl Switcher {
+    fn new() -> Option<Self> {
+        let content = Rc::new(RefCell::new(Content::default()));
+        let painted = content.clone();
+        let overlay = Overlay::new(Layer::Switcher, true, Box::new(move |canvas| {
+            let content = painted.borrow();
+            canvas.fill_rounded_rect(0.0, 0.0, canvas.width, canvas.height, 8.0, rgba(0x20, 0x20, 0x20, 0.9));
+
+            for (index, entry) in content.entries.iter().enumerate() {
+                let top = PADDING + index as f32 * ENTRY_HEIGHT;
+                let (left, right, bottom) = (PADDING, canvas.width - PADDING, top + ENTRY_HEIGHT);
+                if index == content.selected {
+                    canvas.fill_rounded_rect(left, top, right, bottom, 4.0, rgba(0x40, 0xA0, 0xE0, 0.35));
+                }
+                let icon_top = top + (ENTRY_HEIGHT - ICON) / 2.0;
+                if let Some(icon) = &entry.icon {
+                    canvas.bitmap(&icon.pixels, ICON_SIZE, left + 8.0, icon_top, left + 8.0 + ICON, icon_top + ICON);
+                }
+                let text_left = left + 16.0 + ICON;
+                canvas.text_leading(&entry.title, text_left, top, right - 8.0, bottom, 14.0, rgba(0xFF, 0xFF, 0xFF, 1.0));
+            }
+        }))?;
+
+        Some(Self { overlay, content })
+    }
+
+    fn show(&mut self, entries: Vec<Entry>, selected: usize) {
+        let count = entries.len().max(1) as f32;
+        *self.content.borrow_mut() = Content { entries, selected };
+
+        let scale = self.overlay.scale();
+        let (screen_width, screen_height) = unsafe {
+            (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN))
+        };
+        let width = (WIDTH * scale) as i32;
+        let height = ((PADDING * 2.0 + count * ENTRY_HEIGHT) * scale) as i32;
+        let left = (screen_width - width) / 2;
+        let top = (screen_height - height) / 2;
+
+        self.overlay.show_at(RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        });
+    }
+}
+
+thread_local! {
+    static SWITCHER: RefCell<Option<Switcher>> = const { RefCell::new(None) };
+}
+
+// `selected` indexes into `entries`
+pub fn show(entries: Vec<Entry>, selected: usize) {
+    SWITCHER.with(|switcher| {
+        let mut switcher = switcher.borrow_mut();
+        if switcher.is_none() {
+            *switcher = Switcher::new();
+        }
+        if let Some(switcher) = switcher.as_mut() {
+            switcher.show(entries, selected);
+        }
+    });
+}
+
+pub fn hide() {
+    SWITCHER.with(|switcher| {
+        if let Some(switcher) = switcher.borrow().as_ref() {
+            switcher.overlay.hide();
+        }
+    });
+}
+
+pub fn close() {
+    SWITCHER.with(|switcher| *switcher.borrow_mut() = None);
+}
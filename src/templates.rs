@@ -0,0 +1,42 @@
+use crate::launch::{SlotSize, StartupApp};
+
+// Built-in layouts to start from, by name, with what each sets up
+pub const TEMPLATES: &[(&str, &str)] = &[
+    ("coding", "VS Code full width, with Windows Terminal at half width on the row below"),
+    ("research", "Edge and Notepad side by side"),
+    ("comms", "Teams and Outlook side by side"),
+];
+
+// Started through `start`, which finds apps by their registered name as the Run
+// dialog does, so they needn't be on the PATH. The window is matched by `exe`.
+fn app(name: &str, exe: &str, row: i32, slot: usize, size: SlotSize) -> StartupApp {
+    StartupApp {
+        command: "cmd.exe".to_string(),
+        args: ["/c", "start", "", name].map(String::from).to_vec(),
+        row,
+        slot,
+        size,
+        exe: Some(exe.to_string()),
+        title: None,
+    }
+}
+
+// A template's apps, with its first row at `row`
+pub fn get(name: &str, row: i32) -> Option<Vec<StartupApp>> {
+    let apps = match name.to_ascii_lowercase().as_str() {
+        "coding" => vec![
+            app("code", "Code.exe", row, 0, SlotSize::Full),
+            app("wt", "WindowsTerminal.exe", row + 1, 0, SlotSize::Half),
+        ],
+        "research" => vec![
+            app("msedge", "msedge.exe", row, 0, SlotSize::Half),
+            app("notepad", "Notepad.exe", row, 1, SlotSize::Half),
+        ],
+        "comms" => vec![
+            app("ms-teams", "ms-teams.exe", row, 0, SlotSize::Half),
+            app("outlook", "OUTLOOK.EXE", row, 1, SlotSize::Half),
+        ],
+        _ => return None,
+    };
+    Some(apps)
+}
@@ -0,0 +1,182 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::HWND,
+    System::SystemInformation::GetLocalTime,
+};
+
+use crate::window_info;
+
+// Focus blips shorter than this (alt-tab passes, flashing popups) are not logged
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackFormat {
+    Csv,
+    JsonLines,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeTrackingOptions {
+    pub path: PathBuf,
+    pub format: TrackFormat,
+    pub record_titles: bool,       // Titles can leak document names, so they can be dropped
+    pub managed_only: bool,        // Only log windows that live in the ribbon
+    pub excluded_exes: Vec<String>, // Exe names that are never logged (case-insensitive)
+}
+
+impl TimeTrackingOptions {
+    pub fn new(path: PathBuf) -> Self {
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("jsonl") => TrackFormat::JsonLines,
+            _ => TrackFormat::Csv,
+        };
+
+        Self {
+            path,
+            format,
+            record_titles: true,
+            managed_only: false,
+            excluded_exes: Vec::new(),
+        }
+    }
+}
+
+// A focused window interval that is still running
+struct FocusInterval {
+    hwnd: isize,
+    exe: String,
+    title: String,
+    started_at: String,
+    started: Instant,
+}
+
+// Logs how long each window held the foreground
+pub struct TimeTracker {
+    options: TimeTrackingOptions,
+    current: Option<FocusInterval>,
+}
+
+impl TimeTracker {
+    pub fn new(options: TimeTrackingOptions) -> Self {
+        Self {
+            options,
+            current: None,
+        }
+    }
+
+    // Called whenever the foreground window changes
+    pub fn on_focus(&mut self, hwnd: HWND, managed: bool) {
+        if self.current.as_ref().is_some_and(|c| c.hwnd == hwnd.0) {
+            return;
+        }
+
+        self.finish_current();
+
+        if hwnd.0 == 0 || (self.options.managed_only && !managed) {
+            return;
+        }
+
+        let exe = window_info::process_name(hwnd).unwrap_or_default();
+        if self.options.excluded_exes.iter().any(|e| e.eq_ignore_ascii_case(&exe)) {
+            return;
+        }
+
+        let title = if self.options.record_titles {
+            window_info::window_title(hwnd)
+        } else {
+            String::new()
+        };
+
+        self.current = Some(FocusInterval {
+            hwnd: hwnd.0,
+            exe,
+            title,
+            started_at: local_timestamp(),
+            started: Instant::now(),
+        });
+    }
+
+    // Close the running interval and append it to the log
+    pub fn finish_current(&mut self) {
+        let Some(interval) = self.current.take() else {
+            return;
+        };
+
+        let duration = interval.started.elapsed();
+        if duration < MIN_INTERVAL {
+            return;
+        }
+
+        if let Err(e) = self.write_record(&interval, &local_timestamp(), duration) {
+            println!("Warning: Failed to write time tracking log: {}", e);
+        }
+    }
+
+    fn write_record(&self, interval: &FocusInterval, ended_at: &str, duration: Duration) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.options.path)?;
+
+        let line = match self.options.format {
+            TrackFormat::Csv => {
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "start,end,duration_secs,exe,title")?;
+                }
+                format!(
+                    "{},{},{:.1},{},{}",
+                    interval.started_at,
+                    ended_at,
+                    duration.as_secs_f32(),
+                    csv_field(&interval.exe),
+                    csv_field(&interval.title),
+                )
+            }
+            TrackFormat::JsonLines => format!(
+                "{{\"start\":\"{}\",\"end\":\"{}\",\"duration_secs\":{:.1},\"exe\":\"{}\",\"title\":\"{}\"}}",
+                interval.started_at,
+                ended_at,
+                duration.as_secs_f32(),
+                json_escape(&interval.exe),
+                json_escape(&interval.title),
+            ),
+        };
+
+        writeln!(file, "{}", line)
+    }
+}
+
+fn local_timestamp() -> String {
+    let t = unsafe { GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        t.wYear, t.wMonth, t.wDay, t.wHour, t.wMinute, t.wSecond
+    )
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,155 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicIsize, AtomicU32, Ordering};
+use windows::{
+    core::*,
+    Win32::{
+        Foundation::*,
+        UI::Shell::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::{console, request_shutdown, WM_TILER_TRAY};
+
+const ICON_ID: u32 = 1;
+// Tooltips are cut off past this many characters, terminator included
+const TIP_LEN: usize = 128;
+const INFO_TITLE_LEN: usize = 64;
+const INFO_LEN: usize = 256;
+
+const MENU_OPEN_LOG: usize = 1;
+const MENU_QUIT: usize = 2;
+
+// Window the icon belongs to, or 0 while there is none
+static ICON_HWND: AtomicIsize = AtomicIsize::new(0);
+static STATUS: Mutex<String> = Mutex::new(String::new());
+static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+// A notification raised before the icon was added, as (title, text)
+static PENDING: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+fn icon_data(hwnd: HWND, status: &str) -> NOTIFYICONDATAW {
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: ICON_ID,
+        uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP,
+        uCallbackMessage: WM_TILER_TRAY,
+        hIcon: unsafe { LoadIconW(None, IDI_APPLICATION).unwrap_or_default() },
+        ..Default::default()
+    };
+    let tip: Vec<u16> = status.encode_utf16().take(TIP_LEN - 1).collect();
+    data.szTip[..tip.len()].copy_from_slice(&tip);
+    data
+}
+
+// The notification area icon stands in for the console: its tooltip shows what the
+// tiler is doing and its menu quits. Also called again when Explorer restarts.
+pub fn add(hwnd: HWND) {
+    let added = {
+        let mut status = STATUS.lock().unwrap();
+        if status.is_empty() {
+            *status = "Thymeline".to_string();
+        }
+        unsafe { Shell_NotifyIconW(NIM_ADD, &icon_data(hwnd, &status)) }.as_bool()
+    };
+    if !added {
+        println!("Warning: Failed to add the notification area icon");
+        return;
+    }
+    ICON_HWND.store(hwnd.0, Ordering::Relaxed);
+    if let Some((title, text)) = PENDING.lock().unwrap().take() {
+        notify(&title, &text);
+    }
+}
+
+// A warning balloon from the icon, for problems the user has to fix. Raised before the
+// icon exists, it waits for it.
+pub fn notify(title: &str, text: &str) {
+    let hwnd = ICON_HWND.load(Ordering::Relaxed);
+    if hwnd == 0 {
+        *PENDING.lock().unwrap() = Some((title.to_string(), text.to_string()));
+        return;
+    }
+    let mut data = icon_data(HWND(hwnd), &STATUS.lock().unwrap());
+    data.uFlags |= NIF_INFO;
+    data.dwInfoFlags = NIIF_WARNING;
+    let title: Vec<u16> = title.encode_utf16().take(INFO_TITLE_LEN - 1).collect();
+    data.szInfoTitle[..title.len()].copy_from_slice(&title);
+    let text: Vec<u16> = text.encode_utf16().take(INFO_LEN - 1).collect();
+    data.szInfo[..text.len()].copy_from_slice(&text);
+    unsafe {
+        Shell_NotifyIconW(NIM_MODIFY, &data);
+    }
+}
+
+// Only touches the icon when the text changed
+pub fn set_status(text: &str) {
+    let mut status = STATUS.lock().unwrap();
+    if *status == text {
+        return;
+    }
+    *status = text.to_string();
+    let hwnd = ICON_HWND.load(Ordering::Relaxed);
+    if hwnd != 0 {
+        unsafe {
+            Shell_NotifyIconW(NIM_MODIFY, &icon_data(HWND(hwnd), &status));
+        }
+    }
+}
+
+pub fn remove() {
+    let hwnd = ICON_HWND.swap(0, Ordering::Relaxed);
+    if hwnd != 0 {
+        unsafe {
+            Shell_NotifyIconW(NIM_DELETE, &icon_data(HWND(hwnd), ""));
+        }
+    }
+}
+
+// Broadcast to top-level windows once a restarted Explorer has a fresh taskbar
+pub fn taskbar_created() -> u32 {
+    match TASKBAR_CREATED.load(Ordering::Relaxed) {
+        0 => {
+            let id = unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) };
+            TASKBAR_CREATED.store(id, Ordering::Relaxed);
+            id
+        }
+        id => id,
+    }
+}
+
+// WM_TILER_TRAY, with the mouse message in lparam
+pub fn on_message(hwnd: HWND, lparam: LPARAM) {
+    if lparam.0 as u32 != WM_RBUTTONUP {
+        return;
+    }
+    unsafe {
+        let Ok(menu) = CreatePopupMenu() else {
+            return;
+        };
+        if console::log_path().is_some() {
+            AppendMenuW(menu, MF_STRING, MENU_OPEN_LOG, w!("Open log")).ok();
+        }
+        AppendMenuW(menu, MF_STRING, MENU_QUIT, w!("Quit Thymeline")).ok();
+
+        // The menu only closes on a click elsewhere if its owner is in the foreground
+        let mut cursor = POINT::default();
+        GetCursorPos(&mut cursor).ok();
+        SetForegroundWindow(hwnd);
+        let chosen = TrackPopupMenu(menu, TPM_RETURNCMD | TPM_RIGHTBUTTON, cursor.x, cursor.y, 0, hwnd, None);
+        PostMessageW(hwnd, WM_NULL, WPARAM(0), LPARAM(0)).ok();
+        DestroyMenu(menu).ok();
+
+        match chosen.0 as usize {
+            MENU_OPEN_LOG => {
+                if let Some(path) = console::log_path() {
+                    ShellExecuteW(hwnd, w!("open"), &HSTRING::from(path.to_string_lossy().into_owned()), PCWSTR::null(), PCWSTR::null(), SW_SHOWNORMAL);
+                }
+            }
+            MENU_QUIT => {
+                request_shutdown();
+            }
+            _ => {}
+        }
+    }
+}
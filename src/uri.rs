@@ -0,0 +1,167 @@
+use windows::{
+    core::*,
+    Win32::{Foundation::*, System::Registry::*},
+};
+
+use crate::cli::Subcommand;
+use crate::TilerCommand;
+
+// Links like thymeline://pan-right run one command through a short-lived client, so
+// macro pads and launchers that can only open a URL reach the running instance
+const SCHEME: &str = "thymeline";
+
+fn class_key() -> String {
+    format!(r"Software\Classes\{}", SCHEME)
+}
+
+fn wide(text: &str) -> Vec<u8> {
+    text.encode_utf16().chain(std::iter::once(0)).flat_map(u16::to_le_bytes).collect()
+}
+
+unsafe fn set_string(key: HKEY, name: PCWSTR, value: &str) -> Result<()> {
+    RegSetValueExW(key, name, 0, REG_SZ, Some(&wide(value))).ok()
+}
+
+// Opens `thymeline:` links with this binary, for the current user only
+pub fn register() -> Result<()> {
+    let exe = std::env::current_exe().map_err(|_| Error::from(E_FAIL))?;
+    let command = format!("\"{}\" uri \"%1\"", exe.display());
+    unsafe {
+        let mut key = HKEY::default();
+        RegCreateKeyExW(HKEY_CURRENT_USER, &HSTRING::from(class_key()), 0, None,
+            REG_OPTION_NON_VOLATILE, KEY_WRITE, None, &mut key, None).ok()?;
+        let described = set_string(key, PCWSTR::null(), "URL:Thymeline command")
+            .and_then(|_| set_string(key, w!("URL Protocol"), ""));
+        RegCloseKey(key);
+        described?;
+
+        let mut open = HKEY::default();
+        RegCreateKeyExW(HKEY_CURRENT_USER, &HSTRING::from(format!(r"{}\shell\open\command", class_key())), 0, None,
+            REG_OPTION_NON_VOLATILE, KEY_WRITE, None, &mut open, None).ok()?;
+        let set = set_string(open, PCWSTR::null(), &command);
+        RegCloseKey(open);
+        set
+    }
+}
+
+pub fn unregister() -> Result<()> {
+    unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, &HSTRING::from(class_key())).ok() }
+}
+
+// Links are percent-encoded, so a macro named "code review" arrives as code%20review
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// thymeline://<command> runs a command against the foreground window, and
+// thymeline://macro/<name> plays a saved macro. Any web page can open a link, so only
+// navigation commands are honoured, in macros too; nothing that launches, moves tiles
+// or changes settings. Browsers may add a trailing slash.
+pub fn parse(uri: &str) -> Option<Subcommand> {
+    let (scheme, rest) = uri.split_once(':')?;
+    if !scheme.eq_ignore_ascii_case(SCHEME) {
+        return None;
+    }
+    let path = rest.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_start_matches('/').trim_end_matches('/');
+    match path.split_once('/') {
+        Some((verb, name)) if verb.eq_ignore_ascii_case("macro") => Some(Subcommand::LinkMacro(decode(name))),
+        Some(_) => None,
+        None if path.is_empty() => None,
+        None => {
+            let name = decode(path).to_ascii_lowercase();
+            TilerCommand::from_name(&name)
+                .filter(|command| command.is_navigation())
+                .map(|_| Subcommand::Command { name, hwnd: None })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(name: &str) -> Option<Subcommand> {
+        Some(Subcommand::Command { name: name.to_string(), hwnd: None })
+    }
+
+    fn macro_link(name: &str) -> Option<Subcommand> {
+        Some(Subcommand::LinkMacro(name.to_string()))
+    }
+
+    #[test]
+    fn links() {
+        let cases = [
+            ("thymeline://pan-right", command("pan-right")),
+            ("THYMELINE://pan-right", command("pan-right")),
+            ("Thymeline:PAN-RIGHT", command("pan-right")),
+            ("thymeline://pan-right/", command("pan-right")),
+            ("thymeline://pan-right///", command("pan-right")),
+            ("thymeline://pan-right?from=deck", command("pan-right")),
+            ("thymeline://pan-right#top", command("pan-right")),
+            ("thymeline://pan-right/?x=1", command("pan-right")),
+            ("thymeline://pan%2Dright", command("pan-right")),
+            ("thymeline://macro/review", macro_link("review")),
+            ("thymeline://MACRO/review/", macro_link("review")),
+            ("thymeline://macro/code%20review", macro_link("code review")),
+            ("thymeline://macro/caf%C3%A9", macro_link("café")),
+            ("thymeline://macro/%E2%9C%93%20done", macro_link("✓ done")),
+            ("thymeline://macro/100%", macro_link("100%")),
+            ("thymeline://macro/50%2", macro_link("50%2")),
+            ("thymeline://macro/%zz", macro_link("%zz")),
+            ("thymeline://macro/%+1", macro_link("%+1")),
+            ("thymeline://macro/%C3", macro_link("\u{FFFD}")),
+            ("thymeline://macro/%é", macro_link("%é")),
+            ("thymeline://macro/review?x=1#y", macro_link("review")),
+        ];
+        for (uri, expected) in cases {
+            assert_eq!(parse(uri), expected, "{}", uri);
+        }
+    }
+
+    #[test]
+    fn rejected_links() {
+        for uri in [
+            "thymeline:",
+            "thymeline://",
+            "thymeline:///",
+            "thymeline://?pan-right",
+            "thymeline://#pan-right",
+            "thymeline://no-such-command",
+            "thymeline://pan-right/extra",
+            "thymeline://macro",
+            "https://pan-right",
+            "thymelinex://pan-right",
+            "pan-right",
+            // Real commands, but not navigation ones
+            "thymeline://remove-window",
+            "thymeline://toggle-dry-run",
+            "thymeline://copy-layout",
+            "thymeline://import-layout",
+            "thymeline://record-macro",
+            "thymeline://remove%2Dwindow",
+        ] {
+            assert_eq!(parse(uri), None, "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::*,
+        System::Threading::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+const HUNG_TIMEOUT_MS: u32 = 250;
+
+// Current title text of a window
+pub fn window_title(hwnd: HWND) -> String {
+    unsafe {
+        let mut title = [0u16; 512];
+        let len = GetWindowTextW(hwnd, &mut title);
+        String::from_utf16_lossy(&title[..len.max(0) as usize])
+    }
+}
+
+unsafe extern "system" fn collect_window(hwnd: HWND, data: LPARAM) -> BOOL {
+    let windows = &mut *(data.0 as *mut Vec<HWND>);
+    windows.push(hwnd);
+    BOOL::from(true)
+}
+
+// Every top-level window, hidden ones included
+pub fn top_level_windows() -> Vec<HWND> {
+    let mut windows: Vec<HWND> = Vec::new();
+    unsafe {
+        EnumWindows(Some(collect_window), LPARAM(&mut windows as *mut _ as isize)).ok();
+    }
+    windows
+}
+
+// Window class name, as GetClassNameW reports it
+pub fn class_name(hwnd: HWND) -> String {
+    unsafe {
+        let mut class = [0u16; 256];
+        let len = GetClassNameW(hwnd, &mut class);
+        String::from_utf16_lossy(&class[..len.max(0) as usize])
+    }
+}
+
+// Not answering messages: a SendMessage to it would block
+pub fn is_hung(hwnd: HWND) -> bool {
+    unsafe {
+        SendMessageTimeoutW(
+            hwnd,
+            WM_NULL,
+            WPARAM(0),
+            LPARAM(0),
+            SMTO_ABORTIFHUNG | SMTO_BLOCK,
+            HUNG_TIMEOUT_MS,
+            None,
+        ).0 == 0
+    }
+}
+
+// Full image path of the process that owns a window
+pub fn process_path(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut size,
+        );
+        CloseHandle(process).ok();
+        result.ok()?;
+
+        Some(String::from_utf16_lossy(&buffer[..size as usize]))
+    }
+}
+
+// Executable file name (e.g. "firefox.exe") of the process that owns a window
+pub fn process_name(hwnd: HWND) -> Option<String> {
+    process_path(hwnd).map(|path| {
+        path.rsplit('\\').next().unwrap_or(&path).to_string()
+    })
+}
+
+const SETTLE_POLL: Duration = Duration::from_millis(50);
+
+// Block until the window has kept the same rect for `stable_for`, giving up after `timeout`.
+// Returns false if the window went away meanwhile.
+pub fn wait_until_settled(hwnd: HWND, stable_for: Duration, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut last = RECT::default();
+    let mut stable_since = Instant::now();
+
+    loop {
+        let mut rect = RECT::default();
+        unsafe {
+            if !IsWindow(hwnd).as_bool() || GetWindowRect(hwnd, &mut rect).is_err() {
+                return false;
+            }
+        }
+
+        if rect != last {
+            last = rect;
+            stable_since = Instant::now();
+        } else if stable_since.elapsed() >= stable_for {
+            return true;
+        }
+
+        if Instant::now() >= deadline {
+            return true;
+        }
+        thread::sleep(SETTLE_POLL);
+    }
+}
+
+// Frame classes browsers and Electron apps use for their top-level windows
+const CHROMIUM_FRAME_CLASS: &str = "Chrome_WidgetWin_1";
+const GECKO_FRAME_CLASS: &str = "MozillaWindowClass";
+
+// Gecko-based executables; Chromium's frame class is shared by every Electron app
+const GECKO_EXES: &[&str] = &["firefox.exe", "thunderbird.exe", "librewolf.exe", "waterfox.exe", "floorp.exe", "zen.exe"];
+
+// Smaller than this is a tooltip, toast or widget rather than an app window
+const MIN_FRAME_WIDTH: i32 = 200;
+const MIN_FRAME_HEIGHT: i32 = 150;
+
+// What the browser check looks at, gathered up front so the rules stay plain
+pub struct WindowSignature<'a> {
+    pub class: &'a str,
+    pub exe: Option<&'a str>,
+    pub ex_style: WINDOW_EX_STYLE,
+    pub owned: bool,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl WindowSignature<'_> {
+    // Real top-level frame of a browser or Electron app, as opposed to its popups and widgets
+    pub fn is_browser_frame(&self) -> bool {
+        let gecko = self.class == GECKO_FRAME_CLASS
+            && self.exe.is_some_and(|exe| GECKO_EXES.iter().any(|known| exe.eq_ignore_ascii_case(known)));
+        let chromium = self.class == CHROMIUM_FRAME_CLASS && self.exe.is_some();
+        if !gecko && !chromium {
+            return false;
+        }
+
+        // Popups, menus and notification widgets share the frame class but never take activation
+        if (self.ex_style & WS_EX_NOACTIVATE).0 != 0 || (self.ex_style & WS_EX_TOOLWINDOW).0 != 0 {
+            return false;
+        }
+
+        !self.owned && self.width >= MIN_FRAME_WIDTH && self.height >= MIN_FRAME_HEIGHT
+    }
+}
+
+pub fn is_browser_frame_class(class: &str) -> bool {
+    class == CHROMIUM_FRAME_CLASS || class == GECKO_FRAME_CLASS
+}
+
+pub fn is_browser_frame(hwnd: HWND, class: &str) -> bool {
+    if !is_browser_frame_class(class) {
+        return false;
+    }
+
+    unsafe {
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).ok();
+        let exe = process_name(hwnd);
+
+        WindowSignature {
+            class,
+            exe: exe.as_deref(),
+            ex_style: WINDOW_EX_STYLE(GetWindowLongW(hwnd, GWL_EXSTYLE) as u32),
+            owned: GetWindow(hwnd, GW_OWNER).0 != 0,
+            width: rect.right - rect.left,
+            height: rect.bottom - rect.top,
+        }
+        .is_browser_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame<'a>(class: &'a str, exe: Option<&'a str>) -> WindowSignature<'a> {
+        WindowSignature { class, exe, ex_style: WINDOW_EX_STYLE(0), owned: false, width: 1280, height: 800 }
+    }
+
+    #[test]
+    fn browser_frame_signatures() {
+        let cases = [
+            // Chromium browsers, and Electron apps, which share the frame class
+            ("Chrome_WidgetWin_1", Some("chrome.exe"), true),
+            ("Chrome_WidgetWin_1", Some("msedge.exe"), true),
+            ("Chrome_WidgetWin_1", Some("Code.exe"), true),
+            ("Chrome_WidgetWin_1", Some("Slack.exe"), true),
+            ("Chrome_WidgetWin_1", Some("Discord.exe"), true),
+            ("Chrome_WidgetWin_1", None, false),
+            // Gecko, only from known executables
+            ("MozillaWindowClass", Some("firefox.exe"), true),
+            ("MozillaWindowClass", Some("FIREFOX.EXE"), true),
+            ("MozillaWindowClass", Some("thunderbird.exe"), true),
+            ("MozillaWindowClass", Some("someapp.exe"), false),
+            ("MozillaWindowClass", None, false),
+            // Neither family
+            ("Chrome_WidgetWin_0", Some("chrome.exe"), false),
+            ("Notepad", Some("notepad.exe"), false),
+            ("CASCADIA_HOSTING_WINDOW_CLASS", Some("WindowsTerminal.exe"), false),
+        ];
+        for (class, exe, expected) in cases {
+            assert_eq!(frame(class, exe).is_browser_frame(), expected, "{} / {:?}", class, exe);
+        }
+    }
+
+    #[test]
+    fn browser_popups_are_not_frames() {
+        let popups = [
+            WindowSignature { ex_style: WS_EX_NOACTIVATE, ..frame("Chrome_WidgetWin_1", Some("chrome.exe")) },
+            WindowSignature { ex_style: WS_EX_TOOLWINDOW, ..frame("MozillaWindowClass", Some("firefox.exe")) },
+            WindowSignature { owned: true, ..frame("Chrome_WidgetWin_1", Some("Code.exe")) },
+            WindowSignature { width: MIN_FRAME_WIDTH - 1, ..frame("Chrome_WidgetWin_1", Some("msedge.exe")) },
+            WindowSignature { height: MIN_FRAME_HEIGHT - 1, ..frame("MozillaWindowClass", Some("firefox.exe")) },
+        ];
+        for popup in popups {
+            assert!(!popup.is_browser_frame(), "{} / {:?}", popup.class, popup.exe);
+        }
+    }
+
+    #[test]
+    fn browser_frame_classes() {
+        assert!(is_browser_frame_class("Chrome_WidgetWin_1"));
+        assert!(is_browser_frame_class("MozillaWindowClass"));
+        assert!(!is_browser_frame_class("Chrome_WidgetWin_0"));
+        assert!(!is_browser_frame_class("ApplicationFrameWindow"));
+    }
+}